@@ -0,0 +1,68 @@
+//! Measures the overhead of this crate on top of the bus, using a [`NullBus`] that does nothing.
+//!
+//! Run with `cargo bench`. The numbers are per call: `select_mask` is one control register write
+//! under one lock, `read_mask` adds a read, `cached_mask` doesn't touch the bus at all,
+//! and the `SubBus` numbers include the select of every call.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use embedded_hal::i2c::I2c;
+use pca9548a::{test_util::NullBus, Pca9548a, BASE_ADDRESS};
+
+const ITERATIONS: u32 = 1_000_000;
+
+/// Run `f` [`ITERATIONS`] times (after a warm-up) and print the mean time per call.
+fn bench(name: &str, mut f: impl FnMut()) {
+    for _ in 0..ITERATIONS / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_call = start.elapsed().as_secs_f64() * 1e9 / f64::from(ITERATIONS);
+    println!("{name:<32} {per_call:>8.1} ns");
+}
+
+fn main() {
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(NullBus, BASE_ADDRESS);
+
+    bench("raw bus write", || {
+        NullBus.write(black_box(0x42), black_box(&[1])).unwrap();
+    });
+    bench("select_mask", || {
+        drop(pca.select_mask(black_box(0b101)).unwrap());
+    });
+    bench("read_mask (uncached)", || {
+        black_box(pca.read_mask().unwrap());
+    });
+    bench("cached_mask", || {
+        black_box(pca.cached_mask());
+    });
+
+    let mut subbus = pca.single_subbus(3);
+    bench("SubBus::write", || {
+        subbus.write(black_box(0x42), black_box(&[1])).unwrap();
+    });
+    bench("SubBus::write_read", || {
+        let mut buf = [0; 2];
+        subbus
+            .write_read(black_box(0x42), black_box(&[1]), &mut buf)
+            .unwrap();
+        black_box(buf);
+    });
+    bench("SubBus::transaction (2 ops)", || {
+        let mut buf = [0; 2];
+        subbus
+            .transaction(
+                black_box(0x42),
+                &mut [
+                    embedded_hal::i2c::Operation::Write(&[1]),
+                    embedded_hal::i2c::Operation::Read(&mut buf),
+                ],
+            )
+            .unwrap();
+        black_box(buf);
+    });
+}
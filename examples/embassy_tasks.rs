@@ -0,0 +1,44 @@
+//! Drive devices on two channels from two tasks, each with its own [`StaticSubBus`].
+//!
+//! On embassy, the task functions would be annotated with `#[embassy_executor::task]` and started with
+//! `spawner.spawn(sensor_task(pca.static_subbus(Channel::C0)))`; tasks can only take `'static` arguments,
+//! which is what [`Pca9548a::static_subbus`] provides. To keep the example runnable on the host,
+//! the device is leaked instead of put into a `static_cell::StaticCell` (see `examples/static_cell.rs`),
+//! the bus is a mock and both tasks are joined on the current thread.
+
+use embassy_futures::{block_on, join::join};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embedded_hal_async::i2c::I2c;
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+use pca9548a::{Channel, Pca9548a, StaticSubBus, BASE_ADDRESS};
+
+type Bus = Mutex<CriticalSectionRawMutex, Mock>;
+
+const SENSOR_ADDRESS: u8 = 0x48;
+
+/// Both tasks talk to a sensor with the same address, on different channels.
+async fn sensor_task(mut bus: StaticSubBus<Bus>) -> u8 {
+    let mut value = [0];
+    bus.write_read(SENSOR_ADDRESS, &[0x00], &mut value)
+        .await
+        .expect("read sensor");
+    value[0]
+}
+
+fn main() {
+    let mut i2c = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write_read(SENSOR_ADDRESS, vec![0x00], vec![21]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write_read(SENSOR_ADDRESS, vec![0x00], vec![42]),
+    ]);
+    let pca: &'static Pca9548a<Bus> = Box::leak(Box::new(Pca9548a::new(i2c.clone(), BASE_ADDRESS)));
+
+    let (first, second) = block_on(join(
+        sensor_task(pca.static_subbus(Channel::C0)),
+        sensor_task(pca.static_subbus(Channel::C1)),
+    ));
+    println!("sensor on channel 0: {first}, sensor on channel 1: {second}");
+
+    i2c.done();
+}
@@ -0,0 +1,41 @@
+//! Put the mux into a `static_cell::StaticCell` and drive a channel from a `'static` task.
+//!
+//! On embassy, `main` would be the `#[embassy_executor::main]` function and `sensor_task` an
+//! `#[embassy_executor::task]` started with `spawner.spawn(sensor_task(pca.static_subbus(Channel::C2)))`.
+//! To keep the example runnable on the host, the bus is a mock and the task is run with `block_on`.
+//!
+//! Run it with `cargo run --example static_cell --features embassy,static-cell`.
+
+use embassy_futures::block_on;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embedded_hal_async::i2c::I2c;
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+use pca9548a::{Channel, Pca9548a, StaticSubBus, BASE_ADDRESS};
+use static_cell::StaticCell;
+
+type Bus = Mutex<CriticalSectionRawMutex, Mock>;
+
+const SENSOR_ADDRESS: u8 = 0x48;
+
+static PCA: StaticCell<Pca9548a<Bus>> = StaticCell::new();
+
+async fn sensor_task(mut bus: StaticSubBus<Bus>) -> u8 {
+    let mut value = [0];
+    bus.write_read(SENSOR_ADDRESS, &[0x00], &mut value)
+        .await
+        .expect("read sensor");
+    value[0]
+}
+
+fn main() {
+    let mut i2c = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write_read(SENSOR_ADDRESS, vec![0x00], vec![21]),
+    ]);
+    let pca = Pca9548a::new_static(&PCA, i2c.clone(), BASE_ADDRESS);
+
+    let value = block_on(sensor_task(pca.static_subbus(Channel::C2)));
+    println!("sensor on channel 2: {value}");
+
+    i2c.done();
+}
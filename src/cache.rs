@@ -0,0 +1,28 @@
+use core::sync::atomic::{AtomicU16, Ordering};
+
+/// Marker for an unknown mask; outside the range of `u8`.
+const UNKNOWN: u16 = 0x100;
+
+/// The crate's belief about the value of the control register.
+///
+/// Only updated while the bus is locked (or by `assume_mask`, which doesn't touch the bus),
+/// so relaxed ordering is sufficient.
+pub(crate) struct MaskCache(AtomicU16);
+
+impl MaskCache {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicU16::new(UNKNOWN))
+    }
+
+    pub(crate) fn get(&self) -> Option<u8> {
+        u8::try_from(self.0.load(Ordering::Relaxed)).ok()
+    }
+
+    pub(crate) fn set(&self, mask: u8) {
+        self.0.store(mask as u16, Ordering::Relaxed);
+    }
+
+    pub(crate) fn invalidate(&self) {
+        self.0.store(UNKNOWN, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,123 @@
+use core::ops::{Deref, DerefMut};
+
+use crate::{
+    AsyncMutex, BusError, ControlAddress, Pca9548a, SelectObserver, SelectionCoordinator,
+    NO_CHANNELS,
+};
+
+/// A bus lock that deselects all channels when it is dropped.
+///
+/// Returned by [`Pca9548a::select_mask_cancel_safe_async`](crate::Pca9548a::select_mask_cancel_safe_async).
+///
+/// If a future holding this guard across await points is cancelled (i.e. dropped before it completes),
+/// the guard is dropped with it. Because Rust has no async `Drop`, the deselect is performed
+/// with a *blocking* write of an empty mask to the control register; this is why the bus must also implement
+/// [`embedded_hal::i2c::I2c`]. The deselect is best-effort: its error is only recorded by the error latch
+/// (see [`Pca9548a::with_error_latch`]).
+///
+/// Cancellation-safety semantics:
+/// * Dropping the guard (by cancellation or normally) leaves the mux with no channel selected,
+///   unless the write fails.
+/// * The deselect happens while the bus lock is still held, so no other task can observe
+///   the stale selection.
+/// * Use [`Self::keep_selection`] to release the lock without deselecting.
+/// * Use [`Self::close`] to deselect with an async write and get its error; dropping the guard
+///   without calling it falls back to the silent blocking deselect.
+pub struct CancellationGuard<'a, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c<Address> + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Address: ControlAddress,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+    bus: Option<Guard>,
+}
+
+impl<'a, Mutex, Coordinator, Pins, Observer, Address, Guard>
+    CancellationGuard<'a, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c<Address> + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Address: ControlAddress,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    pub(crate) fn new(
+        pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+        bus: Guard,
+    ) -> Self {
+        Self {
+            pca,
+            bus: Some(bus),
+        }
+    }
+
+    /// Return the inner bus lock without deselecting the channels.
+    pub fn keep_selection(mut self) -> Guard {
+        self.bus.take().expect("bus lock is only taken on drop")
+    }
+
+    /// Deselect all channels with an async write and release the lock.
+    ///
+    /// Unlike dropping the guard, the error of the deselect is returned.
+    pub async fn close(mut self) -> Result<(), BusError<Mutex>> {
+        let mut bus = self.bus.take().expect("bus lock is only taken on drop");
+        self.pca
+            .write_mask_exact_on_async(&mut *bus, NO_CHANNELS)
+            .await
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> Deref
+    for CancellationGuard<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c<Address> + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Address: ControlAddress,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    type Target = Mutex::Bus;
+
+    fn deref(&self) -> &Self::Target {
+        self.bus.as_ref().expect("bus lock is only taken on drop")
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> DerefMut
+    for CancellationGuard<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c<Address> + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Address: ControlAddress,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.bus.as_mut().expect("bus lock is only taken on drop")
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> Drop
+    for CancellationGuard<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c<Address> + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Address: ControlAddress,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    fn drop(&mut self) {
+        if let Some(bus) = self.bus.as_mut() {
+            let _: Result<(), BusError<Mutex>> =
+                self.pca.write_mask_exact_on(&mut **bus, NO_CHANNELS);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{ChannelMask, CHANNELS};
+#[cfg(any(feature = "sync", feature = "async"))]
+use crate::{Error, Pca9548a};
+
+/// A valid channel id, in range 0..=7.
+///
+/// Methods taking a `Channel` (e.g. [`Pca9548a::channel_subbus`]) can't be passed an invalid id,
+/// so unlike their `u8` counterparts they never panic.
+///
+/// ```
+/// use pca9548a::{Channel, InvalidChannel};
+///
+/// assert_eq!(Channel::new(3), Some(Channel::C3));
+/// assert_eq!(Channel::new(8), None);
+/// assert_eq!(Channel::C5.id(), 5);
+///
+/// // E.g. for a channel number read from a config file
+/// assert_eq!(Channel::try_from(3), Ok(Channel::C3));
+/// let invalid: Result<Channel, _> = 8.try_into();
+/// assert_eq!(invalid, Err(InvalidChannel(8)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Channel(u8);
+
+impl Channel {
+    /// Channel 0
+    pub const C0: Self = Self(0);
+    /// Channel 1
+    pub const C1: Self = Self(1);
+    /// Channel 2
+    pub const C2: Self = Self(2);
+    /// Channel 3
+    pub const C3: Self = Self(3);
+    /// Channel 4
+    pub const C4: Self = Self(4);
+    /// Channel 5
+    pub const C5: Self = Self(5);
+    /// Channel 6
+    pub const C6: Self = Self(6);
+    /// Channel 7
+    pub const C7: Self = Self(7);
+
+    /// Create a channel, or `None` if `id` is out of range.
+    pub const fn new(id: u8) -> Option<Self> {
+        if id < CHANNELS as u8 {
+            Some(Self(id))
+        } else {
+            None
+        }
+    }
+
+    /// Get the id of this channel.
+    pub const fn id(self) -> u8 {
+        self.0
+    }
+
+    /// Get the mask with only this channel enabled.
+    pub const fn mask(self) -> ChannelMask {
+        ChannelMask::from_bits(1 << self.0)
+    }
+}
+
+impl From<Channel> for u8 {
+    fn from(channel: Channel) -> Self {
+        channel.0
+    }
+}
+
+impl TryFrom<u8> for Channel {
+    type Error = InvalidChannel;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        Self::new(id).ok_or(InvalidChannel(id))
+    }
+}
+
+impl From<Channel> for ChannelMask {
+    fn from(channel: Channel) -> Self {
+        channel.mask()
+    }
+}
+
+impl<const N: usize> From<[Channel; N]> for ChannelMask {
+    fn from(channels: [Channel; N]) -> Self {
+        channels.into_iter().collect()
+    }
+}
+
+impl From<&[Channel]> for ChannelMask {
+    fn from(channels: &[Channel]) -> Self {
+        channels.iter().copied().collect()
+    }
+}
+
+impl FromIterator<Channel> for ChannelMask {
+    fn from_iter<I: IntoIterator<Item = Channel>>(channels: I) -> Self {
+        Self::from_bits(
+            channels
+                .into_iter()
+                .fold(0, |mask, channel| mask | 1 << channel.0),
+        )
+    }
+}
+
+/// A channel id that is out of range (see [`CHANNELS`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidChannel(pub u8);
+
+impl core::fmt::Display for InvalidChannel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid channel id {}", self.0)
+    }
+}
+
+impl core::error::Error for InvalidChannel {}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Turn `id` into a [`Channel`] of this device, i.e. one within its channel count
+    /// (see [`Self::with_channel_count`]).
+    pub(crate) fn device_channel(&self, id: u8) -> Result<Channel, InvalidChannel> {
+        Channel::try_from(id)
+            .ok()
+            .filter(|channel| self.mask_policy.valid() & channel.mask().bits() != 0)
+            .ok_or(InvalidChannel(id))
+    }
+
+    /// Check a channel id passed to a fallible method.
+    ///
+    /// Panics on an invalid id. With the no-panic feature, an error is returned instead.
+    #[track_caller]
+    pub(crate) fn check_channel<E, Bus>(&self, id: u8) -> Result<(), Error<E, Bus>> {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(id < CHANNELS as u8);
+        if id < CHANNELS as u8 {
+            Ok(())
+        } else {
+            Err(self.last_error.invalid_channel(id))
+        }
+    }
+
+    /// Check all channel ids passed to a fallible method, before any of them is used.
+    ///
+    /// Panics on the first invalid id. With the no-panic feature, an error is returned for it instead.
+    #[track_caller]
+    pub(crate) fn check_channels<E, Bus>(&self, ids: &[u8]) -> Result<(), Error<E, Bus>> {
+        for &id in ids {
+            self.check_channel(id)?;
+        }
+        Ok(())
+    }
+
+    /// Check that the register and value buffers of [`Self::read_registers`] have the same length.
+    pub(crate) fn check_length<E, Bus>(
+        &self,
+        regs: &[u8],
+        out: &[u8],
+    ) -> Result<(), Error<E, Bus>> {
+        if regs.len() == out.len() {
+            Ok(())
+        } else {
+            Err(self.last_error.invalid_length(regs.len(), out.len()))
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use core::ops::{Deref, DerefMut};
+
+use crate::{
+    ControlAddress, MutexBase, Pca9548a, SelectObserver, SelectionCoordinator, NO_CHANNELS,
+};
+
+/// The lock returned by the sync selects with the `deselect-on-drop` feature.
+///
+/// It deselects all channels when it is dropped, including the sticky ones (see [`Pca9548a::set_sticky_channels`]);
+/// errors of that write are ignored.
+pub(crate) struct DeselectOnDrop<'a, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: MutexBase,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Address: ControlAddress,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    pub(crate) pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+    pub(crate) bus: Guard,
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> Deref
+    for DeselectOnDrop<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: MutexBase,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Address: ControlAddress,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    type Target = Mutex::Bus;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bus
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> DerefMut
+    for DeselectOnDrop<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: MutexBase,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Address: ControlAddress,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.bus
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> Drop
+    for DeselectOnDrop<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: MutexBase,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Address: ControlAddress,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    fn drop(&mut self) {
+        let _ = self.pca.write_mask_exact(&mut self.bus, NO_CHANNELS);
+    }
+}
@@ -0,0 +1,47 @@
+use core::future::Future;
+use core::ops::DerefMut;
+
+use embassy_sync::{
+    blocking_mutex::raw::RawMutex,
+    mutex::{Mutex, MutexGuard},
+};
+
+use crate::{race, AsyncMutex, AsyncMutexMap, MutexBase};
+
+impl<M: RawMutex, T> MutexBase for Mutex<M, T> {
+    type Bus = T;
+    type Error = core::convert::Infallible;
+
+    fn new(v: Self::Bus) -> Self {
+        Self::new(v)
+    }
+
+    fn into_inner(self) -> Self::Bus {
+        self.into_inner()
+    }
+}
+
+/// Locking never fails; the returned `MutexGuard` borrows the mutex and can be held across await points.
+impl<M: RawMutex, T> AsyncMutex for Mutex<M, T> {
+    async fn lock(&self) -> Result<impl DerefMut<Target = Self::Bus>, Self::Error> {
+        Ok(self.lock().await)
+    }
+
+    /// Races the lock against `timeout`; a lock that is given up on is not acquired later.
+    async fn lock_timeout(
+        &self,
+        timeout: impl Future<Output = ()>,
+    ) -> Option<Result<impl DerefMut<Target = Self::Bus>, Self::Error>> {
+        race(self.lock(), timeout).await.map(Ok)
+    }
+}
+
+/// The returned guard is a `MappedMutexGuard`.
+impl<M: RawMutex, T> AsyncMutexMap for Mutex<M, T> {
+    async fn lock_map<U>(
+        &self,
+        f: impl FnOnce(&mut Self::Bus) -> &mut U,
+    ) -> Result<impl DerefMut<Target = U>, Self::Error> {
+        Ok(MutexGuard::map(self.lock().await, f))
+    }
+}
@@ -0,0 +1,249 @@
+use core::cell::UnsafeCell;
+use core::future::{poll_fn, Future};
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use core::task::{Poll, Waker};
+
+use crate::{race, AsyncMutex, MutexBase};
+
+/// An [`AsyncMutex`] wrapper that hands out the lock in FIFO order.
+///
+/// Async mutexes like `embassy_sync::mutex::Mutex` don't guarantee an order in which waiting tasks
+/// acquire the lock, so under heavy contention a low-priority task may starve.
+/// `FairMutex` puts a ticket queue in front of the wrapped mutex: every [`AsyncMutex::lock`] draws a ticket
+/// and the tickets are served in the order they were drawn. Use it in place of the wrapped mutex:
+/// `Pca9548a::<FairMutex<embassy_sync::mutex::Mutex<_, _>>>::new(..)`.
+///
+/// A waiting `lock` future that is dropped (e.g. by a timeout) gives up its place in the queue.
+///
+/// `N` is the length of the queue, a power of two up to 32 (checked at compile time). Every place stores the waker
+/// of its task, so only the task whose turn it is gets woken.
+///
+/// *Note:* Once `N` tasks are queued, further tasks are polled again on every executor pass until a place frees up,
+/// and they don't keep the order among each other. Pick `N` at least as large as the number of contending tasks.
+pub struct FairMutex<M, const N: usize = 32> {
+    inner: M,
+    next_ticket: AtomicU32,
+    now_serving: AtomicU32,
+    cancelled: AtomicU32,
+    wakers: [AtomicWaker; N],
+}
+
+impl<M, const N: usize> FairMutex<M, N> {
+    /// Rejects queue lengths whose places can't be told apart by `ticket % N` and a bit of `cancelled`.
+    const VALID_LENGTH: () = assert!(
+        N.is_power_of_two() && N <= u32::BITS as usize,
+        "the queue length of a `FairMutex` must be a power of two up to 32"
+    );
+
+    fn slot(ticket: u32) -> usize {
+        ticket as usize % N
+    }
+
+    /// Draw the next ticket, unless all `N` places are taken.
+    fn draw(&self) -> Option<Ticket<'_, M, N>> {
+        self.next_ticket
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |next| {
+                let queued = next.wrapping_sub(self.now_serving.load(Ordering::SeqCst));
+                (queued < N as u32).then(|| next.wrapping_add(1))
+            })
+            .ok()
+            .map(|ticket| Ticket {
+                mutex: self,
+                ticket,
+            })
+    }
+
+    /// Hand the lock to the ticket after `ticket`, skipping cancelled tickets, and wake its task.
+    fn advance(&self, mut ticket: u32) {
+        loop {
+            ticket = ticket.wrapping_add(1);
+            self.now_serving.store(ticket, Ordering::SeqCst);
+            // Whoever clears the bit of a cancelled ticket also advances past it.
+            let bit = 1 << Self::slot(ticket);
+            if self.cancelled.fetch_and(!bit, Ordering::SeqCst) & bit == 0 {
+                break;
+            }
+        }
+        self.wakers[Self::slot(ticket)].wake();
+    }
+}
+
+impl<M: MutexBase, const N: usize> MutexBase for FairMutex<M, N> {
+    type Bus = M::Bus;
+    type Error = M::Error;
+
+    fn new(v: Self::Bus) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID_LENGTH;
+        Self {
+            inner: M::new(v),
+            next_ticket: AtomicU32::new(0),
+            now_serving: AtomicU32::new(0),
+            cancelled: AtomicU32::new(0),
+            wakers: core::array::from_fn(|_| AtomicWaker::new()),
+        }
+    }
+
+    fn into_inner(self) -> Self::Bus {
+        self.inner.into_inner()
+    }
+}
+
+impl<M: AsyncMutex, const N: usize> AsyncMutex for FairMutex<M, N> {
+    async fn lock(&self) -> Result<impl DerefMut<Target = Self::Bus>, Self::Error> {
+        let ticket = poll_fn(|cx| match self.draw() {
+            Some(ticket) => Poll::Ready(ticket),
+            None => {
+                // The queue is full and nobody wakes us when a place frees up.
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await;
+        poll_fn(|cx| {
+            // Register before checking, so a concurrent `advance` can't be missed.
+            self.wakers[Self::slot(ticket.ticket)].register(cx.waker());
+            if ticket.is_served() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+        let guard = self.inner.lock().await?;
+        Ok(FairGuard { guard, ticket })
+    }
+
+    /// Races the lock against `timeout`; giving up drops the ticket, so the queue moves on.
+    async fn lock_timeout(
+        &self,
+        timeout: impl Future<Output = ()>,
+    ) -> Option<Result<impl DerefMut<Target = Self::Bus>, Self::Error>> {
+        race(self.lock(), timeout).await
+    }
+}
+
+/// A place in the queue of a [`FairMutex`]; dropping it releases or cancels the place.
+struct Ticket<'a, M, const N: usize> {
+    mutex: &'a FairMutex<M, N>,
+    ticket: u32,
+}
+
+impl<M, const N: usize> Ticket<'_, M, N> {
+    fn is_served(&self) -> bool {
+        self.mutex.now_serving.load(Ordering::SeqCst) == self.ticket
+    }
+}
+
+impl<M, const N: usize> Drop for Ticket<'_, M, N> {
+    fn drop(&mut self) {
+        // Only the holder of the served ticket advances past it, so this can't change concurrently.
+        if self.is_served() {
+            self.mutex.advance(self.ticket);
+            return;
+        }
+        let bit = 1 << FairMutex::<M, N>::slot(self.ticket);
+        self.mutex.cancelled.fetch_or(bit, Ordering::SeqCst);
+        // It may have become our turn in the meantime; then the one clearing the bit advances.
+        if self.is_served() && self.mutex.cancelled.fetch_and(!bit, Ordering::SeqCst) & bit != 0 {
+            self.mutex.advance(self.ticket);
+        }
+    }
+}
+
+/// The lock of a [`FairMutex`]. The wrapped guard is released before the next ticket is served.
+struct FairGuard<'a, M, G, const N: usize> {
+    guard: G,
+    #[allow(dead_code)]
+    ticket: Ticket<'a, M, N>,
+}
+
+impl<M, G: Deref, const N: usize> Deref for FairGuard<'_, M, G, N> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<M, G: DerefMut, const N: usize> DerefMut for FairGuard<'_, M, G, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+/// The waker of one place in the queue of a [`FairMutex`].
+///
+/// `register` and `wake` may race; `state` works as a small lock around `waker`, and whoever finds the other
+/// one holding it leaves the wake-up to them.
+struct AtomicWaker {
+    state: AtomicU8,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+impl AtomicWaker {
+    const IDLE: u8 = 0;
+    const REGISTERING: u8 = 1;
+    const WAKING: u8 = 2;
+
+    fn new() -> Self {
+        Self {
+            state: AtomicU8::new(Self::IDLE),
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    fn register(&self, waker: &Waker) {
+        match self.state.compare_exchange(
+            Self::IDLE,
+            Self::REGISTERING,
+            Ordering::Acquire,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => {
+                // SAFETY: `REGISTERING` is set, so neither `wake` nor another `register` touches the cell.
+                let slot = unsafe { &mut *self.waker.get() };
+                if !slot.as_ref().is_some_and(|old| old.will_wake(waker)) {
+                    *slot = Some(waker.clone());
+                }
+                if self
+                    .state
+                    .compare_exchange(
+                        Self::REGISTERING,
+                        Self::IDLE,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_err()
+                {
+                    // `wake` was called meanwhile and left the wake-up to us. `REGISTERING` is still set.
+                    // SAFETY: as above.
+                    let waker = unsafe { &mut *self.waker.get() }.take();
+                    self.state.store(Self::IDLE, Ordering::Release);
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                }
+            }
+            // A wake-up is in progress and may have taken the old waker, so wake the new one directly.
+            Err(Self::WAKING) => waker.wake_by_ref(),
+            // Another task registers concurrently; places aren't shared, so this only happens on misuse.
+            Err(_) => {}
+        }
+    }
+
+    fn wake(&self) {
+        if self.state.fetch_or(Self::WAKING, Ordering::AcqRel) == Self::IDLE {
+            // SAFETY: `WAKING` is set, so `register` doesn't touch the cell until it is cleared.
+            let waker = unsafe { &mut *self.waker.get() }.take();
+            self.state.fetch_and(!Self::WAKING, Ordering::Release);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// SAFETY: the cell is only accessed while holding `state`, see `register` and `wake`.
+unsafe impl Sync for AtomicWaker {}
@@ -0,0 +1,664 @@
+#[cfg(feature = "async")]
+use core::future::Future;
+use core::ops::DerefMut;
+
+use embedded_hal::i2c::ErrorType;
+
+#[cfg(feature = "async")]
+use crate::AsyncMutex;
+#[cfg(feature = "sync")]
+use crate::SyncMutex;
+use crate::{
+    BusError, ChannelMask, Error, MutexBase, Pca9548a, PinConfig, SelectObserver,
+    SelectionCoordinator, NO_CHANNELS,
+};
+
+/// A mux of a [`MuxGroup`]: a [`Pca9548a`] without a bus of its own.
+///
+/// Create it with [`Pca9548a::group_member`], configure it with the builder methods of [`Pca9548a`]
+/// (e.g. [`Pca9548a::with_channel_count`] or [`Pca9548a::with_error_latch`]) and pass it to [`MuxGroup::from_muxes`].
+/// All members of a group share the type of their coordinator and observer.
+pub type GroupMember<Coordinator = (), Observer = ()> =
+    Pca9548a<(), Coordinator, PinConfig, Observer>;
+
+/// Several pca9548a devices on one physical bus.
+///
+/// Each [`Pca9548a`] owns its bus, so two muxes on the same bus (e.g. at `0x70` and `0x71`)
+/// can't be used side by side. A group owns the bus instead and hands out a [`GroupMux`] handle per device.
+///
+/// The muxes are addressed by their index in the `addresses` passed to [`Self::new`] (or the `muxes`
+/// passed to [`Self::from_muxes`]).
+/// When a channel of one mux is selected, all other muxes of the group are deselected first
+/// (unless they are known to be deselected already), so the downstream buses of different muxes
+/// are never connected at the same time. This also disables their sticky channels (see [`Pca9548a::set_sticky_channels`]).
+/// All handles share one lock, so selects of different muxes are serialized.
+///
+/// Every mux is written like a single [`Pca9548a`], so its channel count, mask policy, error latch,
+/// coordinator and observer apply to the group as well.
+///
+/// Example:
+/// ```
+/// # #[cfg(feature = "sync")]
+/// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) {
+/// use pca9548a::{address, MuxGroup};
+/// use embedded_hal::i2c::I2c;
+///
+/// let group = MuxGroup::<std::sync::Mutex<_>, 2>::new(
+///     i2c_bus,
+///     [address(false, false, false), address(true, false, false)],
+/// );
+///
+/// // Channel 0 of the mux at 0x70, the mux at 0x71 is deselected first.
+/// group.mux(0).single_subbus(0).write(0x42, &[1]).expect("write");
+/// // Channel 5 of the mux at 0x71, the mux at 0x70 is deselected first.
+/// group.mux(1).single_subbus(5).write(0x42, &[2]).expect("write");
+/// # }
+/// ```
+pub struct MuxGroup<Mutex, const N: usize, Coordinator = (), Observer = ()> {
+    bus: Mutex,
+    muxes: [GroupMember<Coordinator, Observer>; N],
+}
+
+impl<Mutex: MutexBase, const N: usize> MuxGroup<Mutex, N> {
+    /// Create a new group for the muxes at `addresses` on `bus`.
+    ///
+    /// To configure the muxes, see [`Self::from_muxes`].
+    pub fn new(bus: Mutex::Bus, addresses: [u8; N]) -> Self {
+        Self::from_muxes(bus, addresses.map(Pca9548a::group_member))
+    }
+}
+
+impl<Mutex: MutexBase, const N: usize, Coordinator, Observer>
+    MuxGroup<Mutex, N, Coordinator, Observer>
+{
+    /// Create a new group of the configured `muxes` on `bus`.
+    ///
+    /// ```
+    /// # #[cfg(feature = "sync")]
+    /// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) {
+    /// use pca9548a::{MuxGroup, Pca9548a};
+    ///
+    /// let group = MuxGroup::<std::sync::Mutex<_>, 2>::from_muxes(
+    ///     i2c_bus,
+    ///     [
+    ///         Pca9548a::group_member(0x70).with_channel_count(4),
+    ///         Pca9548a::group_member(0x71).with_error_latch(),
+    ///     ],
+    /// );
+    /// # }
+    /// ```
+    pub fn from_muxes(bus: Mutex::Bus, muxes: [GroupMember<Coordinator, Observer>; N]) -> Self {
+        Self {
+            bus: Mutex::new(bus),
+            muxes,
+        }
+    }
+
+    /// Get the handle of the mux with the given `index` in the addresses of this group.
+    ///
+    /// Panics if `index` is out of range. With the no-panic feature this is deprecated, as there is
+    /// no handle to return instead; use [`Self::try_mux`].
+    #[cfg_attr(
+        feature = "no-panic",
+        deprecated(note = "panics if `index` is out of range, use `try_mux` instead")
+    )]
+    #[track_caller]
+    pub fn mux(&self, index: usize) -> GroupMux<'_, Mutex, N, Coordinator, Observer> {
+        assert!(index < N);
+        GroupMux { group: self, index }
+    }
+
+    /// Get the handle of the mux with the given `index`, or `None` if `index` is out of range.
+    pub fn try_mux(&self, index: usize) -> Option<GroupMux<'_, Mutex, N, Coordinator, Observer>> {
+        (index < N).then_some(GroupMux { group: self, index })
+    }
+
+    /// Consume the group and return the wrapped bus.
+    pub fn into_inner(self) -> Mutex::Bus {
+        self.bus.into_inner()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<
+        Mutex: AsyncMutex,
+        const N: usize,
+        Coordinator: SelectionCoordinator,
+        Observer: SelectObserver,
+    > MuxGroup<Mutex, N, Coordinator, Observer>
+where
+    Mutex::Bus: embedded_hal_async::i2c::I2c,
+{
+    /// Deselect all muxes except `index` and write `mask` to mux `index`.
+    ///
+    /// The mask is checked against the policy of mux `index` first, so a rejected mask changes nothing.
+    async fn write_mask_async(
+        &self,
+        bus: &mut Mutex::Bus,
+        index: usize,
+        mask: u8,
+    ) -> Result<(), BusError<Mutex>> {
+        let selected = &self.muxes[index];
+        let mask = selected.mask_policy.apply(&selected.last_error, mask)?;
+        for (other, mux) in self.muxes.iter().enumerate() {
+            if other != index && mux.cache.get() != Some(NO_CHANNELS) {
+                mux.write_mask_exact_on_async(bus, NO_CHANNELS).await?;
+            }
+        }
+        selected.write_mask_exact_on_async(bus, mask).await
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<
+        Mutex: SyncMutex,
+        const N: usize,
+        Coordinator: SelectionCoordinator,
+        Observer: SelectObserver,
+    > MuxGroup<Mutex, N, Coordinator, Observer>
+where
+    Mutex::Bus: embedded_hal::i2c::I2c,
+{
+    /// Deselect all muxes except `index` and write `mask` to mux `index`.
+    ///
+    /// *Note:* see [`Self::write_mask_async`] for more info.
+    fn write_mask(
+        &self,
+        bus: &mut Mutex::Bus,
+        index: usize,
+        mask: u8,
+    ) -> Result<(), BusError<Mutex>> {
+        let selected = &self.muxes[index];
+        let mask = selected.mask_policy.apply(&selected.last_error, mask)?;
+        for (other, mux) in self.muxes.iter().enumerate() {
+            if other != index && mux.cache.get() != Some(NO_CHANNELS) {
+                mux.write_mask_exact_on(bus, NO_CHANNELS)?;
+            }
+        }
+        selected.write_mask_exact_on(bus, mask)
+    }
+}
+
+/// A handle to one mux of a [`MuxGroup`].
+///
+/// Returned by [`MuxGroup::mux`].
+pub struct GroupMux<'a, Mutex, const N: usize, Coordinator = (), Observer = ()> {
+    group: &'a MuxGroup<Mutex, N, Coordinator, Observer>,
+    index: usize,
+}
+
+impl<'a, Mutex, const N: usize, Coordinator, Observer> Clone
+    for GroupMux<'a, Mutex, N, Coordinator, Observer>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, Mutex, const N: usize, Coordinator, Observer> Copy
+    for GroupMux<'a, Mutex, N, Coordinator, Observer>
+{
+}
+
+impl<'a, Mutex, const N: usize, Coordinator, Observer>
+    GroupMux<'a, Mutex, N, Coordinator, Observer>
+{
+    /// Get the member of the group behind this handle, e.g. for its [`Pca9548a::last_error`].
+    pub fn member(&self) -> &'a GroupMember<Coordinator, Observer> {
+        &self.group.muxes[self.index]
+    }
+
+    /// The address of this mux.
+    pub fn address(&self) -> u8 {
+        self.member().address
+    }
+
+    /// Get the *cached* mask of this mux.
+    ///
+    /// *Note:* see [`Pca9548a::cached_mask`] for more info.
+    pub fn cached_mask(&self) -> Option<u8> {
+        self.member().cached_mask()
+    }
+
+    /// Get a subbus of this mux.
+    ///
+    /// * `mask` The mask to use for the subbus
+    ///
+    /// See [`GroupSubBus`] for more info.
+    pub fn subbus(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> GroupSubBus<'a, Mutex, N, Coordinator, Observer> {
+        GroupSubBus {
+            mux: *self,
+            mask: mask.into().bits(),
+            invalid_channel: None,
+        }
+    }
+
+    /// Get a subbus of this mux with a single channel enabled.
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    ///
+    /// See [`GroupSubBus`] for more info.
+    ///
+    /// Panics if `id` is out of range. With the no-panic feature, every operation of the returned subbus
+    /// fails with [`Error::InvalidChannel`] instead.
+    #[track_caller]
+    pub fn single_subbus(&self, id: u8) -> GroupSubBus<'a, Mutex, N, Coordinator, Observer> {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(id < 8);
+        if id < 8 {
+            self.subbus(1 << id)
+        } else {
+            GroupSubBus {
+                invalid_channel: Some(id),
+                ..self.subbus(NO_CHANNELS)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<
+        'a,
+        Mutex: AsyncMutex,
+        const N: usize,
+        Coordinator: SelectionCoordinator,
+        Observer: SelectObserver,
+    > GroupMux<'a, Mutex, N, Coordinator, Observer>
+where
+    Mutex::Bus: embedded_hal_async::i2c::I2c,
+{
+    /// Select the subbus and return the lock to the shared bus.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_mask`].
+    ///
+    /// * `mask` The mask to use for the subbus
+    ///
+    /// All other muxes of the group are deselected first (see [`MuxGroup`]).
+    ///
+    /// *Note:* see [`Pca9548a::select_mask_async`] for more info.
+    pub async fn select_mask_async(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + 'a, BusError<Mutex>> {
+        let (group, member) = (self.group, self.member());
+        let mut bus = group
+            .bus
+            .lock()
+            .await
+            .map_err(|e| member.last_error.mutex(e))?;
+        group
+            .write_mask_async(&mut bus, self.index, mask.into().bits())
+            .await?;
+        Ok(bus)
+    }
+
+    /// Select a single subbus and return the lock to the shared bus.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_single`].
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    ///
+    /// Panics if `id` is out of range. With the no-panic feature, [`Error::InvalidChannel`] is returned instead.
+    #[track_caller]
+    pub fn select_single_async(
+        &self,
+        id: u8,
+    ) -> impl Future<Output = Result<impl DerefMut<Target = Mutex::Bus> + 'a, BusError<Mutex>>> + '_
+    {
+        let checked = self.member().check_channel(id);
+        async move {
+            checked?;
+            self.select_mask_async(1 << id).await
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<
+        'a,
+        Mutex: SyncMutex,
+        const N: usize,
+        Coordinator: SelectionCoordinator,
+        Observer: SelectObserver,
+    > GroupMux<'a, Mutex, N, Coordinator, Observer>
+where
+    Mutex::Bus: embedded_hal::i2c::I2c,
+{
+    /// Select the subbus and return the lock to the shared bus.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_mask_async`].
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    pub fn select_mask(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + 'a, BusError<Mutex>> {
+        let (group, member) = (self.group, self.member());
+        let mut bus = group.bus.lock().map_err(|e| member.last_error.mutex(e))?;
+        group.write_mask(&mut bus, self.index, mask.into().bits())?;
+        Ok(bus)
+    }
+
+    /// Select a single subbus and return the lock to the shared bus.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_single_async`].
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    ///
+    /// *Note:* see [`Self::select_single_async`] for more info.
+    #[track_caller]
+    pub fn select_single(
+        &self,
+        id: u8,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + 'a, BusError<Mutex>> {
+        self.member().check_channel(id)?;
+        self.select_mask(1 << id)
+    }
+}
+
+/// A proxy to a subbus of one mux of a [`MuxGroup`].
+///
+/// Like [`SubBus`](crate::SubBus), this implements the [`embedded_hal::i2c::I2c`]/[`embedded_hal_async::i2c::I2c`] traits.
+/// Every operation selects the subbus first, which deselects all other muxes of the group.
+pub struct GroupSubBus<'a, Mutex, const N: usize, Coordinator = (), Observer = ()> {
+    mux: GroupMux<'a, Mutex, N, Coordinator, Observer>,
+    mask: u8,
+    /// The out-of-range id passed to [`GroupMux::single_subbus`] or [`DualMux::single_subbus`] with the no-panic feature
+    invalid_channel: Option<u8>,
+}
+
+impl<'a, Mutex: MutexBase, const N: usize, Coordinator, Observer>
+    GroupSubBus<'a, Mutex, N, Coordinator, Observer>
+{
+    /// Fail with [`Error::InvalidChannel`] if this subbus was created for an out-of-range id.
+    fn check_channel<Bus>(&self) -> Result<(), Error<Mutex::Error, Bus>> {
+        match self.invalid_channel {
+            Some(id) => Err(self.mux.member().last_error.invalid_channel(id)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a, Mutex, const N: usize, Coordinator, Observer> ErrorType
+    for GroupSubBus<'a, Mutex, N, Coordinator, Observer>
+where
+    Mutex: MutexBase,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: ErrorType,
+{
+    type Error = BusError<Mutex>;
+}
+
+#[cfg(feature = "async")]
+impl<'a, Mutex, const N: usize, Coordinator, Observer> embedded_hal_async::i2c::I2c
+    for GroupSubBus<'a, Mutex, N, Coordinator, Observer>
+where
+    Mutex: AsyncMutex,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal_async::i2c::I2c,
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.check_channel()?;
+        let last_error = &self.mux.member().last_error;
+        self.mux
+            .select_mask_async(self.mask)
+            .await?
+            .transaction(address, operations)
+            .await
+            .map_err(|e| last_error.bus(e))
+    }
+
+    async fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_channel()?;
+        let last_error = &self.mux.member().last_error;
+        self.mux
+            .select_mask_async(self.mask)
+            .await?
+            .read(address, read)
+            .await
+            .map_err(|e| last_error.bus(e))
+    }
+
+    async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        self.check_channel()?;
+        let last_error = &self.mux.member().last_error;
+        self.mux
+            .select_mask_async(self.mask)
+            .await?
+            .write(address, write)
+            .await
+            .map_err(|e| last_error.bus(e))
+    }
+
+    async fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.check_channel()?;
+        let last_error = &self.mux.member().last_error;
+        self.mux
+            .select_mask_async(self.mask)
+            .await?
+            .write_read(address, write, read)
+            .await
+            .map_err(|e| last_error.bus(e))
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<'a, Mutex, const N: usize, Coordinator, Observer> embedded_hal::i2c::I2c
+    for GroupSubBus<'a, Mutex, N, Coordinator, Observer>
+where
+    Mutex: SyncMutex,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal::i2c::I2c,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.check_channel()?;
+        let last_error = &self.mux.member().last_error;
+        self.mux
+            .select_mask(self.mask)?
+            .transaction(address, operations)
+            .map_err(|e| last_error.bus(e))
+    }
+
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_channel()?;
+        let last_error = &self.mux.member().last_error;
+        self.mux
+            .select_mask(self.mask)?
+            .read(address, read)
+            .map_err(|e| last_error.bus(e))
+    }
+
+    fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        self.check_channel()?;
+        let last_error = &self.mux.member().last_error;
+        self.mux
+            .select_mask(self.mask)?
+            .write(address, write)
+            .map_err(|e| last_error.bus(e))
+    }
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.check_channel()?;
+        let last_error = &self.mux.member().last_error;
+        self.mux
+            .select_mask(self.mask)?
+            .write_read(address, write, read)
+            .map_err(|e| last_error.bus(e))
+    }
+}
+
+/// Two pca9548a devices on one bus, used as a single 16-channel mux.
+///
+/// Channels 0..=7 are the channels of the first mux, channels 8..=15 those of the second one.
+/// Like in a [`MuxGroup`] (which this wraps), the other mux is deselected before a channel is selected,
+/// so at most one of the two downstream buses is connected at a time.
+///
+/// Example:
+/// ```
+/// # #[cfg(feature = "sync")]
+/// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) {
+/// use pca9548a::{address, DualMux};
+/// use embedded_hal::i2c::I2c;
+///
+/// let mux = DualMux::<std::sync::Mutex<_>>::new(
+///     i2c_bus,
+///     address(false, false, false),
+///     address(true, false, false),
+/// );
+///
+/// // Channel 2 of the mux at 0x71, the mux at 0x70 is deselected first.
+/// mux.single_subbus(10).write(0x42, &[1]).expect("write");
+/// # }
+/// ```
+pub struct DualMux<Mutex> {
+    group: MuxGroup<Mutex, 2>,
+}
+
+impl<Mutex: MutexBase> DualMux<Mutex> {
+    /// Create a new 16-channel mux from the muxes at `first` (channels 0..=7) and `second` (channels 8..=15) on `bus`.
+    pub fn new(bus: Mutex::Bus, first: u8, second: u8) -> Self {
+        Self {
+            group: MuxGroup::new(bus, [first, second]),
+        }
+    }
+
+    /// Get the underlying group; mux `0` holds channels 0..=7, mux `1` channels 8..=15.
+    pub fn group(&self) -> &MuxGroup<Mutex, 2> {
+        &self.group
+    }
+
+    /// Get a subbus with a single channel enabled, or `None` if `id` is not in range 0..=15.
+    ///
+    /// See [`GroupSubBus`] for more info.
+    pub fn try_single_subbus(&self, id: u8) -> Option<GroupSubBus<'_, Mutex, 2>> {
+        if id >= 16 {
+            return None;
+        }
+        let mux = self.group.try_mux(usize::from(id / 8))?;
+        Some(mux.subbus(1 << (id % 8)))
+    }
+
+    /// Get a subbus with a single channel enabled.
+    ///
+    /// * `id` The id of the subbus in range 0..=15
+    ///
+    /// See [`GroupSubBus`] for more info.
+    ///
+    /// Panics if `id` is out of range. With the no-panic feature, every operation of the returned subbus
+    /// fails with [`Error::InvalidChannel`] instead; see also [`Self::try_single_subbus`].
+    #[track_caller]
+    pub fn single_subbus(&self, id: u8) -> GroupSubBus<'_, Mutex, 2> {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(id < 16);
+        match self.try_single_subbus(id) {
+            Some(subbus) => subbus,
+            None => GroupSubBus {
+                invalid_channel: Some(id),
+                ..self.first_mux().subbus(NO_CHANNELS)
+            },
+        }
+    }
+
+    /// Consume the mux and return the wrapped bus.
+    pub fn into_inner(self) -> Mutex::Bus {
+        self.group.into_inner()
+    }
+
+    /// Get the first mux, which reports the errors of out-of-range channel ids.
+    fn first_mux(&self) -> GroupMux<'_, Mutex, 2> {
+        GroupMux {
+            group: &self.group,
+            index: 0,
+        }
+    }
+
+    /// Get the mux of channel `id` and the id of the channel on that mux.
+    ///
+    /// Panics if `id` is out of range. With the no-panic feature, an error is returned instead.
+    #[track_caller]
+    fn route<Bus>(&self, id: u8) -> Result<(GroupMux<'_, Mutex, 2>, u8), Error<Mutex::Error, Bus>> {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(id < 16);
+        match self.group.try_mux(usize::from(id / 8)) {
+            Some(mux) => Ok((mux, id % 8)),
+            None => Err(self.first_mux().member().last_error.invalid_channel(id)),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Mutex: AsyncMutex> DualMux<Mutex>
+where
+    Mutex::Bus: embedded_hal_async::i2c::I2c,
+{
+    /// Select a single channel and return the lock to the shared bus.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_single`].
+    ///
+    /// * `id` The id of the channel in range 0..=15
+    ///
+    /// The other mux is deselected first.
+    ///
+    /// *Note:* see [`GroupMux::select_mask_async`] for more info.
+    ///
+    /// Panics if `id` is out of range. With the no-panic feature, [`Error::InvalidChannel`] is returned instead.
+    #[track_caller]
+    pub fn select_single_async(
+        &self,
+        id: u8,
+    ) -> impl Future<Output = Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>>> + '_
+    {
+        let routed = self.route(id);
+        async move {
+            let (mux, channel) = routed?;
+            mux.select_mask_async(1 << channel).await
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex: SyncMutex> DualMux<Mutex>
+where
+    Mutex::Bus: embedded_hal::i2c::I2c,
+{
+    /// Select a single channel and return the lock to the shared bus.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_single_async`].
+    ///
+    /// *Note:* see [`Self::select_single_async`] for more info.
+    #[track_caller]
+    pub fn select_single(
+        &self,
+        id: u8,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        let (mux, channel) = self.route(id)?;
+        mux.select_mask(1 << channel)
+    }
+}
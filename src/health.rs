@@ -0,0 +1,93 @@
+use embedded_hal::i2c::ErrorKind;
+
+#[cfg(feature = "async")]
+use crate::AsyncMutex;
+#[cfg(feature = "sync")]
+use crate::SyncMutex;
+use crate::{BusError, ControlAddress, Error, MutexBase, Pca9548a};
+
+/// The result of [`Pca9548a::health_check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Health {
+    /// Whether the device acknowledged its address
+    pub responds: bool,
+    /// The value read from the control register; `0` if the device does not respond
+    pub control_register: u8,
+    /// Whether the control register matches the cached mask (see [`Pca9548a::cached_mask`]).
+    /// This is `true` if no mask is cached, and `false` if the device does not respond.
+    pub matches_cache: bool,
+}
+
+impl<Mutex: MutexBase, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Turn the result of reading the control register into a [`Health`].
+    fn health<Bus: embedded_hal::i2c::Error>(
+        &self,
+        result: Result<u8, Bus>,
+    ) -> Result<Health, Error<Mutex::Error, Bus>> {
+        match result {
+            Ok(control_register) => Ok(Health {
+                responds: true,
+                control_register,
+                matches_cache: self
+                    .cache
+                    .get()
+                    .is_none_or(|cached| cached == control_register),
+            }),
+            Err(e) if matches!(e.kind(), ErrorKind::NoAcknowledge(_)) => Ok(Health {
+                responds: false,
+                control_register: 0,
+                matches_cache: false,
+            }),
+            Err(e) => Err(self.last_error.bus(e)),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Mutex: AsyncMutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+{
+    /// Check that the device responds and that its selection is the expected one.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::health_check`].
+    ///
+    /// *Note:* see [`Self::health_check`] for more info.
+    pub async fn health_check_async(&self) -> Result<Health, BusError<Mutex>> {
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        let mut mask = [0];
+        let result = embedded_hal_async::i2c::I2c::read(&mut *bus, self.address, &mut mask).await;
+        self.health(result.map(|()| self.mask_policy.decode(mask[0])))
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex: SyncMutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+{
+    /// Check that the device responds and that its selection is the expected one.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::health_check_async`].
+    ///
+    /// This is a one-call diagnostic for a supervisory loop: the control register is read, which also
+    /// probes the device, and compared to the cached mask. A device that doesn't acknowledge its address
+    /// is reported with `responds: false`; other bus errors are returned.
+    /// The check is read-only: neither the selection nor the cached mask is changed.
+    pub fn health_check(&self) -> Result<Health, BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let mut mask = [0];
+        let result = embedded_hal::i2c::I2c::read(&mut *bus, self.address, &mut mask);
+        self.health(result.map(|()| self.mask_policy.decode(mask[0])))
+    }
+}
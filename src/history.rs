@@ -0,0 +1,98 @@
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+
+use crate::{Pca9548a, SelectObserver};
+
+/// One select recorded by a [`SelectHistory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectRecord {
+    /// The number of the select, counted from `0` since the history was created
+    pub sequence: u32,
+    /// The mask that was written to the control register
+    pub mask: u8,
+}
+
+/// A [`SelectObserver`] that keeps the masks of the last `N` selects, for diagnosing channel glitches.
+///
+/// Attach it with [`Pca9548a::with_observer`] and dump it with [`Pca9548a::select_history`]
+/// after a failure. Only the writes of the control register are recorded, including the deselects
+/// of e.g. a [`CancellationGuard`](crate::CancellationGuard); failed writes are not (see [`SelectObserver`]).
+/// The masks are the logical ones, i.e. before [`Pca9548a::with_inverted_mask`] is applied.
+///
+/// The overhead is `N` bytes plus a counter, and an atomic increment and store per select.
+/// Older records are overwritten once `N` selects were recorded.
+///
+/// ```
+/// # #[cfg(all(feature = "sync", feature = "std"))]
+/// # {
+/// use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+/// use pca9548a::{Pca9548a, SelectHistory, SelectRecord, BASE_ADDRESS};
+///
+/// # let mut expectations = vec![Transaction::write(BASE_ADDRESS, vec![0b101])];
+/// # if cfg!(feature = "deselect-on-drop") {
+/// #     expectations.push(Transaction::write(BASE_ADDRESS, vec![0]));
+/// # }
+/// let mut bus = Mock::new(&expectations);
+/// let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+///     .with_observer(SelectHistory::<8>::new());
+///
+/// drop(pca.select_mask(0b101).unwrap());
+/// assert_eq!(pca.select_history()[0], SelectRecord { sequence: 0, mask: 0b101 });
+/// # // With `deselect-on-drop`, the drop is recorded as a second select.
+/// # assert_eq!(pca.select_history().len(), if cfg!(feature = "deselect-on-drop") { 2 } else { 1 });
+/// # bus.done();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SelectHistory<const N: usize> {
+    masks: [AtomicU8; N],
+    next: AtomicU32,
+}
+
+impl<const N: usize> SelectHistory<N> {
+    /// Create an empty history.
+    pub const fn new() -> Self {
+        Self {
+            masks: [const { AtomicU8::new(0) }; N],
+            next: AtomicU32::new(0),
+        }
+    }
+
+    /// Get the recorded selects, oldest first.
+    pub fn records(&self) -> heapless::Vec<SelectRecord, N> {
+        let next = self.next.load(Ordering::Acquire);
+        let len = next.min(N as u32);
+        (next - len..next)
+            .map(|sequence| SelectRecord {
+                sequence,
+                mask: self.masks[sequence as usize % N].load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+impl<const N: usize> Default for SelectHistory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> SelectObserver for SelectHistory<N> {
+    fn on_select(&self, mask: u8) {
+        if N == 0 {
+            return;
+        }
+        // Selects are serialized by the bus lock, so there is a single writer.
+        let sequence = self.next.load(Ordering::Relaxed);
+        self.masks[sequence as usize % N].store(mask, Ordering::Relaxed);
+        self.next.store(sequence.wrapping_add(1), Ordering::Release);
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Address, const N: usize>
+    Pca9548a<Mutex, Coordinator, Pins, SelectHistory<N>, Address>
+{
+    /// Get the last selects recorded by the [`SelectHistory`] attached to this device, oldest first.
+    pub fn select_history(&self) -> heapless::Vec<SelectRecord, N> {
+        self.observer.records()
+    }
+}
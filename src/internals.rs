@@ -0,0 +1,52 @@
+//! Internal state of a [`Pca9548a`], for property-based tests of downstream crates (feature `test-internals`).
+//!
+//! Nothing here is covered by semver; it tracks the implementation.
+
+use crate::Pca9548a;
+
+/// A snapshot of the bookkeeping of a [`Pca9548a`], returned by [`Pca9548a::internal_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct InternalState {
+    /// The cached mask, see [`Pca9548a::cached_mask`]
+    pub cached_mask: Option<u8>,
+    /// The bits that may be set in a mask, see [`Pca9548a::with_channel_count`]
+    pub valid_mask: u8,
+    /// The channels added to every select, see [`Pca9548a::set_sticky_channels`]
+    pub sticky_mask: u8,
+    /// Whether invalid bits are rejected, see [`Pca9548a::with_strict_mask`]
+    pub strict: bool,
+    /// Whether the bytes on the bus are inverted, see [`Pca9548a::with_inverted_mask`]
+    pub inverted: bool,
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Get a snapshot of the internal state.
+    pub fn internal_state(&self) -> InternalState {
+        InternalState {
+            cached_mask: self.cache.get(),
+            valid_mask: self.mask_policy.valid(),
+            sticky_mask: self.mask_policy.sticky,
+            strict: self.mask_policy.strict,
+            inverted: self.mask_policy.inverted,
+        }
+    }
+
+    /// Get the mask that a select of `mask` would store, i.e. with the sticky channels added
+    /// and the invalid bits cleared (in strict mode, a mask with invalid bits is rejected instead).
+    pub fn effective_mask(&self, mask: u8) -> u8 {
+        self.mask_policy.expected(mask)
+    }
+
+    /// Get the byte written to the control register for the (effective) `mask`.
+    pub fn encode_control(&self, mask: u8) -> u8 {
+        self.mask_policy.encode(mask)
+    }
+
+    /// Get the mask for a byte read from the control register.
+    pub fn decode_control(&self, raw: u8) -> u8 {
+        self.mask_policy.decode(raw)
+    }
+}
@@ -0,0 +1,89 @@
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use embedded_hal::delay::DelayNs;
+
+use crate::SelectionCoordinator;
+
+/// A [`SelectionCoordinator`] that enforces a minimum interval between two selects.
+///
+/// Rapidly toggling the mux can stress its analog switches or the devices behind it.
+/// Attach this with [`Pca9548a::with_coordinator`](crate::Pca9548a::with_coordinator): before every write
+/// of the control register, it checks the time of the last write with `clock` and, if that was less than
+/// the interval ago, waits for the rest of it with `delay`.
+///
+/// * `clock` returns a timestamp in microseconds; it may wrap around, as only differences are used.
+/// * `delay` is cloned for every wait, so a delay shared by clones (e.g. one that only reads a timer) is best.
+///
+/// *Note:* The coordinator hooks neither know the mask nor can be awaited, which has two consequences:
+/// * Every write of the control register counts as a select, including the deselects
+///   (e.g. [`Pca9548a::deselect`](crate::Pca9548a::deselect), closing a session or a dropped lock with the
+///   `deselect-on-drop` feature). A deselect that comes too soon is delayed like a select, and delays the next
+///   select in turn.
+/// * The wait blocks with `delay`, also for the async selects, so the executor is stalled for up to the interval.
+///   In an async application, keep the interval short or space the selects out with an async timer instead.
+///
+/// It is opt-in: the default coordinator `()` costs nothing.
+///
+/// ```
+/// # #[cfg(feature = "sync")]
+/// # fn example(i2c_bus: impl embedded_hal::i2c::I2c, delay: impl embedded_hal::delay::DelayNs + Clone, now_us: fn() -> u32) {
+/// use pca9548a::{MinSelectInterval, Pca9548a, BASE_ADDRESS};
+///
+/// let pca = Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, BASE_ADDRESS)
+///     .with_coordinator(MinSelectInterval::new(now_us, delay, 500));
+///
+/// // Relax the limit at runtime.
+/// pca.coordinator().set_min_select_interval(100);
+/// # }
+/// ```
+pub struct MinSelectInterval<Clock, Delay> {
+    clock: Clock,
+    delay: Delay,
+    interval_us: AtomicU32,
+    last_us: AtomicU32,
+    selected: AtomicBool,
+}
+
+impl<Clock: Fn() -> u32, Delay: DelayNs + Clone> MinSelectInterval<Clock, Delay> {
+    /// Create a coordinator that keeps at least `interval_us` microseconds between two selects.
+    pub fn new(clock: Clock, delay: Delay, interval_us: u32) -> Self {
+        Self {
+            clock,
+            delay,
+            interval_us: AtomicU32::new(interval_us),
+            last_us: AtomicU32::new(0),
+            selected: AtomicBool::new(false),
+        }
+    }
+
+    /// Change the minimum interval between two selects, in microseconds; `0` disables the limit.
+    pub fn set_min_select_interval(&self, interval_us: u32) {
+        self.interval_us.store(interval_us, Ordering::Relaxed);
+    }
+
+    /// Get the minimum interval between two selects, in microseconds.
+    pub fn min_select_interval(&self) -> u32 {
+        self.interval_us.load(Ordering::Relaxed)
+    }
+}
+
+/// The hooks are called with the bus lock held, so relaxed ordering is sufficient.
+impl<Clock: Fn() -> u32, Delay: DelayNs + Clone> SelectionCoordinator
+    for MinSelectInterval<Clock, Delay>
+{
+    fn acquire(&self) {
+        if !self.selected.load(Ordering::Relaxed) {
+            return;
+        }
+        let elapsed = (self.clock)().wrapping_sub(self.last_us.load(Ordering::Relaxed));
+        let interval = self.min_select_interval();
+        if elapsed < interval {
+            self.delay.clone().delay_us(interval - elapsed);
+        }
+    }
+
+    fn release(&self) {
+        self.last_us.store((self.clock)(), Ordering::Relaxed);
+        self.selected.store(true, Ordering::Relaxed);
+    }
+}
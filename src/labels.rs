@@ -0,0 +1,54 @@
+use crate::CHANNELS;
+
+/// Human-readable names for the channels of a mux, without an allocator.
+///
+/// Each label holds up to `N` bytes. This is useful, for example, for a serial console status command
+/// in firmware that cannot use `alloc`.
+///
+/// ```
+/// use pca9548a::ChannelLabels;
+///
+/// let mut labels = ChannelLabels::<16>::new();
+/// labels.label_channel(3, "imu").unwrap();
+///
+/// assert_eq!(labels.channel_label(3), Some("imu"));
+/// assert_eq!(labels.channel_label(4), None);
+/// assert!(labels.label_channel(0, "a label that is too long").is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ChannelLabels<const N: usize> {
+    labels: [Option<heapless::String<N>>; CHANNELS],
+}
+
+impl<const N: usize> ChannelLabels<N> {
+    /// Create a new instance without any labels.
+    pub const fn new() -> Self {
+        Self {
+            labels: [const { None }; CHANNELS],
+        }
+    }
+
+    /// Set the label of a channel, replacing the previous one.
+    ///
+    /// * `id` The id of the channel in range 0..=7
+    /// * `name` The label; must not be longer than `N` bytes
+    ///
+    /// If `name` is too long, the previous label is kept.
+    ///
+    /// Panics if `id` is out of range. With the no-panic feature, an invalid id is ignored instead.
+    pub fn label_channel(&mut self, id: u8, name: &str) -> Result<(), heapless::CapacityError> {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(usize::from(id) < CHANNELS);
+        if let Some(label) = self.labels.get_mut(usize::from(id)) {
+            *label = Some(name.try_into()?);
+        }
+        Ok(())
+    }
+
+    /// Get the label of a channel, if it has one.
+    ///
+    /// * `id` The id of the channel in range 0..=7; for other ids `None` is returned
+    pub fn channel_label(&self, id: u8) -> Option<&str> {
+        self.labels.get(usize::from(id))?.as_deref()
+    }
+}
@@ -0,0 +1,182 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+#[cfg(any(feature = "sync", feature = "async"))]
+use crate::Error;
+
+/// The latch is disabled and records nothing.
+const DISABLED: u32 = u32::MAX;
+/// The latch is enabled, but no error was recorded.
+const EMPTY: u32 = 0;
+
+const MUTEX: u32 = 1;
+const BUS: u32 = 2;
+const PIN: u32 = 3;
+const VERIFY_FAILED: u32 = 4;
+const INVALID_CHANNEL: u32 = 5;
+const INVALID_MASK: u32 = 6;
+const TIMEOUT: u32 = 7;
+const INVALID_LENGTH: u32 = 8;
+
+/// The most recent error recorded by the error latch (see [`Pca9548a::with_error_latch`](crate::Pca9548a::with_error_latch)).
+///
+/// Only the kind of the error is kept, so it can be stored without knowing the concrete error types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LastError {
+    /// The mutex could not be locked
+    Mutex,
+    /// The bus returned an error of this kind
+    Bus(ErrorKind),
+    /// One of the optional pins returned an error
+    Pin,
+    /// See [`Error::VerifyFailed`]
+    VerifyFailed {
+        /// The mask that was written
+        expected: u8,
+        /// The mask that was read back
+        actual: u8,
+    },
+    /// See [`Error::InvalidChannel`]
+    InvalidChannel(u8),
+    /// See [`Error::InvalidMask`]
+    InvalidMask(u8),
+    /// See [`Error::Timeout`]
+    Timeout,
+    /// See [`Error::InvalidLength`]
+    InvalidLength,
+}
+
+impl LastError {
+    #[cfg(any(feature = "sync", feature = "async"))]
+    fn encode(self) -> u32 {
+        match self {
+            LastError::Mutex => MUTEX,
+            LastError::Bus(kind) => {
+                let kind = match kind {
+                    ErrorKind::Bus => 0,
+                    ErrorKind::ArbitrationLoss => 1,
+                    ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => 2,
+                    ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data) => 3,
+                    ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown) => 4,
+                    ErrorKind::Overrun => 5,
+                    _ => 6,
+                };
+                BUS | kind << 8
+            }
+            LastError::Pin => PIN,
+            LastError::VerifyFailed { expected, actual } => {
+                VERIFY_FAILED | u32::from(expected) << 8 | u32::from(actual) << 16
+            }
+            LastError::InvalidChannel(id) => INVALID_CHANNEL | u32::from(id) << 8,
+            LastError::InvalidMask(mask) => INVALID_MASK | u32::from(mask) << 8,
+            LastError::Timeout => TIMEOUT,
+            LastError::InvalidLength => INVALID_LENGTH,
+        }
+    }
+
+    fn decode(value: u32) -> Option<Self> {
+        let [tag, a, b, _] = value.to_le_bytes();
+        match u32::from(tag) {
+            MUTEX => Some(LastError::Mutex),
+            BUS => Some(LastError::Bus(match a {
+                0 => ErrorKind::Bus,
+                1 => ErrorKind::ArbitrationLoss,
+                2 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+                3 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+                4 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+                5 => ErrorKind::Overrun,
+                _ => ErrorKind::Other,
+            })),
+            PIN => Some(LastError::Pin),
+            VERIFY_FAILED => Some(LastError::VerifyFailed {
+                expected: a,
+                actual: b,
+            }),
+            INVALID_CHANNEL => Some(LastError::InvalidChannel(a)),
+            INVALID_MASK => Some(LastError::InvalidMask(a)),
+            TIMEOUT => Some(LastError::Timeout),
+            INVALID_LENGTH => Some(LastError::InvalidLength),
+            _ => None,
+        }
+    }
+}
+
+/// A slot for the most recent error, disabled by default.
+///
+/// The error constructors record the error and return it, so they can be used in `map_err`.
+pub(crate) struct ErrorLatch(AtomicU32);
+
+impl ErrorLatch {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicU32::new(DISABLED))
+    }
+
+    pub(crate) fn enable(&self) {
+        self.0.store(EMPTY, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> Option<LastError> {
+        LastError::decode(self.0.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn clear(&self) {
+        self.replace_if_enabled(EMPTY);
+    }
+
+    fn replace_if_enabled(&self, new: u32) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |value| {
+                (value != DISABLED).then_some(new)
+            });
+    }
+}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+impl ErrorLatch {
+    pub(crate) fn mutex<Mutex, Bus>(&self, e: Mutex) -> Error<Mutex, Bus> {
+        self.replace_if_enabled(LastError::Mutex.encode());
+        Error::Mutex(e)
+    }
+
+    pub(crate) fn bus<Mutex, Bus: embedded_hal::i2c::Error>(&self, e: Bus) -> Error<Mutex, Bus> {
+        self.replace_if_enabled(LastError::Bus(e.kind()).encode());
+        Error::Bus(e)
+    }
+
+    pub(crate) fn pin<Mutex, Bus>(
+        &self,
+        kind: embedded_hal::digital::ErrorKind,
+    ) -> Error<Mutex, Bus> {
+        self.replace_if_enabled(LastError::Pin.encode());
+        Error::Pin(kind)
+    }
+
+    pub(crate) fn verify_failed<Mutex, Bus>(&self, expected: u8, actual: u8) -> Error<Mutex, Bus> {
+        self.replace_if_enabled(LastError::VerifyFailed { expected, actual }.encode());
+        Error::VerifyFailed { expected, actual }
+    }
+
+    pub(crate) fn invalid_channel<Mutex, Bus>(&self, id: u8) -> Error<Mutex, Bus> {
+        self.replace_if_enabled(LastError::InvalidChannel(id).encode());
+        Error::InvalidChannel(id)
+    }
+
+    pub(crate) fn invalid_mask<Mutex, Bus>(&self, mask: u8) -> Error<Mutex, Bus> {
+        self.replace_if_enabled(LastError::InvalidMask(mask).encode());
+        Error::InvalidMask(mask)
+    }
+
+    pub(crate) fn invalid_length<Mutex, Bus>(&self, regs: usize, out: usize) -> Error<Mutex, Bus> {
+        self.replace_if_enabled(LastError::InvalidLength.encode());
+        Error::InvalidLength { regs, out }
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn timeout<Mutex, Bus>(&self) -> Error<Mutex, Bus> {
+        self.replace_if_enabled(LastError::Timeout.encode());
+        Error::Timeout
+    }
+}
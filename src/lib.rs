@@ -1,10 +1,116 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![doc = include_str!("../README.md")]
 
-use core::{future::Future, ops::DerefMut};
+#[cfg(feature = "async")]
+use core::future::Future;
+#[cfg(any(feature = "sync", feature = "async"))]
+use core::ops::DerefMut;
 
-use embedded_hal::i2c::{ErrorType, I2c as _};
+use cache::MaskCache;
+#[cfg(feature = "sync")]
+use embedded_hal::i2c::I2c as _;
+use embedded_hal::i2c::{ErrorType, SevenBitAddress, TenBitAddress};
+#[cfg(feature = "async")]
 use embedded_hal_async::i2c::I2c as _;
+use latch::ErrorLatch;
+use mask::MaskPolicy;
+
+mod cache;
+#[cfg(feature = "async")]
+mod cancel;
+mod channel;
+#[cfg(feature = "deselect-on-drop")]
+mod deselect;
+#[cfg(feature = "embassy")]
+mod embassy;
+#[cfg(feature = "async")]
+mod fair;
+#[cfg(any(feature = "sync", feature = "async"))]
+mod group;
+#[cfg(any(feature = "sync", feature = "async"))]
+mod health;
+#[cfg(feature = "heapless")]
+mod history;
+#[cfg(feature = "test-internals")]
+pub mod internals;
+mod interval;
+#[cfg(feature = "heapless")]
+mod labels;
+mod latch;
+mod mask;
+#[cfg(any(feature = "sync", feature = "async"))]
+mod parent;
+mod pins;
+#[cfg(any(feature = "sync", feature = "async"))]
+mod provider;
+#[cfg(feature = "reassert")]
+mod reassert;
+#[cfg(any(feature = "sync", feature = "async"))]
+mod round_robin;
+#[cfg(any(feature = "sync", feature = "async"))]
+mod scan;
+#[cfg(any(feature = "sync", feature = "async"))]
+mod session;
+#[cfg(all(feature = "std", any(feature = "sync", feature = "async")))]
+mod split;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "postcard")]
+mod topology;
+#[cfg(all(
+    feature = "defmt",
+    feature = "trace",
+    any(feature = "sync", feature = "async")
+))]
+mod trace;
+#[cfg(feature = "sync")]
+mod typestate;
+
+#[cfg(feature = "async")]
+pub use cancel::CancellationGuard;
+pub use channel::{Channel, InvalidChannel};
+#[cfg(feature = "async")]
+pub use fair::FairMutex;
+#[cfg(any(feature = "sync", feature = "async"))]
+pub use group::{DualMux, GroupMember, GroupMux, GroupSubBus, MuxGroup};
+#[cfg(any(feature = "sync", feature = "async"))]
+pub use health::Health;
+#[cfg(feature = "heapless")]
+pub use history::{SelectHistory, SelectRecord};
+pub use interval::MinSelectInterval;
+#[cfg(feature = "heapless")]
+pub use labels::ChannelLabels;
+pub use latch::LastError;
+pub use mask::ChannelMask;
+#[cfg(any(feature = "sync", feature = "async"))]
+pub use parent::ParentBus;
+pub use pins::{NoDelay, NoPin, PinConfig};
+#[cfg(feature = "async")]
+pub use provider::AsyncChannelProvider;
+#[cfg(feature = "sync")]
+pub use provider::ChannelProvider;
+#[cfg(any(feature = "sync", feature = "async"))]
+pub use provider::DirectBus;
+#[cfg(feature = "reassert")]
+pub use reassert::ReassertingSession;
+#[cfg(any(feature = "sync", feature = "async"))]
+pub use round_robin::RoundRobinBus;
+#[cfg(all(feature = "sync", feature = "heapless"))]
+pub use scan::scan_for_muxes;
+#[cfg(all(feature = "async", feature = "heapless"))]
+pub use scan::scan_for_muxes_async;
+#[cfg(any(feature = "sync", feature = "async"))]
+pub use scan::CrosstalkReport;
+#[cfg(all(feature = "heapless", any(feature = "sync", feature = "async")))]
+pub use scan::SCAN_CAPACITY;
+#[cfg(any(feature = "sync", feature = "async"))]
+pub use session::MuxSession;
+#[cfg(all(feature = "std", any(feature = "sync", feature = "async")))]
+pub use split::{OwnedSubBus, SharedPca9548a};
+#[cfg(feature = "postcard")]
+pub use topology::{ChannelTopology, MuxTopology};
+#[cfg(feature = "sync")]
+pub use typestate::{Idle, Selected};
 
 /// The base address of a pca9548a.
 ///
@@ -13,210 +119,2659 @@ use embedded_hal_async::i2c::I2c as _;
 /// where Ax is 1 if the corresponding pin is high and 0 if it is low.
 pub const BASE_ADDRESS: u8 = 0x70;
 
+/// The number of channels of a pca9548a.
+pub const CHANNELS: usize = 8;
+
+/// Position of the interrupt flags in the control register of the PCA9543A/PCA9545A.
+#[cfg(any(feature = "sync", feature = "async", feature = "test-util"))]
+const INTERRUPT_SHIFT: u8 = 4;
+
+/// The mask with all channels enabled.
+///
+/// [`Pca9548a::select_all`] clears the bits beyond the channel count, see [`Pca9548a::with_channel_count`].
+pub const ALL_CHANNELS: u8 = 0xFF;
+
+/// The mask with no channel enabled.
+pub const NO_CHANNELS: u8 = 0x00;
+
+/// Compute the effective address from the levels of the address pins.
+///
+/// Pass `true` for a pin that is tied high and `false` for a pin that is tied low.
+/// Being a `const fn`, this can be used for board constants:
+/// ```
+/// use pca9548a::{address, BASE_ADDRESS};
+///
+/// const MUX_ADDR: u8 = address(true, false, false);
+/// assert_eq!(MUX_ADDR, 0x71);
+///
+/// assert_eq!(address(false, false, false), BASE_ADDRESS);
+/// assert_eq!(address(true, false, false), 0x71);
+/// assert_eq!(address(false, true, false), 0x72);
+/// assert_eq!(address(true, true, false), 0x73);
+/// assert_eq!(address(false, false, true), 0x74);
+/// assert_eq!(address(true, false, true), 0x75);
+/// assert_eq!(address(false, true, true), 0x76);
+/// assert_eq!(address(true, true, true), 0x77);
+/// ```
+pub const fn address(a0: bool, a1: bool, a2: bool) -> u8 {
+    BASE_ADDRESS + ((a2 as u8) << 2) + ((a1 as u8) << 1) + (a0 as u8)
+}
+
+/// The number of addresses a mux of the family can have, see [`possible_addresses`].
+pub const MUX_ADDRESSES: usize = 8;
+
+/// Iterate over all addresses a mux of the family can have (`0x70..=0x77`), in ascending order.
+///
+/// See [`address`] for how the address pins select one of them.
+pub fn possible_addresses() -> impl Iterator<Item = u8> {
+    (0..MUX_ADDRESSES as u8).map(|offset| BASE_ADDRESS + offset)
+}
+
+/// The big-endian memory address of page `index` of a paged read from `start`.
+#[cfg(any(feature = "sync", feature = "async"))]
+fn page_address(start: u16, index: usize, page_size: usize) -> [u8; 2] {
+    // Addresses wrap around like the address counter of the device.
+    let offset = index.wrapping_mul(page_size.max(1)) as u16;
+    start.wrapping_add(offset).to_be_bytes()
+}
+
+/// Trace a select with defmt; expands to nothing unless both the `defmt` and the `trace` features are enabled.
+#[cfg(any(feature = "sync", feature = "async"))]
+macro_rules! trace_select {
+    ($address:expr, $mask:expr) => {
+        #[cfg(all(feature = "defmt", feature = "trace"))]
+        trace::select($address, $mask);
+    };
+}
+
+/// Trace an operation on a device behind the mux, like [`trace_select`].
+#[cfg(any(feature = "sync", feature = "async"))]
+macro_rules! trace_operation {
+    ($address:expr, $mask:expr, $kind:expr, $device_addr:expr) => {
+        #[cfg(all(feature = "defmt", feature = "trace"))]
+        trace::operation($address, $mask, $kind, $device_addr);
+    };
+}
+
+/// Iterate over the ids of the channels enabled in `mask`, in ascending order.
+fn mask_to_channels(mask: u8) -> impl Iterator<Item = u8> {
+    (0..8).filter(move |id| mask & (1 << id) != 0)
+}
+
+/// Whether `result` failed with a bus error that may be transient, i.e. is worth a retry.
+///
+/// A NACK means the device isn't there (or busy), so retrying right away won't help.
+#[cfg(any(feature = "sync", feature = "async"))]
+fn is_transient<M, B: embedded_hal::i2c::Error>(result: &Result<(), Error<M, B>>) -> bool {
+    match result {
+        Err(Error::Bus(e)) => !scan::is_nack(e),
+        _ => false,
+    }
+}
+
+/// Split `bytes` into the part before its last chunk of at most `max_chunk` bytes, and that chunk.
+///
+/// The chunks of the first part are aligned like those of `bytes.chunks(max_chunk)`.
+#[cfg(any(feature = "sync", feature = "async"))]
+fn split_last_chunk(bytes: &[u8], max_chunk: usize) -> (&[u8], &[u8]) {
+    let last = match bytes.len() % max_chunk {
+        0 => max_chunk.min(bytes.len()),
+        rest => rest,
+    };
+    bytes.split_at(bytes.len() - last)
+}
+
 /// This crate allows using sync and async mutexes.
 /// All must implement this trait.
 pub trait MutexBase {
     /// The actual bus that is wrapped inside this mutex.
     type Bus;
 
-    /// The error returned by `try_lock`
-    type Error;
+    /// The error returned by `try_lock`
+    type Error;
+
+    /// Create a new mutex of this type.
+    fn new(v: Self::Bus) -> Self;
+
+    /// Consume the mutex and return the wrapped bus.
+    fn into_inner(self) -> Self::Bus;
+}
+
+/// A "normal" synchronous mutex like `std::sync::Mutex`.
+///
+/// If the std feature is enabled, this is implemented for `std::sync::Mutex`.
+#[cfg(feature = "sync")]
+pub trait SyncMutex: MutexBase {
+    /// Lock the mutex.
+    fn lock(&self) -> Result<impl DerefMut<Target = Self::Bus>, Self::Error>;
+}
+
+/// An asynchronous mutex like `embassy_sync::mutex::Mutex`.
+///
+/// If the embassy feature is enabled, this is implemented for `embassy_sync::mutex::Mutex`.
+/// `embassy_sync::blocking_mutex::Mutex` only gives out access inside a closure and has no guard
+/// that could be held across an await point, so it can't implement this trait.
+#[cfg(feature = "async")]
+pub trait AsyncMutex: MutexBase {
+    /// Lock the mutex.
+    fn lock(&self) -> impl Future<Output = Result<impl DerefMut<Target = Self::Bus>, Self::Error>>;
+
+    /// Lock the mutex, or give up once `timeout` completes.
+    ///
+    /// Returns `None` if `timeout` completed before the lock was acquired.
+    ///
+    /// The default implementation ignores `timeout` and just waits for [`Self::lock`],
+    /// so it never returns `None`. Backends that can abandon a pending lock (like
+    /// `embassy_sync::mutex::Mutex` and [`FairMutex`]) override it to race the lock against `timeout`.
+    fn lock_timeout(
+        &self,
+        timeout: impl Future<Output = ()>,
+    ) -> impl Future<Output = Option<Result<impl DerefMut<Target = Self::Bus>, Self::Error>>> {
+        drop(timeout);
+        async { Some(self.lock().await) }
+    }
+}
+
+/// An [`AsyncMutex`] whose guard can be projected into a part of the bus.
+///
+/// This is for composite bus types, e.g. a struct holding the `I2c` bus together with some metadata
+/// (statistics, a power switch, ...): `lock_map` hands out just the `I2c` part, so it can be passed
+/// to code that only knows about the bus. The part stays locked until the returned guard is dropped.
+///
+/// This is optional for mutex implementations; if the embassy feature is enabled,
+/// it is implemented for `embassy_sync::mutex::Mutex` (using `MutexGuard::map`).
+#[cfg(feature = "async")]
+pub trait AsyncMutexMap: AsyncMutex {
+    /// Lock the mutex and return a guard to the part of the bus selected by `f`.
+    fn lock_map<U>(
+        &self,
+        f: impl FnOnce(&mut Self::Bus) -> &mut U,
+    ) -> impl Future<Output = Result<impl DerefMut<Target = U>, Self::Error>>;
+}
+
+#[cfg(feature = "std")]
+impl<T> MutexBase for std::sync::Mutex<T> {
+    type Bus = T;
+    type Error = ();
+
+    fn new(v: Self::Bus) -> Self {
+        Self::new(v)
+    }
+
+    fn into_inner(self) -> Self::Bus {
+        // A poisoned mutex still holds a valid bus.
+        self.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(all(feature = "std", feature = "sync"))]
+impl<T> SyncMutex for std::sync::Mutex<T> {
+    fn lock(&self) -> Result<impl DerefMut<Target = Self::Bus>, Self::Error> {
+        self.lock().or(Err(()))
+    }
+}
+
+/// The error type returned by most operations.
+///
+/// The error can either come from the mutex, or from the bus.
+///
+/// This enum is `#[non_exhaustive]`: new variants may be added without a breaking change,
+/// so matches outside of this crate need a catch-all arm:
+/// ```
+/// # fn describe<M, B>(e: pca9548a::Error<M, B>) -> &'static str {
+/// match e {
+///     pca9548a::Error::Mutex(_) => "mutex",
+///     pca9548a::Error::Bus(_) => "bus",
+///     _ => "other",
+/// }
+/// # }
+/// ```
+///
+/// To use `?` with an application-wide error type, implement `From` for it once; every method
+/// of this crate then converts at the call boundary. With a concrete bus (and mutex), the impl can be
+/// for that concrete error type; otherwise it is generic over both error types:
+/// ```
+/// # #[cfg(all(feature = "sync", feature = "std"))]
+/// # {
+/// use embedded_hal::i2c::I2c;
+/// use pca9548a::{Pca9548a, SyncMutex};
+///
+/// #[derive(Debug)]
+/// enum AppError {
+///     Mux(String),
+///     Sensor,
+/// }
+///
+/// impl<M: core::fmt::Debug, B: core::fmt::Debug> From<pca9548a::Error<M, B>> for AppError {
+///     fn from(e: pca9548a::Error<M, B>) -> Self {
+///         AppError::Mux(e.to_string())
+///     }
+/// }
+///
+/// fn read_sensor<Mutex>(pca: &Pca9548a<Mutex>) -> Result<u8, AppError>
+/// where
+///     Mutex: SyncMutex,
+///     Mutex::Error: core::fmt::Debug,
+///     Mutex::Bus: I2c,
+/// {
+///     let mut bus = pca.select_mask(1 << 2)?;
+///     let mut value = [0];
+///     bus.write_read(0x48, &[0], &mut value).map_err(|_| AppError::Sensor)?;
+///     Ok(value[0])
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error<Mutex, Bus> {
+    /// Mutex error
+    Mutex(Mutex),
+    /// Bus error
+    Bus(Bus),
+    /// Error of one of the optional pins (see [`PinConfig`])
+    Pin(embedded_hal::digital::ErrorKind),
+    /// The mask read back from the control register differs from the one that was written
+    VerifyFailed {
+        /// The mask that was written
+        expected: u8,
+        /// The mask that was read back
+        actual: u8,
+    },
+    /// A channel id that is out of range was passed to a fallible method
+    InvalidChannel(u8),
+    /// A mask with channels beyond the channel count was selected in strict mode
+    /// (see [`Pca9548a::with_strict_mask`])
+    InvalidMask(u8),
+    /// The operation did not complete in time (see [`Pca9548a::select_mask_timeout_async`])
+    Timeout,
+    /// The register and value buffers passed to [`Pca9548a::read_registers`] differ in length
+    InvalidLength {
+        /// The number of registers
+        regs: usize,
+        /// The length of the value buffer
+        out: usize,
+    },
+}
+
+impl<Mutex, Bus> embedded_hal::i2c::Error for Error<Mutex, Bus>
+where
+    Mutex: core::fmt::Debug,
+    Bus: embedded_hal::i2c::Error,
+{
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        match self {
+            Error::Mutex(_) => embedded_hal::i2c::ErrorKind::Overrun,
+            Error::Bus(e) => e.kind(),
+            Error::Pin(_)
+            | Error::VerifyFailed { .. }
+            | Error::InvalidChannel(_)
+            | Error::InvalidMask(_)
+            | Error::Timeout
+            | Error::InvalidLength { .. } => embedded_hal::i2c::ErrorKind::Other,
+        }
+    }
+}
+
+impl<Mutex: core::fmt::Debug, Bus: core::fmt::Debug> core::fmt::Display for Error<Mutex, Bus> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Mutex(e) => write!(f, "mutex error: {e:?}"),
+            Error::Bus(e) => write!(f, "bus error: {e:?}"),
+            Error::Pin(e) => write!(f, "pin error: {e:?}"),
+            Error::VerifyFailed { expected, actual } => write!(
+                f,
+                "control register is {actual:#04x} instead of {expected:#04x}"
+            ),
+            Error::InvalidChannel(id) => write!(f, "invalid channel id {id}"),
+            Error::InvalidMask(mask) => write!(f, "invalid mask {mask:#04x}"),
+            Error::Timeout => f.write_str("timed out"),
+            Error::InvalidLength { regs, out } => {
+                write!(f, "{regs} registers but {out} values")
+            }
+        }
+    }
+}
+
+impl<Mutex: core::fmt::Debug, Bus: core::fmt::Debug> core::error::Error for Error<Mutex, Bus> {}
+
+/// An error together with the tag of the mux it came from.
+///
+/// Returned by [`Pca9548a::tag_err`], to attribute errors to the right chip in systems with several muxes.
+/// Formats as `<tag>: <error>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tagged<E> {
+    /// The tag of the mux, see [`Pca9548a::with_tag`]
+    pub tag: &'static str,
+    /// The wrapped error
+    pub error: E,
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for Tagged<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {}", self.tag, self.error)
+    }
+}
+
+impl<E: core::error::Error> core::error::Error for Tagged<E> {}
+
+impl<E: embedded_hal::i2c::Error> embedded_hal::i2c::Error for Tagged<E> {
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        self.error.kind()
+    }
+}
+
+/// The [`Error`] returned by the methods of a [`Pca9548a`] that uses `Mutex`.
+///
+/// This is shorthand for `Error<MutexError<Mutex>, I2cError<Mutex>>`:
+/// ```
+/// # #[cfg(feature = "sync")]
+/// # {
+/// use embedded_hal::i2c::I2c;
+/// use pca9548a::{BusError, Pca9548a, SyncMutex};
+///
+/// fn select_sensor<Mutex>(pca: &Pca9548a<Mutex>) -> Result<(), BusError<Mutex>>
+/// where
+///     Mutex: SyncMutex,
+///     Mutex::Bus: I2c,
+/// {
+///     pca.select_mask(1 << 2).map(drop)
+/// }
+/// # }
+/// ```
+pub type BusError<Mutex> = Error<MutexError<Mutex>, I2cError<Mutex>>;
+
+/// The error of locking `Mutex`, i.e. `<Mutex as MutexBase>::Error`.
+///
+/// Returned e.g. by [`Pca9548a::bus`], and wrapped in [`Error::Mutex`].
+pub type MutexError<Mutex> = <Mutex as MutexBase>::Error;
+
+/// The error of the bus wrapped in `Mutex`, i.e. `<<Mutex as MutexBase>::Bus as ErrorType>::Error`.
+///
+/// Wrapped in [`Error::Bus`]:
+/// ```
+/// # #[cfg(feature = "sync")]
+/// # {
+/// use embedded_hal::i2c::I2c;
+/// use pca9548a::{Error, I2cError, Pca9548a, SyncMutex};
+///
+/// /// Select a sensor, treating a failed lock like a missing sensor.
+/// fn select_sensor<Mutex>(pca: &Pca9548a<Mutex>) -> Result<bool, I2cError<Mutex>>
+/// where
+///     Mutex: SyncMutex,
+///     Mutex::Bus: I2c,
+/// {
+///     match pca.select_mask(1 << 2) {
+///         Ok(_) => Ok(true),
+///         Err(Error::Bus(e)) => Err(e),
+///         Err(_) => Ok(false),
+///     }
+/// }
+/// # }
+/// ```
+pub type I2cError<Mutex> = <<Mutex as MutexBase>::Bus as ErrorType>::Error;
+
+/// Coordinates channel selection with other bus masters.
+///
+/// On a multi-master bus other masters may change the selection at any time.
+/// This cannot be prevented by this crate, but if you have an out-of-band arbitration mechanism
+/// (e.g. a shared GPIO semaphore or an RTOS event), you can plug it in with this trait
+/// (see [`Pca9548a::with_coordinator`]).
+///
+/// Sequencing guarantees for every write of the control register:
+/// 1. The bus lock is acquired.
+/// 2. [`Self::acquire`] is called.
+/// 3. The mask is written to the control register.
+/// 4. [`Self::release`] is called, even if the write failed or its future was cancelled.
+///
+/// The hooks are only called around the control register write, not for transactions performed
+/// on the returned bus lock afterwards.
+/// The hooks are also called in async contexts, so they should not block for long.
+///
+/// The default implementation of both hooks is a no-op; `()` uses it.
+pub trait SelectionCoordinator {
+    /// Called right before the control register is written.
+    fn acquire(&self) {}
+
+    /// Called right after the control register was written.
+    fn release(&self) {}
+}
+
+impl SelectionCoordinator for () {}
+
+/// Observes the selects of a [`Pca9548a`], e.g. to record or assert the select sequence in tests.
+///
+/// Attach it with [`Pca9548a::with_observer`]. [`Self::on_select`] is called after every successful
+/// write of the control register, with the bus lock still held. Failed writes are not reported.
+/// Unlike [`SelectionCoordinator`], the observer can't influence the select.
+///
+/// The default implementation is a no-op; `()` uses it, so an unused observer costs nothing.
+pub trait SelectObserver {
+    /// Called after `mask` was written to the control register.
+    fn on_select(&self, _mask: u8) {}
+}
+
+impl SelectObserver for () {}
+
+/// The address mode of the control register of a [`Pca9548a`].
+///
+/// This is [`SevenBitAddress`] for the PCA9548A itself. [`TenBitAddress`] is for exotic boards
+/// where a bridge or level shifter re-addresses the mux (see [`Pca9548a::new_ten_bit`]);
+/// the bus must then implement `I2c<TenBitAddress>` as well.
+/// The devices behind the mux are always addressed with 7 bits.
+pub trait ControlAddress: embedded_hal::i2c::AddressMode + Copy + core::fmt::LowerHex {
+    /// Get the address as a 7-bit address, or `None` if it is a 10-bit one.
+    fn seven_bit(self) -> Option<SevenBitAddress>;
+}
+
+impl ControlAddress for SevenBitAddress {
+    fn seven_bit(self) -> Option<SevenBitAddress> {
+        Some(self)
+    }
+}
+
+impl ControlAddress for TenBitAddress {
+    fn seven_bit(self) -> Option<SevenBitAddress> {
+        None
+    }
+}
+
+/// The Pca9548a is an i2c multiplexer device.
+pub struct Pca9548a<
+    Mutex,
+    Coordinator = (),
+    Pins = PinConfig,
+    Observer = (),
+    Address = SevenBitAddress,
+> {
+    bus: Mutex,
+    address: Address,
+    coordinator: Coordinator,
+    observer: Observer,
+    cache: MaskCache,
+    last_error: ErrorLatch,
+    mask_policy: MaskPolicy,
+    pins: Pins,
+    tag: &'static str,
+}
+
+impl<Mutex: MutexBase> Pca9548a<Mutex> {
+    /// Create a new instance.
+    pub fn new(bus: Mutex::Bus, address: u8) -> Self {
+        Self::from_mutex(Mutex::new(bus), address)
+    }
+
+    /// Create a new instance from an already constructed mutex around the bus.
+    ///
+    /// Use this if the mutex has to be created elsewhere, e.g. an embassy mutex
+    /// that is initialized in a `const` context.
+    pub fn from_mutex(mutex: Mutex, address: u8) -> Self {
+        Self {
+            bus: mutex,
+            address,
+            coordinator: (),
+            observer: (),
+            cache: MaskCache::new(),
+            last_error: ErrorLatch::new(),
+            mask_policy: MaskPolicy::new(),
+            pins: PinConfig::default(),
+            tag: "",
+        }
+    }
+
+    /// Create a new instance in `cell` and return the `'static` reference to it.
+    ///
+    /// This is for embassy tasks, which only take `'static` arguments: the returned reference hands out
+    /// [`StaticSubBus`]es with [`Self::static_subbus`]. Declare the cell as a `static`:
+    /// ```
+    /// # #[cfg(all(feature = "embassy", feature = "std"))]
+    /// # {
+    /// use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+    /// use embedded_hal_mock::eh1::i2c::Mock;
+    /// use pca9548a::{Channel, Pca9548a, StaticSubBus, BASE_ADDRESS};
+    /// use static_cell::StaticCell;
+    ///
+    /// type Bus = Mutex<CriticalSectionRawMutex, Mock>;
+    /// static PCA: StaticCell<Pca9548a<Bus>> = StaticCell::new();
+    ///
+    /// let mut i2c = Mock::new(&[]);
+    /// let pca: &'static Pca9548a<Bus> = Pca9548a::new_static(&PCA, i2c.clone(), BASE_ADDRESS);
+    /// let subbus: StaticSubBus<Bus> = pca.static_subbus(Channel::C3);
+    /// # i2c.done();
+    /// # }
+    /// ```
+    ///
+    /// Panics if `cell` was already initialized, like [`StaticCell::init`](static_cell::StaticCell::init).
+    #[cfg(feature = "static-cell")]
+    pub fn new_static(
+        cell: &'static static_cell::StaticCell<Self>,
+        bus: Mutex::Bus,
+        address: u8,
+    ) -> &'static Self {
+        cell.init(Self::new(bus, address))
+    }
+
+    /// Create a new instance whose control register is accessed with a 10-bit address.
+    ///
+    /// See [`ControlAddress`] for more info.
+    pub fn new_ten_bit(
+        bus: Mutex::Bus,
+        address: TenBitAddress,
+    ) -> Pca9548a<Mutex, (), PinConfig, (), TenBitAddress> {
+        Pca9548a {
+            bus: Mutex::new(bus),
+            address,
+            coordinator: (),
+            observer: (),
+            cache: MaskCache::new(),
+            last_error: ErrorLatch::new(),
+            mask_policy: MaskPolicy::new(),
+            pins: PinConfig::default(),
+            tag: "",
+        }
+    }
+}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+impl Pca9548a<()> {
+    /// Create a mux without a bus of its own, to be used in a [`MuxGroup`].
+    ///
+    /// Configure it with the usual builder methods and pass it to [`MuxGroup::from_muxes`].
+    pub fn group_member(address: u8) -> GroupMember {
+        Self {
+            bus: (),
+            address,
+            coordinator: (),
+            observer: (),
+            cache: MaskCache::new(),
+            last_error: ErrorLatch::new(),
+            mask_policy: MaskPolicy::new(),
+            pins: PinConfig::default(),
+            tag: "",
+        }
+    }
+}
+
+/// Formats the device as `PCA9548A@<address>`, to identify it in log messages.
+/// If a tag is set (see [`Pca9548a::with_tag`]), it is appended as `PCA9548A@<address> (<tag>)`.
+///
+/// ```
+/// # #[cfg(feature = "std")]
+/// # {
+/// use embedded_hal_mock::eh1::i2c::Mock;
+/// use pca9548a::Pca9548a;
+///
+/// let mut bus = Mock::new(&[]);
+/// let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), 0x71);
+/// assert_eq!(pca.to_string(), "PCA9548A@0x71");
+/// assert_eq!(pca.with_tag("imu").to_string(), "PCA9548A@0x71 (imu)");
+/// # bus.done();
+/// # }
+/// ```
+impl<Mutex, Coordinator, Pins, Observer, Address: ControlAddress> core::fmt::Display
+    for Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "PCA9548A@{:#04x}", self.address)?;
+        if !self.tag.is_empty() {
+            write!(f, " ({})", self.tag)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Use `coordinator` to coordinate selections with other bus masters.
+    ///
+    /// See [`SelectionCoordinator`] for more info.
+    pub fn with_coordinator<C: SelectionCoordinator>(
+        self,
+        coordinator: C,
+    ) -> Pca9548a<Mutex, C, Pins, Observer, Address> {
+        Pca9548a {
+            bus: self.bus,
+            address: self.address,
+            coordinator,
+            observer: self.observer,
+            cache: self.cache,
+            last_error: self.last_error,
+            mask_policy: self.mask_policy,
+            pins: self.pins,
+            tag: self.tag,
+        }
+    }
+
+    /// Use `observer` to observe every successful select.
+    ///
+    /// See [`SelectObserver`] for more info.
+    pub fn with_observer<O: SelectObserver>(
+        self,
+        observer: O,
+    ) -> Pca9548a<Mutex, Coordinator, Pins, O, Address> {
+        Pca9548a {
+            bus: self.bus,
+            address: self.address,
+            coordinator: self.coordinator,
+            observer,
+            cache: self.cache,
+            last_error: self.last_error,
+            mask_policy: self.mask_policy,
+            pins: self.pins,
+            tag: self.tag,
+        }
+    }
+
+    /// Tag this mux with `tag`, e.g. `"imu"`, to tell it apart from other muxes in log messages.
+    ///
+    /// The tag is shown by the `Display` implementation and attached to errors by [`Self::tag_err`].
+    pub fn with_tag(mut self, tag: &'static str) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    /// Get the tag set with [`Self::with_tag`], or `""` if none was set.
+    pub fn tag(&self) -> &'static str {
+        self.tag
+    }
+
+    /// Attach the tag of this mux to the error of `result`.
+    ///
+    /// ```
+    /// # #[cfg(all(feature = "sync", feature = "std"))]
+    /// # {
+    /// use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    /// use pca9548a::{Pca9548a, BASE_ADDRESS};
+    ///
+    /// let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![1])
+    ///     .with_error(embedded_hal::i2c::ErrorKind::Other)]);
+    /// let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_tag("imu");
+    ///
+    /// let error = pca.tag_err(pca.select_mask(1)).err().unwrap();
+    /// assert_eq!(error.tag, "imu");
+    /// # bus.done();
+    /// # }
+    /// ```
+    pub fn tag_err<T, E>(&self, result: Result<T, E>) -> Result<T, Tagged<E>> {
+        result.map_err(|error| Tagged {
+            tag: self.tag,
+            error,
+        })
+    }
+
+    /// Get the coordinator set with [`Self::with_coordinator`], e.g. to reconfigure it at runtime.
+    pub fn coordinator(&self) -> &Coordinator {
+        &self.coordinator
+    }
+
+    /// Get a reference to the wrapped mutex, for mutex-specific methods this crate doesn't expose
+    /// (e.g. `try_lock` of an embassy mutex, or `is_poisoned` of a `std::sync::Mutex`).
+    ///
+    /// *Note:* Locking the bus through it bypasses the select logic, like [`Self::raw_bus`] but without
+    /// invalidating the cached mask: don't change the selection with it.
+    pub fn inner_mutex(&self) -> &Mutex {
+        &self.bus
+    }
+
+    /// Record the most recent error of this device, for diagnostics.
+    ///
+    /// Errors are usually propagated up through several layers of drivers and mapped on the way.
+    /// With the latch enabled, the kind of the original error is also kept here, so e.g. a background
+    /// health task can surface problems (see [`Self::last_error`]).
+    ///
+    /// Only the most recent error is stored; every error overwrites the previous one.
+    /// Errors of transactions you perform on a returned bus lock yourself are not recorded.
+    pub fn with_error_latch(self) -> Self {
+        self.last_error.enable();
+        self
+    }
+
+    /// Get the most recent error recorded since the latch was enabled or cleared.
+    ///
+    /// Always returns `None` if the latch is not enabled (see [`Self::with_error_latch`]).
+    pub fn last_error(&self) -> Option<LastError> {
+        self.last_error.get()
+    }
+
+    /// Forget the recorded error.
+    pub fn clear_last_error(&self) {
+        self.last_error.clear();
+    }
+
+    /// Limit the selectable channels to the first `count` ones, for family members with fewer channels
+    /// (e.g. 4 for the PCA9546A).
+    ///
+    /// The default is [`CHANNELS`]. Masks are checked every time they are written to the control register:
+    /// by default, bits of channels beyond the count are cleared before the write, as they are meaningless.
+    /// To get [`Error::InvalidMask`] instead, enable the strict mode with [`Self::with_strict_mask`].
+    ///
+    /// Panics if `count` is greater than [`CHANNELS`]. With the no-panic feature, it is clamped instead.
+    pub fn with_channel_count(mut self, count: u8) -> Self {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(usize::from(count) <= CHANNELS);
+        self.mask_policy.set_channel_count(count);
+        self
+    }
+
+    /// Get the number of channels that can be enabled at the same time, e.g. to decide whether a broadcast
+    /// to several channels is possible.
+    ///
+    /// Family members like the PCA9548A and PCA9546A accept arbitrary masks, so this is the channel count
+    /// (see [`Self::with_channel_count`]). With [`Self::with_single_channel`] (e.g. for the PCA9547), it is `1`.
+    pub fn max_simultaneous_channels(&self) -> u8 {
+        let count = self.mask_policy.valid().count_ones() as u8;
+        if self.mask_policy.single_channel {
+            count.min(1)
+        } else {
+            count
+        }
+    }
+
+    /// Use the control register layout of single-channel muxes like the PCA9547: bit 3 enables the channel
+    /// whose id is in bits 0..=2, so at most one channel can be selected at a time.
+    ///
+    /// Masks passed to and returned by this crate stay the usual ones, with at most one bit set;
+    /// a mask with several channels (including the sticky ones, see [`Self::set_sticky_channels`])
+    /// is rejected with [`Error::InvalidMask`] and nothing is written.
+    pub fn with_single_channel(mut self) -> Self {
+        self.mask_policy.single_channel = true;
+        self
+    }
+
+    /// Reject masks with channels beyond the channel count (see [`Self::with_channel_count`])
+    /// with [`Error::InvalidMask`], instead of clearing those bits.
+    ///
+    /// Nothing is written to the control register in that case.
+    pub fn with_strict_mask(mut self) -> Self {
+        self.mask_policy.strict = true;
+        self
+    }
+
+    /// Verify selects with a separate write and read, instead of a single `write_read`.
+    ///
+    /// This is a fallback for buses that don't handle the repeated start of a `write_read` to the mux,
+    /// see [`Self::select_mask_verified_guard_async`].
+    pub fn with_split_verify(mut self) -> Self {
+        self.mask_policy.split_verify = true;
+        self
+    }
+
+    /// Only report interrupt flags that are set in `reads` consecutive reads, see [`Self::poll_interrupts_async`].
+    ///
+    /// The default is `1`, i.e. no debouncing; `0` is treated as `1`.
+    pub fn with_interrupt_debounce(mut self, reads: u8) -> Self {
+        self.mask_policy.interrupt_debounce = reads.max(1);
+        self
+    }
+
+    /// Invert every byte written to and read from the control register, for boards with an
+    /// inverting buffer in front of the control logic of the device.
+    ///
+    /// Masks passed to and returned by this crate (including the cached mask and the masks reported
+    /// to a [`SelectObserver`]) stay the logical ones; only the bytes on the bus are inverted.
+    pub fn with_inverted_mask(mut self) -> Self {
+        self.mask_policy.inverted = true;
+        self
+    }
+
+    /// Keep the channels of `mask` enabled on every select, in addition to the requested ones.
+    ///
+    /// Use this for a device shared by all channels, e.g. a temperature sensor on a channel that must
+    /// stay connected: after `set_sticky_channels(1 << 7)`, `select_single(2)` writes `0b1000_0100`.
+    /// Selecting an empty mask (e.g. [`Self::deselect`]) leaves only the sticky channels enabled.
+    /// The explicit deselects still disable all channels: [`Self::reinit`], the [`Self::parent_subbus`],
+    /// the probes of [`Self::detect_crosstalk`], [`Selected::close`], [`MuxSession::close`] and a [`CancellationGuard`].
+    ///
+    /// The cached mask and the masks reported to a [`SelectObserver`] include the sticky channels.
+    /// Pass an empty mask to clear them.
+    pub fn set_sticky_channels(&mut self, mask: impl Into<ChannelMask>) {
+        self.mask_policy.sticky = mask.into().bits();
+    }
+
+    /// Get the *cached* mask, i.e. what this crate believes the control register contains.
+    ///
+    /// This is the mask of the last successful select or read, and does not cause any bus traffic.
+    /// Returns `None` if the mask is unknown, e.g. before the first select or after a failed write.
+    ///
+    /// *Note:* The cached mask can diverge from the hardware, e.g. after an external reset
+    /// of the device or if another bus master changes the selection.
+    /// Use `read_mask` to read the actual value from the device.
+    pub fn cached_mask(&self) -> Option<u8> {
+        self.cache.get()
+    }
+
+    /// Set the cached mask to `mask` without any bus traffic.
+    ///
+    /// This is for systems where another agent (e.g. a coprocessor) changes the selection out of band:
+    /// tell the crate what the control register now contains, so [`Self::cached_mask`] and the methods
+    /// that rely on the cache (like [`Self::switch_to`]) reflect it. Nothing is written to or read from the device,
+    /// and the mask is not checked against the channel count; the cache only affects the methods that consult it.
+    /// The observer is not notified, as no select took place.
+    /// See [`Self::forget_cached_mask`] for recovering from a reset.
+    pub fn assume_mask(&self, mask: impl Into<ChannelMask>) {
+        self.cache.set(mask.into().bits());
+    }
+
+    /// Forget the cached mask without any bus traffic, so it becomes unknown (`None`).
+    ///
+    /// Afterwards the methods that rely on the cache (like [`Self::switch_to`]) write the control register again,
+    /// even if the requested mask matches the stale belief.
+    ///
+    /// Recovering from a (suspected) reset of the device, e.g. by a glitch or a shared reset line:
+    /// * [`Self::forget_cached_mask`] if the selection is unknown: the next select writes in any case.
+    /// * [`Self::assume_mask`] if the selection is known, e.g. `0` after a power-on reset.
+    /// * [`Self::reinit`] to actively restore a known state: it deselects all channels and confirms that
+    ///   the device responds (with a reset pin, it resets the device first).
+    pub fn forget_cached_mask(&self) {
+        self.cache.invalidate();
+    }
+
+    /// Get a subbus from this device.
+    ///
+    /// * `mask` The mask to use for the subbus, see [`ChannelMask`] for the accepted forms
+    ///
+    /// See [`SubBus`] for more info.
+    pub fn subbus(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> SubBus<'_, Mutex, Coordinator, Pins, Observer, Address> {
+        SubBus {
+            pca: self,
+            mask: mask.into().bits(),
+            exact: false,
+            invalid_channel: None,
+            max_chunk: usize::MAX,
+        }
+    }
+
+    /// Get a subbus from a device stored in a `static`.
+    ///
+    /// * `mask` The mask to use for the subbus, see [`ChannelMask`] for the accepted forms
+    ///
+    /// The subbus borrows the device for `'static`, so it can be passed to a spawned task
+    /// (e.g. `#[embassy_executor::task]`), which can't take non-`'static` references.
+    /// Put the device in a `static` with e.g. `static_cell::StaticCell`.
+    ///
+    /// See [`StaticSubBus`] and the `embassy_tasks` example for more info.
+    pub fn static_subbus(
+        &'static self,
+        mask: impl Into<ChannelMask>,
+    ) -> StaticSubBus<Mutex, Coordinator, Pins, Observer, Address> {
+        self.subbus(mask)
+    }
+
+    /// Get a subbus for the devices on the parent bus, i.e. upstream of the mux.
+    ///
+    /// This has the same type as the subbuses of the channels, so code that addresses all devices
+    /// through a [`SubBus`] can also address the ones on the parent bus.
+    /// Every call deselects all channels (including the sticky ones, see [`Self::set_sticky_channels`])
+    /// before it forwards to the device: otherwise, a device with the same address on a still enabled channel
+    /// would also respond (address shadowing).
+    pub fn parent_subbus(&self) -> SubBus<'_, Mutex, Coordinator, Pins, Observer, Address> {
+        SubBus {
+            exact: true,
+            ..self.subbus(ChannelMask::NONE)
+        }
+    }
+
+    /// Get a subbus with a single channel enabled.
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    ///
+    /// See [`SubBus`] for more info.
+    ///
+    /// # Panics
+    /// Panics if `id` is out of range. With the no-panic feature, every operation of the returned subbus
+    /// fails with [`Error::InvalidChannel`] instead; see also [`Self::try_single_subbus`].
+    #[track_caller]
+    pub fn single_subbus(&self, id: u8) -> SubBus<'_, Mutex, Coordinator, Pins, Observer, Address> {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(id < 8);
+        if id < 8 {
+            self.subbus(1 << id)
+        } else {
+            SubBus {
+                invalid_channel: Some(id),
+                ..self.subbus(ChannelMask::NONE)
+            }
+        }
+    }
+
+    /// Get a subbus with the single channel `ID` enabled.
+    ///
+    /// Like [`Self::single_subbus`], but `ID` must be in range 0..=7 at compile time, so there is neither a panic path
+    /// nor a subbus that fails with the no-panic feature:
+    /// ```compile_fail
+    /// # use pca9548a::{Pca9548a, BASE_ADDRESS};
+    /// # let i2c_bus = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+    /// let pca = Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, BASE_ADDRESS);
+    /// let subbus = pca.single_subbus_const::<9>();
+    /// ```
+    ///
+    /// See [`SubBus`] for more info.
+    pub fn single_subbus_const<const ID: u8>(
+        &self,
+    ) -> SubBus<'_, Mutex, Coordinator, Pins, Observer, Address> {
+        const { assert!(ID < 8, "ID must be in range 0..=7") };
+        self.subbus(1 << ID)
+    }
+
+    /// Get a subbus with a single channel enabled.
+    ///
+    /// Like [`Self::single_subbus`], but the channel is valid by construction, so this never panics.
+    ///
+    /// See [`SubBus`] for more info.
+    pub fn channel_subbus(
+        &self,
+        channel: Channel,
+    ) -> SubBus<'_, Mutex, Coordinator, Pins, Observer, Address> {
+        self.subbus(channel)
+    }
+
+    /// Get a subbus with a single channel enabled, or an error if `id` is out of range.
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    ///
+    /// See [`SubBus`] for more info.
+    pub fn try_single_subbus(
+        &self,
+        id: u8,
+    ) -> Result<SubBus<'_, Mutex, Coordinator, Pins, Observer, Address>, InvalidChannel> {
+        if id < 8 {
+            Ok(self.subbus(1 << id))
+        } else {
+            Err(InvalidChannel(id))
+        }
+    }
+
+    /// Get an array with a single-channel subbus for each of the first `N` channels.
+    ///
+    /// Element `i` of the array is the subbus of channel `i`. `N` must not be greater than [`CHANNELS`];
+    /// this is checked at compile time:
+    /// ```compile_fail
+    /// # use pca9548a::{Pca9548a, BASE_ADDRESS};
+    /// # let i2c_bus = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+    /// let pca = Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, BASE_ADDRESS);
+    /// let subbuses = pca.channels_const::<9>();
+    /// ```
+    ///
+    /// See [`SubBus`] for more info.
+    pub fn channels_const<const N: usize>(
+        &self,
+    ) -> [SubBus<'_, Mutex, Coordinator, Pins, Observer, Address>; N] {
+        const { assert!(N <= CHANNELS, "N must not be greater than CHANNELS") };
+        core::array::from_fn(|id| self.subbus(1 << id))
+    }
+}
+
+impl<Mutex: MutexBase, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Transform the wrapped bus, e.g. to insert a logging or rate-limiting adapter.
+    ///
+    /// The bus is taken out of the mutex (see [`MutexBase::into_inner`]), transformed with `f`
+    /// and wrapped into a new mutex of type `NewMutex`.
+    /// `NewMutex` is a type parameter, so it usually has to be named, e.g. `map_bus::<std::sync::Mutex<_>>(..)`;
+    /// its `Bus` must be the type returned by `f`.
+    ///
+    /// The address, coordinator, observer, pins and the cached mask are kept.
+    pub fn map_bus<NewMutex: MutexBase>(
+        self,
+        f: impl FnOnce(Mutex::Bus) -> NewMutex::Bus,
+    ) -> Pca9548a<NewMutex, Coordinator, Pins, Observer, Address> {
+        Pca9548a {
+            bus: NewMutex::new(f(self.bus.into_inner())),
+            address: self.address,
+            coordinator: self.coordinator,
+            observer: self.observer,
+            cache: self.cache,
+            last_error: self.last_error,
+            mask_policy: self.mask_policy,
+            pins: self.pins,
+            tag: self.tag,
+        }
+    }
+
+    /// Replace the wrapped bus with `new_bus` and return the old one.
+    ///
+    /// The new bus is wrapped into a new mutex (see [`MutexBase::new`]), so this needs `&mut self`.
+    /// The cached mask is forgotten, as the mux on the new bus may have any selection.
+    /// This is e.g. for swapping in a differently programmed mock in a test, or for moving the mux
+    /// to another physical bus at runtime.
+    pub fn swap_bus(&mut self, new_bus: Mutex::Bus) -> Mutex::Bus {
+        self.cache.invalidate();
+        core::mem::replace(&mut self.bus, Mutex::new(new_bus)).into_inner()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Mutex: AsyncMutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Get a lock on the bus using an `AsyncMutex`
+    ///
+    /// No mask is written, the channels selected last stay connected.
+    /// If you write to the control register yourself, use [`Self::raw_bus_async`] instead.
+    pub async fn bus_async(
+        &self,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, MutexError<Mutex>> {
+        self.bus.lock().await
+    }
+
+    /// Get a lock on the bus without selecting any channel, for talking to the mux or the parent bus directly.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::raw_bus`].
+    ///
+    /// Like [`Self::bus_async`], but the cached mask is invalidated (see [`Self::cached_mask`]),
+    /// so issuing your own mux commands on the returned lock can't leave a stale mask behind.
+    /// Devices on the parent bus are reachable regardless of the selection, see [`Self::raw_bus`] for an example.
+    pub async fn raw_bus_async(
+        &self,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, Mutex::Error> {
+        let bus = self.bus.lock().await?;
+        self.cache.invalidate();
+        Ok(bus)
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex: SyncMutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Get a lock on the bus using an `SyncMutex`
+    ///
+    /// No mask is written, the channels selected last stay connected.
+    /// If you write to the control register yourself, use [`Self::raw_bus`] instead.
+    pub fn bus(&self) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, MutexError<Mutex>> {
+        self.bus.lock()
+    }
+
+    /// Get a lock on the bus without selecting any channel, for talking to the mux or the parent bus directly.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::raw_bus_async`].
+    ///
+    /// ```
+    /// # #[cfg(feature = "sync")]
+    /// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) {
+    /// use embedded_hal::i2c::I2c;
+    /// use pca9548a::{Pca9548a, BASE_ADDRESS};
+    ///
+    /// let pca = Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, BASE_ADDRESS);
+    /// let mut bus = pca.raw_bus().unwrap();
+    /// bus.write(0x20, &[0x01]).unwrap(); // A device on the parent bus
+    /// # }
+    /// ```
+    ///
+    /// *Note:* see [`Self::raw_bus_async`] for more info.
+    pub fn raw_bus(&self) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, Mutex::Error> {
+        let bus = self.bus.lock()?;
+        self.cache.invalidate();
+        Ok(bus)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<
+        Mutex,
+        Coordinator: SelectionCoordinator,
+        Pins,
+        Observer: SelectObserver,
+        Address: ControlAddress,
+    > Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Write `mask` to the control register on `bus` as is, like [`Self::write_mask_exact_async`].
+    ///
+    /// This doesn't need the bus of this mux, so the muxes of a [`MuxGroup`] use it with the shared bus.
+    pub(crate) async fn write_mask_exact_on_async<E, Bus: embedded_hal_async::i2c::I2c<Address>>(
+        &self,
+        bus: &mut Bus,
+        mask: u8,
+    ) -> Result<(), Error<E, Bus::Error>> {
+        self.cache.invalidate();
+        let acquired = Acquired::new(&self.coordinator);
+        let result = bus
+            .write(self.address, &[self.mask_policy.encode(mask)])
+            .await;
+        drop(acquired);
+        result.map_err(|e| self.last_error.bus(e))?;
+        self.cache.set(mask);
+        self.observer.on_select(mask);
+        trace_select!(self.address, mask);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<
+        Mutex: AsyncMutex,
+        Coordinator: SelectionCoordinator,
+        Pins,
+        Observer: SelectObserver,
+        Address: ControlAddress,
+    > Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+{
+    /// Write `mask` to the control register using an already locked bus.
+    ///
+    /// The mask is checked against the channel count first (see [`Self::with_channel_count`]).
+    async fn write_mask_async(
+        &self,
+        bus: &mut Mutex::Bus,
+        mask: u8,
+    ) -> Result<(), BusError<Mutex>> {
+        let mask = self.mask_policy.apply(&self.last_error, mask)?;
+        self.write_mask_exact_async(bus, mask).await
+    }
+
+    /// Write `mask` to the control register as is, using an already locked bus.
+    ///
+    /// Unlike [`Self::write_mask_async`], the sticky channels are not added (see [`Self::set_sticky_channels`]),
+    /// so `NO_CHANNELS` really disables all channels.
+    async fn write_mask_exact_async(
+        &self,
+        bus: &mut Mutex::Bus,
+        mask: u8,
+    ) -> Result<(), BusError<Mutex>> {
+        self.write_mask_exact_on_async(bus, mask).await
+    }
+
+    /// Write `mask`, read the control register back and return the mask read, using an already locked bus.
+    ///
+    /// The write and the read are combined into one `write_read`, unless [`Self::with_split_verify`] is set.
+    async fn write_mask_read_back_async(
+        &self,
+        bus: &mut Mutex::Bus,
+        mask: u8,
+    ) -> Result<u8, BusError<Mutex>> {
+        let mut actual = [0];
+        if self.mask_policy.split_verify {
+            self.write_mask_async(bus, mask).await?;
+            bus.read(self.address, &mut actual)
+                .await
+                .map_err(|e| self.last_error.bus(e))?;
+        } else {
+            let mask = self.mask_policy.apply(&self.last_error, mask)?;
+            self.cache.invalidate();
+            let acquired = Acquired::new(&self.coordinator);
+            let result = bus
+                .write_read(self.address, &[self.mask_policy.encode(mask)], &mut actual)
+                .await;
+            drop(acquired);
+            result.map_err(|e| self.last_error.bus(e))?;
+            self.observer.on_select(mask);
+            trace_select!(self.address, mask);
+        }
+        actual[0] = self.mask_policy.decode(actual[0]);
+        self.cache.set(actual[0]);
+        Ok(actual[0])
+    }
+
+    /// Lock the bus and start a session that can change the selection without releasing the lock.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::session`].
+    ///
+    /// The selection is not changed until you select a mask on the session; see [`MuxSession`] for more info.
+    pub async fn session_async(
+        &self,
+    ) -> Result<
+        MuxSession<
+            '_,
+            Mutex,
+            Coordinator,
+            Pins,
+            Observer,
+            Address,
+            impl DerefMut<Target = Mutex::Bus> + '_,
+        >,
+        Mutex::Error,
+    > {
+        Ok(MuxSession::new(self, self.bus_async().await?))
+    }
+
+    /// Select the subbus and return the lock to the bus.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_mask`].
+    ///
+    /// * `mask` The mask to use for the subbus
+    ///
+    /// *Note:* You can use the returned lock, to perform your transactions on the subbus;
+    /// this makes sure, that the mask is not changed by another task in the meantime.
+    ///
+    /// *Note:* The above guarantee only holds, if `Bus` is not a shared bus (e.g. [shared_bus](https://docs.rs/shared-bus/latest/shared_bus/)).
+    ///
+    /// If the returned future is cancelled (dropped before it completes) during the write, the mux may or may not
+    /// have received the mask. The cached mask is then unknown ([`Self::cached_mask`] returns `None`) and a
+    /// [`SelectionCoordinator`] is still released. Selects never skip the write, so the next select
+    /// re-issues it in any case. To also deselect when a future holding the lock is cancelled later,
+    /// see [`Self::select_mask_cancel_safe_async`].
+    ///
+    /// The `deselect-on-drop` feature only applies to the sync selects; the lock returned here keeps the selection.
+    pub async fn select_mask_async(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.select_mask_inner_async(mask.into().bits(), false)
+            .await
+    }
+
+    /// Lock the bus and write `mask`; with `exact`, the mask is written as is (see [`Self::write_mask_exact_async`]).
+    async fn select_mask_inner_async(
+        &self,
+        mask: u8,
+        exact: bool,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        if exact {
+            self.write_mask_exact_async(&mut bus, mask).await?;
+        } else {
+            self.write_mask_async(&mut bus, mask).await?;
+        }
+        Ok(bus)
+    }
+
+    /// Select all channels and return the lock to the bus, e.g. for a broadcast write.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_all`].
+    ///
+    /// Only the channels within the channel count are enabled (e.g. `0x0F` for 4 channels,
+    /// see [`Self::with_channel_count`]).
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    pub async fn select_all_async(
+        &self,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.select_mask_async(ALL_CHANNELS & self.mask_policy.valid())
+            .await
+    }
+
+    /// Deselect all channels.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::deselect`].
+    ///
+    /// Sticky channels stay enabled, see [`Self::set_sticky_channels`].
+    pub async fn deselect_async(&self) -> Result<(), BusError<Mutex>> {
+        self.select_mask_async(NO_CHANNELS).await.map(drop)
+    }
+
+    /// Switch to `channel`, skipping the write if it is already the only selected channel.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::switch_to`].
+    ///
+    /// The check uses the cached mask (see [`Self::cached_mask`]), so no read is needed. Returns whether the control
+    /// register was written, e.g. to account for the time of the write in timing-sensitive loops.
+    /// If the cached mask is unknown (e.g. after a failed write or [`Self::raw_bus_async`]), the channel is always written.
+    pub async fn switch_to_async(&self, channel: Channel) -> Result<bool, BusError<Mutex>> {
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        let mask = channel.mask().bits();
+        if self.cache.get() == Some(self.mask_policy.apply(&self.last_error, mask)?) {
+            return Ok(false);
+        }
+        self.write_mask_async(&mut bus, mask).await?;
+        Ok(true)
+    }
+
+    /// Select a single subbus and return the lock to the bus.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_single`].
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    ///
+    /// # Panics
+    /// Panics if `id` is out of range. With the no-panic feature, [`Error::InvalidChannel`] is returned instead.
+    /// The id is checked when this is called, not when the future is polled.
+    #[track_caller]
+    pub fn select_single_async(
+        &self,
+        id: u8,
+    ) -> impl Future<Output = Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>>> + '_
+    {
+        let checked = self.check_channel(id);
+        async move {
+            checked?;
+            self.select_mask_async(1 << id).await
+        }
+    }
+
+    /// Select a single subbus and return the lock to the bus.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_channel`].
+    ///
+    /// Like [`Self::select_single_async`], but the channel is valid by construction, so this never panics.
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    pub async fn select_channel_async(
+        &self,
+        channel: Channel,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.select_mask_async(channel).await
+    }
+
+    /// Select several subbuses and return the lock to the bus.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_channels_iter`].
+    ///
+    /// Like [`Self::select_mask_async`] with a slice of ids, but the channels are valid by construction,
+    /// so this never panics: `pca.select_channels_iter_async([Channel::C0, Channel::C3])`.
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    pub async fn select_channels_iter_async(
+        &self,
+        channels: impl IntoIterator<Item = Channel>,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.select_mask_async(channels.into_iter().collect::<ChannelMask>())
+            .await
+    }
+
+    /// Select a single subbus and return the lock to the bus, or [`Error::InvalidChannel`] if `id` is out of range.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::try_select_single`].
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    pub async fn try_select_single_async(
+        &self,
+        id: u8,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        if id >= 8 {
+            return Err(self.last_error.invalid_channel(id));
+        }
+        self.select_mask_async(1 << id).await
+    }
+
+    /// Select the subbus, read the control register back and return the lock to the bus if it matches.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_mask_verified_guard`].
+    ///
+    /// * `mask` The mask to use for the subbus
+    ///
+    /// Write and read-back happen under the same lock that is returned, so the confirmed selection
+    /// is still in place for your transactions. They are sent as a single `write_read` (the mux returns
+    /// its control register on any read, also after a repeated start), which saves a transfer;
+    /// use [`Self::with_split_verify`] for buses that can't do that.
+    /// If the read-back differs, [`Error::VerifyFailed`] is returned and the cached mask is set to the value read.
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    pub async fn select_mask_verified_guard_async(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        let mask = mask.into().bits();
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        let actual = self.write_mask_read_back_async(&mut bus, mask).await?;
+        // Bits beyond the channel count are cleared in non-strict mode, and sticky channels are added.
+        let expected = self.mask_policy.expected(mask);
+        if actual != expected {
+            return Err(self.last_error.verify_failed(expected, actual));
+        }
+        Ok(bus)
+    }
+
+    /// Read the current selection, select `mask` and return the lock to the bus with the previous mask.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_mask_prev`].
+    ///
+    /// * `mask` The mask to use for the subbus
+    ///
+    /// This is for restoring the previous selection manually, e.g. with `select_mask_async(previous)`
+    /// once the returned lock is dropped. Unlike [`Self::select_mask_async`], it always costs
+    /// a read of the control register, so prefer that if you don't need the previous mask.
+    /// Both the read and the write happen under the returned lock.
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    pub async fn select_mask_prev_async(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<(u8, impl DerefMut<Target = Mutex::Bus> + '_), BusError<Mutex>> {
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        let mut previous = [0];
+        bus.read(self.address, &mut previous)
+            .await
+            .map_err(|e| self.last_error.bus(e))?;
+        previous[0] = self.mask_policy.decode(previous[0]);
+        self.cache.set(previous[0]);
+        self.write_mask_async(&mut bus, mask.into().bits()).await?;
+        Ok((previous[0], bus))
+    }
+
+    /// Select the subbus and return a lock to the bus that deselects all channels when dropped.
+    ///
+    /// * `mask` The mask to use for the subbus
+    ///
+    /// Use this instead of [`Self::select_mask_async`] if the returned lock is held across await points
+    /// of a future that may be cancelled. The bus must also implement [`embedded_hal::i2c::I2c`], which is
+    /// used for the best-effort deselect on drop.
+    ///
+    /// See [`CancellationGuard`] for the cancellation-safety semantics.
+    pub async fn select_mask_cancel_safe_async(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<
+        CancellationGuard<
+            '_,
+            Mutex,
+            Coordinator,
+            Pins,
+            Observer,
+            Address,
+            impl DerefMut<Target = Mutex::Bus> + '_,
+        >,
+        BusError<Mutex>,
+    >
+    where
+        Mutex::Bus: embedded_hal::i2c::I2c<Address>,
+    {
+        let bus = self.select_mask_async(mask).await?;
+        Ok(CancellationGuard::new(self, bus))
+    }
+
+    /// Select `mask` and perform a transaction on it.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::transaction_with_mask`].
+    ///
+    /// * `mask` The mask to select; this may enable several channels (e.g. for a broadcast write)
+    /// * `device_addr` The address of the device on the subbus
+    /// * `operations` The operations of the transaction
+    ///
+    /// This is a shortcut for one-shot transactions without creating a [`SubBus`].
+    /// All operations are sent in one transaction after a single select; the select itself can't be
+    /// part of that transaction, as it goes to a different address (see the crate docs on bus traffic).
+    ///
+    /// If `operations` is empty, `mask` is still selected, but no transaction is sent to the device
+    /// and `Ok(())` is returned: some HALs reject empty transactions. [`SubBus`] transactions behave the same.
+    pub async fn transaction_with_mask_async(
+        &self,
+        mask: impl Into<ChannelMask>,
+        device_addr: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), BusError<Mutex>> {
+        self.transaction_with_mask_inner_async(mask.into().bits(), false, device_addr, operations)
+            .await
+    }
+
+    /// See [`Self::transaction_with_mask_async`]; `exact` is passed to [`Self::select_mask_inner_async`].
+    async fn transaction_with_mask_inner_async(
+        &self,
+        mask: u8,
+        exact: bool,
+        device_addr: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), BusError<Mutex>> {
+        let mut bus = self.select_mask_inner_async(mask, exact).await?;
+        if operations.is_empty() {
+            return Ok(());
+        }
+        trace_operation!(self.address, mask, "transaction", device_addr);
+        bus.transaction(device_addr, operations)
+            .await
+            .map_err(|e| self.last_error.bus(e))
+    }
+
+    /// Select `channel` and perform a transaction on it, retrying on transient bus errors.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::transaction_retry`].
+    ///
+    /// * `channel` The channel of the device
+    /// * `device_addr` The address of the device on the subbus
+    /// * `operations` The operations of the transaction
+    /// * `retries` How often to retry after the first attempt
+    ///
+    /// A bus glitch may both corrupt a transaction and reset the mux. So on a bus error other than a NACK
+    /// (see [`embedded_hal::i2c::ErrorKind`]), the lock is released, and the next attempt locks the bus again
+    /// and re-selects the channel before repeating the transaction. NACKs and mutex errors are returned right away,
+    /// as is the last error once all retries are used up.
+    pub async fn transaction_retry_async(
+        &self,
+        channel: Channel,
+        device_addr: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+        retries: u8,
+    ) -> Result<(), BusError<Mutex>> {
+        let mut result = self
+            .transaction_with_mask_async(channel, device_addr, operations)
+            .await;
+        for _ in 0..retries {
+            if !is_transient(&result) {
+                break;
+            }
+            result = self
+                .transaction_with_mask_async(channel, device_addr, operations)
+                .await;
+        }
+        result
+    }
+
+    /// Read a large block from an EEPROM-style device page by page, selecting its channel once.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::read_paged`].
+    ///
+    /// * `channel` The channel of the device
+    /// * `device_addr` The address of the device on the subbus
+    /// * `start` The memory address of the first byte; sent as two bytes, big-endian (e.g. 24C32 and larger)
+    /// * `buf` The buffer to fill, starting at `start`
+    /// * `page_size` The number of bytes read per `write_read`
+    ///
+    /// The bus stays locked for the whole read, and every page is a `write_read` of its memory address
+    /// and the page; the last page may be shorter. Returns on the first error.
+    ///
+    /// Panics if `page_size` is 0. With the no-panic feature, 0 is treated as 1.
+    pub async fn read_paged_async(
+        &self,
+        channel: Channel,
+        device_addr: u8,
+        start: u16,
+        buf: &mut [u8],
+        page_size: usize,
+    ) -> Result<(), BusError<Mutex>> {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(page_size > 0);
+        let mut bus = self.select_mask_async(channel).await?;
+        for (i, page) in buf.chunks_mut(page_size.max(1)).enumerate() {
+            let address = page_address(start, i, page_size);
+            bus.write_read(device_addr, &address, page)
+                .await
+                .map_err(|e| self.last_error.bus(e))?;
+        }
+        Ok(())
+    }
+
+    /// Write `bytes` to `device_addr` on each of the given channels, one channel at a time.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::broadcast_write`].
+    ///
+    /// * `device_addr` The address of the devices on the subbuses
+    /// * `bytes` The bytes to write to each device
+    /// * `channels` The ids of the channels in range 0..=7, in the order they are written
+    ///
+    /// Unlike writing to a subbus with several channels enabled, only one channel is enabled at a time,
+    /// so this also works for devices that would collide if they were enabled together.
+    /// The bus stays locked for the whole broadcast. Returns on the first error.
+    ///
+    /// # Panics
+    /// Panics if any id in `channels` is out of range. With the no-panic feature, [`Error::InvalidChannel`] is returned instead.
+    /// All ids are checked before the first write, when this is called, not when the future is polled.
+    #[track_caller]
+    pub fn broadcast_write_async<'a>(
+        &'a self,
+        device_addr: u8,
+        bytes: &'a [u8],
+        channels: &'a [u8],
+    ) -> impl Future<Output = Result<(), BusError<Mutex>>> + 'a {
+        let checked = self.check_channels(channels);
+        async move {
+            checked?;
+            let mut bus = self
+                .bus_async()
+                .await
+                .map_err(|e| self.last_error.mutex(e))?;
+            for &id in channels {
+                self.write_mask_async(&mut bus, 1 << id).await?;
+                bus.write(device_addr, bytes)
+                    .await
+                    .map_err(|e| self.last_error.bus(e))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Read the current mask from the control register.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::read_mask`].
+    ///
+    /// This does not change the selection, but updates the cached mask (see [`Self::cached_mask`]).
+    pub async fn read_mask_async(&self) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        let mut mask = [0];
+        bus.read(self.address, &mut mask)
+            .await
+            .map_err(|e| self.last_error.bus(e))?;
+        mask[0] = self.mask_policy.decode(mask[0]);
+        self.cache.set(mask[0]);
+        Ok(mask[0])
+    }
+
+    /// Read the interrupt flags of a PCA9543A/PCA9545A.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::read_interrupts`].
+    ///
+    /// Returns a mask of the channels whose `INTx` input is asserted (bit `i` for channel `i`).
+    ///
+    /// On these devices the upper nibble of the control register holds the interrupt flags `INT3..INT0`
+    /// and the lower nibble the channel enable bits. The flags are read-only and just mirror the inputs:
+    /// writing the register can't clear them, and writing back a value read from it would clobber the selection.
+    /// So this only performs a single read; nothing is written and the cached mask is not changed.
+    /// To clear an interrupt, service the interrupting device, see [`Self::ack_interrupts_async`].
+    ///
+    /// The PCA9548A has no interrupt inputs, so the result is meaningless for it.
+    pub async fn read_interrupts_async(&self) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        let mut register = [0];
+        bus.read(self.address, &mut register)
+            .await
+            .map_err(|e| self.last_error.bus(e))?;
+        Ok(self.mask_policy.decode_interrupts(register[0]))
+    }
+
+    /// Read the debounced interrupt flags of a PCA9543A/PCA9544A/PCA9545A.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::poll_interrupts`].
+    ///
+    /// Like [`Self::read_interrupts_async`], but the control register is read as many times as configured
+    /// with [`Self::with_interrupt_debounce`], back to back under one lock, and only the flags set in every read
+    /// are returned. A flag that flickers because of noise on its `INTx` line is thus reported only once it is stable.
+    /// To require a flag to be set for a minimum duration instead, call this repeatedly with a delay in between.
+    pub async fn poll_interrupts_async(&self) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        let mut flags = u8::MAX;
+        for _ in 0..self.mask_policy.interrupt_debounce {
+            let mut register = [0];
+            bus.read(self.address, &mut register)
+                .await
+                .map_err(|e| self.last_error.bus(e))?;
+            flags &= self.mask_policy.decode_interrupts(register[0]);
+        }
+        Ok(flags)
+    }
+
+    /// Acknowledge an interrupt of a PCA9543A/PCA9545A by reading the interrupting device.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::ack_interrupts`].
+    ///
+    /// * `channel` The channel of the interrupting device
+    /// * `device_addr` The address of the interrupting device
+    /// * `write` The bytes sent before reading, e.g. the address of its status register
+    /// * `read` The buffer for the status read from the device
+    ///
+    /// The mux has no way to clear its interrupt flags: an `INTx` flag stays set until the device
+    /// on channel `x` releases its interrupt line, which most devices do when their status is read.
+    /// This selects `channel`, performs a `write_read` on the device and reads the interrupt flags
+    /// again, all under one lock. It returns the flags that are still set (see [`Self::read_interrupts_async`]).
+    /// The channel stays selected afterwards.
+    pub async fn ack_interrupts_async(
+        &self,
+        channel: Channel,
+        device_addr: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self.select_mask_async(channel).await?;
+        bus.write_read(device_addr, write, read)
+            .await
+            .map_err(|e| self.last_error.bus(e))?;
+        let mut register = [0];
+        bus.read(self.address, &mut register)
+            .await
+            .map_err(|e| self.last_error.bus(e))?;
+        Ok(self.mask_policy.decode_interrupts(register[0]))
+    }
+
+    /// Read several one-byte registers of a device, selecting the channel once.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::read_registers`].
+    ///
+    /// * `channel` The channel of the device
+    /// * `device_addr` The address of the device
+    /// * `regs` The registers to read
+    /// * `out` The buffer for the values, `out[i]` receives the value of `regs[i]`
+    ///
+    /// The channel is selected once, then each register is read with a `write_read` of its address,
+    /// all under one lock. On an error, the values read so far are in `out`.
+    ///
+    /// If `regs` and `out` differ in length, [`Error::InvalidLength`] is returned before the channel is selected.
+    pub async fn read_registers_async(
+        &self,
+        channel: Channel,
+        device_addr: u8,
+        regs: &[u8],
+        out: &mut [u8],
+    ) -> Result<(), BusError<Mutex>> {
+        self.check_length(regs, out)?;
+        let mut bus = self.select_mask_async(channel).await?;
+        for (reg, value) in regs.iter().zip(out.chunks_exact_mut(1)) {
+            bus.write_read(device_addr, &[*reg], value)
+                .await
+                .map_err(|e| self.last_error.bus(e))?;
+        }
+        Ok(())
+    }
+
+    /// Read the control register and then read from a device, under one lock.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::read_selection_then`].
+    ///
+    /// * `device_addr` The address of the device on the currently selected channels
+    /// * `read_buf` The buffer to fill with the read from the device
+    ///
+    /// Returns the value of the control register. The selection is not changed, and since the lock is
+    /// held for both reads, no other task can change it in between: the device was read on exactly
+    /// the returned channels. This is useful for diagnostics that must attribute a reading to a channel.
+    pub async fn read_selection_then_async(
+        &self,
+        device_addr: u8,
+        read_buf: &mut [u8],
+    ) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        let mut mask = [0];
+        bus.read(self.address, &mut mask)
+            .await
+            .map_err(|e| self.last_error.bus(e))?;
+        mask[0] = self.mask_policy.decode(mask[0]);
+        self.cache.set(mask[0]);
+        bus.read(device_addr, read_buf)
+            .await
+            .map_err(|e| self.last_error.bus(e))?;
+        Ok(mask[0])
+    }
+
+    /// Get a single-channel subbus for every channel that is currently enabled.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::enabled_subbuses`].
+    ///
+    /// The current selection is read from the device (see [`Self::read_mask_async`]) and is not changed.
+    /// If no channel is enabled, the iterator is empty.
+    pub async fn enabled_subbuses_async(
+        &self,
+    ) -> Result<
+        impl Iterator<Item = (u8, SubBus<'_, Mutex, Coordinator, Pins, Observer, Address>)>,
+        BusError<Mutex>,
+    > {
+        let mask = self.read_mask_async().await?;
+        Ok(mask_to_channels(mask).map(|id| (id, self.subbus(1 << id))))
+    }
+
+    /// Count the channels that are currently enabled.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::selected_count`].
+    ///
+    /// The current selection is read from the device (see [`Self::read_mask_async`]).
+    pub async fn selected_count_async(&self) -> Result<u32, BusError<Mutex>> {
+        Ok(self.read_mask_async().await?.count_ones())
+    }
+
+    /// Check whether the control register holds its power-on default `0x00`, i.e. all channels are off.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::is_default_state`].
+    ///
+    /// As an application normally keeps at least one channel selected, this is a quick way to detect
+    /// that the mux was just reset (e.g. by a glitch or by a reset line shared with another device).
+    /// A selection that is legitimately all-off (e.g. after [`Self::deselect_async`]) also reports `true`.
+    ///
+    /// The current selection is read from the device (see [`Self::read_mask_async`]).
+    pub async fn is_default_state_async(&self) -> Result<bool, BusError<Mutex>> {
+        Ok(self.read_mask_async().await? == NO_CHANNELS)
+    }
+
+    /// Get the ids of the channels that are currently enabled, in ascending order.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::selected_channels`].
+    ///
+    /// The current selection is read from the device (see [`Self::read_mask_async`]).
+    #[cfg(feature = "heapless")]
+    pub async fn selected_channels_async(
+        &self,
+    ) -> Result<heapless::Vec<u8, CHANNELS>, BusError<Mutex>> {
+        Ok(mask_to_channels(self.read_mask_async().await?).collect())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Mutex, Coordinator, IntPin, ResetPin, Delay, Observer, Address>
+    Pca9548a<Mutex, Coordinator, PinConfig<IntPin, ResetPin, Delay>, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Delay: embedded_hal_async::delay::DelayNs + Clone,
+{
+    /// Select a single subbus and wait until a device on it responds.
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    /// * `device_addr` The address of the device that is expected on the subbus
+    /// * `retry_delay_us` The time to wait between two probes in microseconds
+    /// * `max_retries` How often to retry after the first probe failed
+    ///
+    /// Some devices take a few milliseconds to respond after the analog switch of their channel closed.
+    /// This selects the channel once and then probes `device_addr` with an empty write until it is acknowledged.
+    /// Only a NACK is retried, any other bus error is returned right away.
+    /// If the device does not respond after `max_retries` retries, the NACK of the last probe is returned.
+    /// The retries are timed with a clone of the delay configured with [`Self::with_delay`].
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    ///
+    /// # Panics
+    /// Panics if `id` is out of range. With the no-panic feature, [`Error::InvalidChannel`] is returned instead.
+    /// The id is checked when this is called, not when the future is polled.
+    #[track_caller]
+    pub fn select_single_ready_async(
+        &self,
+        id: u8,
+        device_addr: u8,
+        retry_delay_us: u32,
+        max_retries: u32,
+    ) -> impl Future<Output = Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>>> + '_
+    {
+        let checked = self.check_channel(id);
+        async move {
+            checked?;
+            let mut bus = self.select_mask_async(1 << id).await?;
+            let mut delay = self.pins.delay.clone();
+            let mut retries = 0;
+            loop {
+                match bus.write(device_addr, &[]).await {
+                    Ok(()) => return Ok(bus),
+                    Err(e) if retries == max_retries || !scan::is_nack(&e) => {
+                        return Err(self.last_error.bus(e))
+                    }
+                    Err(_) => {
+                        retries += 1;
+                        delay.delay_us(retry_delay_us).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Select the subbus and return the lock to the bus, or give up after `timeout_us` microseconds.
+    ///
+    /// * `mask` The mask to use for the subbus
+    /// * `timeout_us` How long to wait for the lock, and then for the select, in microseconds
+    ///
+    /// Returns [`Error::Timeout`] if the lock could not be acquired (or the select did not complete)
+    /// in time, e.g. because another task holds the lock for too long. The timeout is timed with a clone
+    /// of the delay configured with [`Self::with_delay`]; with the `embassy-time` feature, the default
+    /// [`NoDelay`] waits with `embassy_time::Timer`, so no delay has to be configured on embassy.
+    ///
+    /// If the timeout expires during the select, the select is abandoned and the cached mask is forgotten.
+    ///
+    /// The lock is acquired with [`AsyncMutex::lock_timeout`]. For mutexes that don't override it,
+    /// waiting for the lock is not timed, only the select is.
+    ///
+    /// *Note:* see [`Self::select_mask_async`] for more info.
+    pub async fn select_mask_timeout_async(
+        &self,
+        mask: impl Into<ChannelMask>,
+        timeout_us: u32,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        let mask = mask.into().bits();
+        let mut delay = self.pins.delay.clone();
+        let mut bus = self
+            .bus
+            .lock_timeout(async move { delay.delay_us(timeout_us).await })
+            .await
+            .ok_or_else(|| self.last_error.timeout())?
+            .map_err(|e| self.last_error.mutex(e))?;
+        let mut delay = self.pins.delay.clone();
+        match race(
+            self.write_mask_async(&mut bus, mask),
+            delay.delay_us(timeout_us),
+        )
+        .await
+        {
+            Some(result) => result?,
+            None => return Err(self.last_error.timeout()),
+        }
+        Ok(bus)
+    }
+}
+
+/// Calls [`SelectionCoordinator::acquire`], and [`SelectionCoordinator::release`] when dropped,
+/// so a cancelled async write of the control register still releases the coordinator.
+#[cfg(feature = "async")]
+pub(crate) struct Acquired<'a, Coordinator: SelectionCoordinator>(&'a Coordinator);
+
+#[cfg(feature = "async")]
+impl<'a, Coordinator: SelectionCoordinator> Acquired<'a, Coordinator> {
+    pub(crate) fn new(coordinator: &'a Coordinator) -> Self {
+        coordinator.acquire();
+        Self(coordinator)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Coordinator: SelectionCoordinator> Drop for Acquired<'_, Coordinator> {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+/// Poll `future` until it completes, or until `deadline` completes first.
+///
+/// `future` is polled first, so it wins if both are ready.
+#[cfg(feature = "async")]
+pub(crate) async fn race<F: Future, D: Future<Output = ()>>(
+    future: F,
+    deadline: D,
+) -> Option<F::Output> {
+    let mut future = core::pin::pin!(future);
+    let mut deadline = core::pin::pin!(deadline);
+    core::future::poll_fn(|cx| {
+        if let core::task::Poll::Ready(output) = future.as_mut().poll(cx) {
+            return core::task::Poll::Ready(Some(output));
+        }
+        deadline.as_mut().poll(cx).map(|()| None)
+    })
+    .await
+}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+impl<
+        Mutex,
+        Coordinator: SelectionCoordinator,
+        Pins,
+        Observer: SelectObserver,
+        Address: ControlAddress,
+    > Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Write `mask` to the control register on `bus` as is, like [`Self::write_mask_exact`].
+    ///
+    /// This doesn't need the bus of this mux, so the muxes of a [`MuxGroup`] use it with the shared bus.
+    pub(crate) fn write_mask_exact_on<E, Bus: embedded_hal::i2c::I2c<Address>>(
+        &self,
+        bus: &mut Bus,
+        mask: u8,
+    ) -> Result<(), Error<E, Bus::Error>> {
+        self.cache.invalidate();
+        self.coordinator.acquire();
+        let result =
+            embedded_hal::i2c::I2c::write(bus, self.address, &[self.mask_policy.encode(mask)]);
+        self.coordinator.release();
+        result.map_err(|e| self.last_error.bus(e))?;
+        self.cache.set(mask);
+        self.observer.on_select(mask);
+        trace_select!(self.address, mask);
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "sync", feature = "async-to-sync"))]
+impl<
+        Mutex: MutexBase,
+        Coordinator: SelectionCoordinator,
+        Pins,
+        Observer: SelectObserver,
+        Address: ControlAddress,
+    > Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+{
+    /// Write `mask` to the control register using an already locked bus.
+    ///
+    /// The mask is checked against the channel count first (see [`Self::with_channel_count`]).
+    fn write_mask(&self, bus: &mut Mutex::Bus, mask: u8) -> Result<(), BusError<Mutex>> {
+        let mask = self.mask_policy.apply(&self.last_error, mask)?;
+        self.write_mask_exact(bus, mask)
+    }
+
+    /// Write `mask` to the control register as is, using an already locked bus.
+    ///
+    /// Unlike [`Self::write_mask`], the sticky channels are not added (see [`Self::set_sticky_channels`]),
+    /// so `NO_CHANNELS` really disables all channels.
+    fn write_mask_exact(&self, bus: &mut Mutex::Bus, mask: u8) -> Result<(), BusError<Mutex>> {
+        self.write_mask_exact_on(bus, mask)
+    }
+
+    /// Write `mask`, read the control register back and return the mask read, using an already locked bus.
+    ///
+    /// The write and the read are combined into one `write_read`, unless [`Self::with_split_verify`] is set.
+    #[cfg(feature = "sync")]
+    fn write_mask_read_back(&self, bus: &mut Mutex::Bus, mask: u8) -> Result<u8, BusError<Mutex>> {
+        let mut actual = [0];
+        if self.mask_policy.split_verify {
+            self.write_mask(bus, mask)?;
+            embedded_hal::i2c::I2c::read(bus, self.address, &mut actual)
+                .map_err(|e| self.last_error.bus(e))?;
+        } else {
+            let mask = self.mask_policy.apply(&self.last_error, mask)?;
+            self.cache.invalidate();
+            self.coordinator.acquire();
+            let result = embedded_hal::i2c::I2c::write_read(
+                bus,
+                self.address,
+                &[self.mask_policy.encode(mask)],
+                &mut actual,
+            );
+            self.coordinator.release();
+            result.map_err(|e| self.last_error.bus(e))?;
+            self.observer.on_select(mask);
+            trace_select!(self.address, mask);
+        }
+        actual[0] = self.mask_policy.decode(actual[0]);
+        self.cache.set(actual[0]);
+        Ok(actual[0])
+    }
+}
+
+#[cfg(feature = "async-to-sync")]
+impl<
+        Mutex: AsyncMutex,
+        Coordinator: SelectionCoordinator,
+        Pins,
+        Observer: SelectObserver,
+        Address: ControlAddress,
+    > Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+{
+    /// Select the subbus and return the lock to the bus, from a non-async context with an async mutex.
+    ///
+    /// * `mask` The mask to use for the subbus
+    ///
+    /// The async lock is awaited with a *blocking* busy loop ([`embassy_futures::block_on`]) and the mask is
+    /// written with the blocking [`embedded_hal::i2c::I2c`] implementation of the bus. This is only
+    /// sound if the current holder of the lock can make progress meanwhile, e.g. from a thread or an
+    /// interrupt of higher priority; called from a task of the same executor that holds the lock, it never returns.
+    ///
+    /// *Note:* see [`Self::select_mask`] for more info.
+    pub fn select_mask_blocking(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        let mask = mask.into().bits();
+        let mut bus =
+            embassy_futures::block_on(self.bus.lock()).map_err(|e| self.last_error.mutex(e))?;
+        self.write_mask(&mut bus, mask)?;
+        #[cfg(feature = "deselect-on-drop")]
+        let bus = deselect::DeselectOnDrop { pca: self, bus };
+        Ok(bus)
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<
+        Mutex: SyncMutex,
+        Coordinator: SelectionCoordinator,
+        Pins,
+        Observer: SelectObserver,
+        Address: ControlAddress,
+    > Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+{
+    /// Lock the bus and start a session that can change the selection without releasing the lock.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::session_async`].
+    ///
+    /// *Note:* see [`Self::session_async`] for more info.
+    #[allow(clippy::type_complexity)]
+    pub fn session(
+        &self,
+    ) -> Result<
+        MuxSession<
+            '_,
+            Mutex,
+            Coordinator,
+            Pins,
+            Observer,
+            Address,
+            impl DerefMut<Target = Mutex::Bus> + '_,
+        >,
+        Mutex::Error,
+    > {
+        Ok(MuxSession::new(self, self.bus()?))
+    }
+
+    /// Lock the bus once and run `f` with a session on it, for sequences on several channels.
+    ///
+    /// * `f` The closure that selects the channels on the session and performs the transactions
+    ///
+    /// Like [`Self::session`], but the lock is released when `f` returns:
+    /// ```
+    /// # #[cfg(feature = "sync")]
+    /// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) {
+    /// use embedded_hal::i2c::I2c;
+    /// use pca9548a::{Pca9548a, BASE_ADDRESS};
+    ///
+    /// let pca = Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, BASE_ADDRESS);
+    /// pca.with_lock(|session| {
+    ///     for id in [0, 3, 1] {
+    ///         session.select_single(id).unwrap();
+    ///         session.write(0x42, &[id]).unwrap();
+    ///     }
+    /// })
+    /// .unwrap();
+    /// # }
+    /// ```
+    ///
+    /// With the `deselect-on-drop` feature, all channels (including the sticky ones) are deselected
+    /// before the lock is released (errors are ignored).
+    pub fn with_lock<R>(
+        &self,
+        f: impl FnOnce(
+            &mut MuxSession<'_, Mutex, Coordinator, Pins, Observer, Address, &mut Mutex::Bus>,
+        ) -> R,
+    ) -> Result<R, MutexError<Mutex>> {
+        let mut bus = self.bus()?;
+        let result = f(&mut MuxSession::new(self, &mut *bus));
+        #[cfg(feature = "deselect-on-drop")]
+        let _ = self.write_mask_exact(&mut bus, NO_CHANNELS);
+        Ok(result)
+    }
+
+    /// Select the subbus and return the lock to the bus.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_mask_async`].
+    ///
+    /// * `mask` The mask to use for the subbus
+    ///
+    /// *Note:* You can use the returned lock, to perform your transactions on the subbus;
+    /// this makes sure, that the mask is not changed by another task in the meantime.
+    ///
+    /// *Note:* The above guarantee only holds, if `Bus` is not a shared bus (e.g. [shared_bus](https://docs.rs/shared-bus/latest/shared_bus/)).
+    ///
+    /// With the `deselect-on-drop` feature, the returned lock deselects all channels when it is dropped
+    /// (errors are ignored). This applies to everything built on this method, e.g. [`Self::select_single`]
+    /// and the [`SubBus`] methods, and costs an extra write per call.
+    pub fn select_mask(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.select_mask_inner(mask.into().bits(), false)
+    }
+
+    /// Lock the bus and write `mask`; with `exact`, the mask is written as is (see [`Self::write_mask_exact`]).
+    fn select_mask_inner(
+        &self,
+        mask: u8,
+        exact: bool,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        if exact {
+            self.write_mask_exact(&mut bus, mask)?;
+        } else {
+            self.write_mask(&mut bus, mask)?;
+        }
+        #[cfg(feature = "deselect-on-drop")]
+        let bus = deselect::DeselectOnDrop { pca: self, bus };
+        Ok(bus)
+    }
+
+    /// Select all channels and return the lock to the bus, e.g. for a broadcast write.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_all_async`].
+    ///
+    /// *Note:* see [`Self::select_all_async`] for more info.
+    pub fn select_all(&self) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.select_mask(ALL_CHANNELS & self.mask_policy.valid())
+    }
+
+    /// Deselect all channels.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::deselect_async`].
+    ///
+    /// *Note:* see [`Self::deselect_async`] for more info.
+    pub fn deselect(&self) -> Result<(), BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        self.write_mask(&mut bus, NO_CHANNELS)
+    }
+
+    /// Switch to `channel`, skipping the write if it is already the only selected channel.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::switch_to_async`].
+    ///
+    /// *Note:* see [`Self::switch_to_async`] for more info.
+    pub fn switch_to(&self, channel: Channel) -> Result<bool, BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let mask = channel.mask().bits();
+        if self.cache.get() == Some(self.mask_policy.apply(&self.last_error, mask)?) {
+            return Ok(false);
+        }
+        self.write_mask(&mut bus, mask)?;
+        Ok(true)
+    }
+
+    /// Select a single subbus and return the lock to the bus.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_single_async`].
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    ///
+    /// *Note:* see [`Self::select_mask`] for more info.
+    ///
+    /// # Panics
+    /// Panics if `id` is out of range. With the no-panic feature, [`Error::InvalidChannel`] is returned instead.
+    #[track_caller]
+    pub fn select_single(
+        &self,
+        id: u8,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.check_channel(id)?;
+        self.select_mask(1 << id)
+    }
+
+    /// Select a single subbus and return the lock to the bus.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_channel_async`].
+    ///
+    /// *Note:* see [`Self::select_channel_async`] for more info.
+    pub fn select_channel(
+        &self,
+        channel: Channel,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.select_mask(channel)
+    }
+
+    /// Select several subbuses and return the lock to the bus.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_channels_iter_async`].
+    ///
+    /// *Note:* see [`Self::select_channels_iter_async`] for more info.
+    pub fn select_channels_iter(
+        &self,
+        channels: impl IntoIterator<Item = Channel>,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.select_mask(channels.into_iter().collect::<ChannelMask>())
+    }
+
+    /// Select a single subbus and return the lock to the bus, or [`Error::InvalidChannel`] if `id` is out of range.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::try_select_single_async`].
+    ///
+    /// *Note:* see [`Self::select_mask`] for more info.
+    pub fn try_select_single(
+        &self,
+        id: u8,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        if id >= 8 {
+            return Err(self.last_error.invalid_channel(id));
+        }
+        self.select_mask(1 << id)
+    }
 
-    /// Create a new mutex of this type.
-    fn new(v: Self::Bus) -> Self;
-}
+    /// Select the subbus, read the control register back and return the lock to the bus if it matches.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_mask_verified_guard_async`].
+    ///
+    /// *Note:* see [`Self::select_mask_verified_guard_async`] for more info.
+    pub fn select_mask_verified_guard(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        let mask = mask.into().bits();
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let actual = self.write_mask_read_back(&mut bus, mask)?;
+        // Bits beyond the channel count are cleared in non-strict mode, and sticky channels are added.
+        let expected = self.mask_policy.expected(mask);
+        if actual != expected {
+            return Err(self.last_error.verify_failed(expected, actual));
+        }
+        #[cfg(feature = "deselect-on-drop")]
+        let bus = deselect::DeselectOnDrop { pca: self, bus };
+        Ok(bus)
+    }
 
-/// A "normal" synchronous mutex like `std::sync::Mutex`.
-///
-/// If the std feature is enabled, this is implemented for `std::sync::Mutex`.
-pub trait SyncMutex: MutexBase {
-    /// Lock the mutex.
-    fn lock(&self) -> Result<impl DerefMut<Target = Self::Bus>, Self::Error>;
-}
+    /// Read the current selection, select `mask` and return the lock to the bus with the previous mask.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_mask_prev_async`].
+    ///
+    /// *Note:* see [`Self::select_mask_prev_async`] for more info.
+    pub fn select_mask_prev(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<(u8, impl DerefMut<Target = Mutex::Bus> + '_), BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let mut previous = [0];
+        bus.read(self.address, &mut previous)
+            .map_err(|e| self.last_error.bus(e))?;
+        previous[0] = self.mask_policy.decode(previous[0]);
+        self.cache.set(previous[0]);
+        self.write_mask(&mut bus, mask.into().bits())?;
+        #[cfg(feature = "deselect-on-drop")]
+        let bus = deselect::DeselectOnDrop { pca: self, bus };
+        Ok((previous[0], bus))
+    }
 
-/// An asynchronous mutex like `embassy_sync::mutex::Mutex`.
-pub trait AsyncMutex: MutexBase {
-    /// Lock the mutex.
-    fn lock(&self) -> impl Future<Output = Result<impl DerefMut<Target = Self::Bus>, Self::Error>>;
-}
+    /// Temporarily select `mask`, run `f` on the bus and restore the previous selection afterwards.
+    ///
+    /// * `mask` The mask to select while `f` runs
+    /// * `f` The closure that performs the transactions on the subbus
+    ///
+    /// The previous selection is taken from the cache (see [`Self::cached_mask`]) or, if it is unknown,
+    /// read from the device. The bus stays locked until the previous selection is restored.
+    ///
+    /// If `f` panics, the previous selection is restored on a best-effort basis while unwinding.
+    /// Otherwise, an error while restoring is returned (and the result of `f` is lost).
+    pub fn with_temporary_mask<R>(
+        &self,
+        mask: impl Into<ChannelMask>,
+        f: impl FnOnce(&mut Mutex::Bus) -> R,
+    ) -> Result<R, BusError<Mutex>> {
+        /// Restores the previous selection if dropped while unwinding.
+        struct Restore<'a, Mutex, Coordinator, Pins, Observer, Address, Guard>
+        where
+            Address: ControlAddress,
+            Mutex: SyncMutex,
+            Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+            Coordinator: SelectionCoordinator,
+            Observer: SelectObserver,
+            Guard: DerefMut<Target = Mutex::Bus>,
+        {
+            pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+            bus: Guard,
+            previous: Option<u8>,
+        }
 
-#[cfg(feature = "std")]
-impl<T> MutexBase for std::sync::Mutex<T> {
-    type Bus = T;
-    type Error = ();
+        impl<Mutex, Coordinator, Pins, Observer, Address, Guard> Drop
+            for Restore<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+        where
+            Address: ControlAddress,
+            Mutex: SyncMutex,
+            Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+            Coordinator: SelectionCoordinator,
+            Observer: SelectObserver,
+            Guard: DerefMut<Target = Mutex::Bus>,
+        {
+            fn drop(&mut self) {
+                if let Some(previous) = self.previous {
+                    let _ = self.pca.write_mask(&mut self.bus, previous);
+                }
+            }
+        }
 
-    fn new(v: Self::Bus) -> Self {
-        Self::new(v)
-    }
-}
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let previous = match self.cache.get() {
+            Some(previous) => previous,
+            None => {
+                let mut previous = [0];
+                bus.read(self.address, &mut previous)
+                    .map_err(|e| self.last_error.bus(e))?;
+                previous[0] = self.mask_policy.decode(previous[0]);
+                previous[0]
+            }
+        };
+        self.write_mask(&mut bus, mask.into().bits())?;
 
-#[cfg(feature = "std")]
-impl<T> SyncMutex for std::sync::Mutex<T> {
-    fn lock(&self) -> Result<impl DerefMut<Target = Self::Bus>, Self::Error> {
-        self.lock().or(Err(()))
+        let mut restore = Restore {
+            pca: self,
+            bus,
+            previous: Some(previous),
+        };
+        let result = f(&mut restore.bus);
+        restore.previous = None;
+        self.write_mask(&mut restore.bus, previous)?;
+        Ok(result)
     }
-}
 
-/// The error type returned by most operations.
-///
-/// The error can either come from the mutex, or from the bus.
-#[derive(Debug)]
-pub enum Error<Mutex, Bus> {
-    /// Mutex error
-    Mutex(Mutex),
-    /// Bus error
-    Bus(Bus),
-}
+    /// Select `mask` and perform a transaction on it.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::transaction_with_mask_async`].
+    ///
+    /// *Note:* see [`Self::transaction_with_mask_async`] for more info.
+    pub fn transaction_with_mask(
+        &self,
+        mask: impl Into<ChannelMask>,
+        device_addr: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), BusError<Mutex>> {
+        self.transaction_with_mask_inner(mask.into().bits(), false, device_addr, operations)
+    }
 
-impl<Mutex, Bus> embedded_hal::i2c::Error for Error<Mutex, Bus>
-where
-    Mutex: core::fmt::Debug,
-    Bus: embedded_hal::i2c::Error,
-{
-    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
-        match self {
-            Error::Mutex(_) => embedded_hal::i2c::ErrorKind::Overrun,
-            Error::Bus(e) => e.kind(),
+    /// See [`Self::transaction_with_mask`]; `exact` is passed to [`Self::select_mask_inner`].
+    fn transaction_with_mask_inner(
+        &self,
+        mask: u8,
+        exact: bool,
+        device_addr: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), BusError<Mutex>> {
+        let mut bus = self.select_mask_inner(mask, exact)?;
+        if operations.is_empty() {
+            return Ok(());
         }
+        trace_operation!(self.address, mask, "transaction", device_addr);
+        bus.transaction(device_addr, operations)
+            .map_err(|e| self.last_error.bus(e))
     }
-}
-
-/// The Pca9548a is an i2c multiplexer device.
-pub struct Pca9548a<Mutex> {
-    bus: Mutex,
-    address: u8,
-}
 
-impl<Mutex: MutexBase> Pca9548a<Mutex> {
-    /// Create a new instance.
-    pub fn new(bus: Mutex::Bus, address: u8) -> Self {
-        Self {
-            bus: Mutex::new(bus),
-            address,
+    /// Select `channel` and perform a transaction on it, retrying on transient bus errors.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::transaction_retry_async`].
+    ///
+    /// *Note:* see [`Self::transaction_retry_async`] for more info.
+    pub fn transaction_retry(
+        &self,
+        channel: Channel,
+        device_addr: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+        retries: u8,
+    ) -> Result<(), BusError<Mutex>> {
+        let mut result = self.transaction_with_mask(channel, device_addr, operations);
+        for _ in 0..retries {
+            if !is_transient(&result) {
+                break;
+            }
+            result = self.transaction_with_mask(channel, device_addr, operations);
         }
+        result
     }
 
-    /// Get a subbus from this device.
+    /// Read a large block from an EEPROM-style device page by page, selecting its channel once.
     ///
-    /// * `mask` The mask to use for the subbus
+    /// Use this version in a non-async context. For a async version see [`Self::read_paged_async`].
     ///
-    /// See [`SubBus`] for more info.
-    pub fn subbus(&self, mask: u8) -> SubBus<'_, Mutex> {
-        SubBus { pca: self, mask }
+    /// *Note:* see [`Self::read_paged_async`] for more info.
+    pub fn read_paged(
+        &self,
+        channel: Channel,
+        device_addr: u8,
+        start: u16,
+        buf: &mut [u8],
+        page_size: usize,
+    ) -> Result<(), BusError<Mutex>> {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(page_size > 0);
+        let mut bus = self.select_mask(channel)?;
+        for (i, page) in buf.chunks_mut(page_size.max(1)).enumerate() {
+            let address = page_address(start, i, page_size);
+            bus.write_read(device_addr, &address, page)
+                .map_err(|e| self.last_error.bus(e))?;
+        }
+        Ok(())
     }
 
-    /// Get a subbus with a single channel enabled.
+    /// Write `bytes` to `device_addr` on each of the given channels, one channel at a time.
     ///
-    /// * `id` The id of the subbus in range 0..=7
+    /// Use this version in a non-async context. For a async version see [`Self::broadcast_write_async`].
     ///
-    /// See [`SubBus`] for more info.
-    pub fn single_subbus(&self, id: u8) -> SubBus<'_, Mutex> {
-        assert!(id < 8);
-        self.subbus(1 << id)
+    /// *Note:* see [`Self::broadcast_write_async`] for more info.
+    ///
+    /// # Panics
+    /// Panics if any id in `channels` is out of range, before the first write. With the no-panic feature,
+    /// [`Error::InvalidChannel`] is returned instead.
+    #[track_caller]
+    pub fn broadcast_write(
+        &self,
+        device_addr: u8,
+        bytes: &[u8],
+        channels: &[u8],
+    ) -> Result<(), BusError<Mutex>> {
+        self.check_channels(channels)?;
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        for &id in channels {
+            self.write_mask(&mut bus, 1 << id)?;
+            bus.write(device_addr, bytes)
+                .map_err(|e| self.last_error.bus(e))?;
+        }
+        Ok(())
     }
-}
 
-impl<Mutex: AsyncMutex> Pca9548a<Mutex> {
-    /// Get a lock on the bus using an `AsyncMutex`
-    pub async fn bus_async(&self) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, Mutex::Error> {
-        self.bus.lock().await
+    /// Read the current mask from the control register.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::read_mask_async`].
+    ///
+    /// This does not change the selection, but updates the cached mask (see [`Self::cached_mask`]).
+    pub fn read_mask(&self) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let mut mask = [0];
+        bus.read(self.address, &mut mask)
+            .map_err(|e| self.last_error.bus(e))?;
+        mask[0] = self.mask_policy.decode(mask[0]);
+        self.cache.set(mask[0]);
+        Ok(mask[0])
     }
-}
 
-impl<Mutex: SyncMutex> Pca9548a<Mutex> {
-    /// Get a lock on the bus using an `SyncMutex`
-    pub fn bus(&self) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, Mutex::Error> {
-        self.bus.lock()
+    /// Read the interrupt flags of a PCA9543A/PCA9545A.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::read_interrupts_async`].
+    ///
+    /// *Note:* see [`Self::read_interrupts_async`] for more info.
+    pub fn read_interrupts(&self) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let mut register = [0];
+        bus.read(self.address, &mut register)
+            .map_err(|e| self.last_error.bus(e))?;
+        Ok(self.mask_policy.decode_interrupts(register[0]))
     }
-}
 
-impl<Mutex: AsyncMutex> Pca9548a<Mutex>
-where
-    Mutex::Bus: embedded_hal_async::i2c::I2c,
-{
-    /// Select the subbus and return the lock to the bus.
+    /// Read the debounced interrupt flags of a PCA9543A/PCA9544A/PCA9545A.
     ///
-    /// Use this version in an async context. For a non-async version see [`Self::select_mask`].
+    /// Use this version in a non-async context. For a async version see [`Self::poll_interrupts_async`].
     ///
-    /// * `mask` The mask to use for the subbus
+    /// *Note:* see [`Self::poll_interrupts_async`] for more info.
+    pub fn poll_interrupts(&self) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let mut flags = u8::MAX;
+        for _ in 0..self.mask_policy.interrupt_debounce {
+            let mut register = [0];
+            bus.read(self.address, &mut register)
+                .map_err(|e| self.last_error.bus(e))?;
+            flags &= self.mask_policy.decode_interrupts(register[0]);
+        }
+        Ok(flags)
+    }
+
+    /// Acknowledge an interrupt of a PCA9543A/PCA9545A by reading the interrupting device.
     ///
-    /// *Note:* You can use the returned lock, to perform your transactions on the subbus;
-    /// this makes sure, that the mask is not changed by another task in the meantime.
+    /// Use this version in a non-async context. For a async version see [`Self::ack_interrupts_async`].
     ///
-    /// *Note:* The above guarantee only holds, if `Bus` is not a shared bus (e.g. [shared_bus](https://docs.rs/shared-bus/latest/shared_bus/)).
-    pub async fn select_mask_async(
+    /// *Note:* see [`Self::ack_interrupts_async`] for more info.
+    pub fn ack_interrupts(
         &self,
-        mask: u8,
-    ) -> Result<
-        impl DerefMut<Target = Mutex::Bus> + '_,
-        Error<Mutex::Error, <Mutex::Bus as ErrorType>::Error>,
-    > {
-        let mut bus = self.bus_async().await.map_err(Error::Mutex)?;
-        bus.write(self.address, &[mask]).await.map_err(Error::Bus)?;
-        Ok(bus)
+        channel: Channel,
+        device_addr: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self.select_mask(channel)?;
+        bus.write_read(device_addr, write, read)
+            .map_err(|e| self.last_error.bus(e))?;
+        let mut register = [0];
+        bus.read(self.address, &mut register)
+            .map_err(|e| self.last_error.bus(e))?;
+        Ok(self.mask_policy.decode_interrupts(register[0]))
     }
 
-    /// Select a single subbus and return the lock to the bus.
+    /// Read several one-byte registers of a device, selecting the channel once.
     ///
-    /// Use this version in an async context. For a non-async version see [`Self::select_single`].
-    ///
-    /// * `id` The id of the subbus in range 0..=7
+    /// Use this version in a non-async context. For a async version see [`Self::read_registers_async`].
     ///
-    /// *Note:* see [`Self::select_mask_async`] for more info.
-    pub async fn select_single_async(
+    /// *Note:* see [`Self::read_registers_async`] for more info.
+    pub fn read_registers(
         &self,
-        id: u8,
-    ) -> Result<
-        impl DerefMut<Target = Mutex::Bus> + '_,
-        Error<Mutex::Error, <Mutex::Bus as ErrorType>::Error>,
-    > {
-        assert!(id < 8);
-        self.select_mask_async(1 << id).await
+        channel: Channel,
+        device_addr: u8,
+        regs: &[u8],
+        out: &mut [u8],
+    ) -> Result<(), BusError<Mutex>> {
+        self.check_length(regs, out)?;
+        let mut bus = self.select_mask(channel)?;
+        for (reg, value) in regs.iter().zip(out.chunks_exact_mut(1)) {
+            bus.write_read(device_addr, &[*reg], value)
+                .map_err(|e| self.last_error.bus(e))?;
+        }
+        Ok(())
     }
-}
 
-impl<Mutex: SyncMutex> Pca9548a<Mutex>
-where
-    Mutex::Bus: embedded_hal::i2c::I2c,
-{
-    /// Select the subbus and return the lock to the bus.
+    /// Read the control register and then read from a device, under one lock.
     ///
-    /// Use this version in a non-async context. For a async version see [`Self::select_mask_async`].
+    /// Use this version in a non-async context. For a async version see [`Self::read_selection_then_async`].
     ///
-    /// * `mask` The mask to use for the subbus
+    /// *Note:* see [`Self::read_selection_then_async`] for more info.
+    pub fn read_selection_then(
+        &self,
+        device_addr: u8,
+        read_buf: &mut [u8],
+    ) -> Result<u8, BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let mut mask = [0];
+        bus.read(self.address, &mut mask)
+            .map_err(|e| self.last_error.bus(e))?;
+        mask[0] = self.mask_policy.decode(mask[0]);
+        self.cache.set(mask[0]);
+        bus.read(device_addr, read_buf)
+            .map_err(|e| self.last_error.bus(e))?;
+        Ok(mask[0])
+    }
+
+    /// Get a single-channel subbus for every channel that is currently enabled.
     ///
-    /// *Note:* You can use the returned lock, to perform your transactions on the subbus;
-    /// this makes sure, that the mask is not changed by another task in the meantime.
+    /// Use this version in a non-async context. For a async version see [`Self::enabled_subbuses_async`].
     ///
-    /// *Note:* The above guarantee only holds, if `Bus` is not a shared bus (e.g. [shared_bus](https://docs.rs/shared-bus/latest/shared_bus/)).
-    pub fn select_mask(
+    /// The current selection is read from the device (see [`Self::read_mask`]) and is not changed.
+    /// If no channel is enabled, the iterator is empty.
+    #[allow(clippy::type_complexity)]
+    pub fn enabled_subbuses(
         &self,
-        mask: u8,
     ) -> Result<
-        impl DerefMut<Target = Mutex::Bus> + '_,
-        Error<Mutex::Error, <Mutex::Bus as ErrorType>::Error>,
+        impl Iterator<Item = (u8, SubBus<'_, Mutex, Coordinator, Pins, Observer, Address>)>,
+        BusError<Mutex>,
     > {
-        let mut bus = self.bus().map_err(Error::Mutex)?;
-        bus.write(self.address, &[mask]).map_err(Error::Bus)?;
-        Ok(bus)
+        let mask = self.read_mask()?;
+        Ok(mask_to_channels(mask).map(|id| (id, self.subbus(1 << id))))
     }
 
-    /// Select a single subbus and return the lock to the bus.
+    /// Count the channels that are currently enabled.
     ///
-    /// Use this version in a non-async context. For a async version see [`Self::select_single_async`].
+    /// Use this version in a non-async context. For a async version see [`Self::selected_count_async`].
     ///
-    /// * `id` The id of the subbus in range 0..=7
+    /// The current selection is read from the device (see [`Self::read_mask`]).
+    pub fn selected_count(&self) -> Result<u32, BusError<Mutex>> {
+        Ok(self.read_mask()?.count_ones())
+    }
+
+    /// Check whether the control register holds its power-on default `0x00`, i.e. all channels are off.
     ///
-    /// *Note:* see [`Self::select_mask`] for more info.
-    pub fn select_single(
-        &self,
-        id: u8,
-    ) -> Result<
-        impl DerefMut<Target = Mutex::Bus> + '_,
-        Error<Mutex::Error, <Mutex::Bus as ErrorType>::Error>,
-    > {
-        assert!(id < 8);
-        self.select_mask(1 << id)
+    /// Use this version in a non-async context. For a async version see [`Self::is_default_state_async`].
+    ///
+    /// *Note:* see [`Self::is_default_state_async`] for more info.
+    pub fn is_default_state(&self) -> Result<bool, BusError<Mutex>> {
+        Ok(self.read_mask()? == NO_CHANNELS)
+    }
+
+    /// Get the ids of the channels that are currently enabled, in ascending order.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::selected_channels_async`].
+    ///
+    /// The current selection is read from the device (see [`Self::read_mask`]).
+    #[cfg(feature = "heapless")]
+    pub fn selected_channels(&self) -> Result<heapless::Vec<u8, CHANNELS>, BusError<Mutex>> {
+        Ok(mask_to_channels(self.read_mask()?).collect())
     }
 }
 
@@ -224,8 +2779,24 @@ where
 ///
 /// This implements the [`embedded_hal::i2c::I2c`]/[`embedded_hal_async::i2c::I2c`] traits, so you can use it with e.g. device drivers.
 ///
+/// Every call (`read`, `write`, `write_read` or `transaction`) locks the bus once, selects the mask
+/// with a write to the mux and then forwards the call unchanged to the device.
+/// The select can't be merged into the device transaction, as it goes to a different address.
+///
+/// Nothing is retried: a `transaction` performs exactly one select and forwards `operations` in exactly
+/// one transaction, so every read buffer is filled by the device once. If the select fails, the device
+/// transaction is not started and the buffers are left untouched. If the device transaction fails,
+/// the contents of the read buffers are unspecified (they may be partially filled); a retry layer
+/// on top must re-run the whole call instead of resuming with them.
+///
+/// A `SubBus` only borrows the mux; all mutation happens through its mutex. The `I2c` traits still take
+/// `&mut self`, so a subbus behind a shared reference can't be used directly. Since `SubBus` is [`Copy`],
+/// copy it into a local binding instead: `let mut bus = *shared;`. All copies address the same channels.
+///
 /// Example:
 /// ```
+/// # #[cfg(feature = "sync")]
+/// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) {
 /// use pca9548a::{Pca9548a, BASE_ADDRESS};
 /// use embedded_hal::i2c::I2c;
 ///
@@ -233,26 +2804,164 @@ where
 ///
 /// let mut subbus0 = pca.single_subbus(0);
 ///
-/// subbus.write(0x42, &[1, 2, 3]).expect("write");
+/// subbus0.write(0x42, &[1, 2, 3]).expect("write");
+/// # }
 /// ```
-pub struct SubBus<'a, Mutex> {
-    pca: &'a Pca9548a<Mutex>,
+pub struct SubBus<
+    'a,
+    Mutex,
+    Coordinator = (),
+    Pins = PinConfig,
+    Observer = (),
+    Address = SevenBitAddress,
+> {
+    pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
     mask: u8,
+    /// Whether the mask is written without the sticky channels, see [`Pca9548a::parent_subbus`]
+    #[cfg_attr(not(any(feature = "sync", feature = "async")), allow(dead_code))]
+    exact: bool,
+    /// The out-of-range id passed to [`Pca9548a::single_subbus`] with the no-panic feature
+    #[cfg_attr(not(any(feature = "sync", feature = "async")), allow(dead_code))]
+    invalid_channel: Option<u8>,
+    max_chunk: usize,
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address> Clone
+    for SubBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address> Copy
+    for SubBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+{
+}
+
+/// A [`SubBus`] of a device stored in a `static`, returned by [`Pca9548a::static_subbus`].
+///
+/// It is `'static` (given `'static` type parameters), so it satisfies the bounds of spawned tasks,
+/// and it is used exactly like any other [`SubBus`].
+pub type StaticSubBus<
+    Mutex,
+    Coordinator = (),
+    Pins = PinConfig,
+    Observer = (),
+    Address = SevenBitAddress,
+> = SubBus<'static, Mutex, Coordinator, Pins, Observer, Address>;
+
+/// Formats the subbus as its mux followed by the list of its channels, e.g. `PCA9548A@0x70[0, 2]`.
+///
+/// ```
+/// # #[cfg(feature = "std")]
+/// # {
+/// use embedded_hal_mock::eh1::i2c::Mock;
+/// use pca9548a::{Channel, Pca9548a, BASE_ADDRESS};
+///
+/// let mut bus = Mock::new(&[]);
+/// let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+/// assert_eq!(pca.subbus([Channel::C0, Channel::C2]).to_string(), "PCA9548A@0x70[0, 2]");
+/// assert_eq!(pca.parent_subbus().to_string(), "PCA9548A@0x70[]");
+/// # bus.done();
+/// # }
+/// ```
+impl<Mutex, Coordinator, Pins, Observer, Address: ControlAddress> core::fmt::Display
+    for SubBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}[", self.pca)?;
+        for (i, id) in mask_to_channels(self.mask).enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{id}")?;
+        }
+        f.write_str("]")
+    }
+}
+
+impl<'a, Mutex, Coordinator, Pins, Observer, Address>
+    SubBus<'a, Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Split reads and writes longer than `len` bytes into several ones, for buses with a transfer limit.
+    ///
+    /// This applies to `read`, `write` and `write_read` (the write part is sent first, the last write chunk
+    /// and the first read chunk are combined into one `write_read`). The channel is selected once and
+    /// all chunks are sent under the same lock. `transaction` is forwarded unchanged.
+    ///
+    /// *Note:* Each chunk is a separate transfer with its own start and stop condition.
+    /// This works for e.g. sequential EEPROM reads, but not for devices that expect the whole buffer in one transfer.
+    ///
+    /// # Panics
+    /// Panics if `len` is 0. With the no-panic feature, 0 is treated as 1.
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(len > 0);
+        self.max_chunk = len.max(1);
+        self
+    }
+
+    /// Get the mask this subbus selects.
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Iterate over the ids of the channels this subbus selects, in ascending order.
+    ///
+    /// ```
+    /// # #[cfg(feature = "std")]
+    /// # {
+    /// use embedded_hal_mock::eh1::i2c::Mock;
+    /// use pca9548a::{Channel, Pca9548a, BASE_ADDRESS};
+    ///
+    /// let mut bus = Mock::new(&[]);
+    /// let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    /// let subbus = pca.subbus([Channel::C1, Channel::C4, Channel::C6]);
+    ///
+    /// assert_eq!(subbus.mask(), 0b0101_0010);
+    /// assert!(subbus.channels().eq([1, 4, 6]));
+    /// # bus.done();
+    /// # }
+    /// ```
+    pub fn channels(&self) -> impl Iterator<Item = u8> {
+        mask_to_channels(self.mask)
+    }
+}
+
+#[cfg(any(feature = "sync", feature = "async"))]
+impl<Mutex: MutexBase, Coordinator, Pins, Observer, Address>
+    SubBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Fail with [`Error::InvalidChannel`] if this subbus was created for an out-of-range id,
+    /// see [`Pca9548a::single_subbus`].
+    fn check_channel<Bus>(&self) -> Result<(), Error<Mutex::Error, Bus>> {
+        match self.invalid_channel {
+            Some(id) => Err(self.pca.last_error.invalid_channel(id)),
+            None => Ok(()),
+        }
+    }
 }
 
-impl<'a, Mutex> embedded_hal::i2c::ErrorType for SubBus<'a, Mutex>
+impl<'a, Mutex, Coordinator, Pins, Observer, Address> embedded_hal::i2c::ErrorType
+    for SubBus<'a, Mutex, Coordinator, Pins, Observer, Address>
 where
     Mutex: MutexBase,
     Mutex::Error: core::fmt::Debug,
     Mutex::Bus: embedded_hal::i2c::ErrorType,
 {
-    type Error = Error<Mutex::Error, <Mutex::Bus as ErrorType>::Error>;
+    type Error = BusError<Mutex>;
 }
 
-impl<'a, Mutex> SubBus<'a, Mutex>
+#[cfg(feature = "async")]
+impl<'a, Mutex, Coordinator, Pins, Observer, Address>
+    SubBus<'a, Mutex, Coordinator, Pins, Observer, Address>
 where
+    Address: ControlAddress,
     Mutex: AsyncMutex,
-    Mutex::Bus: embedded_hal_async::i2c::I2c,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
 {
     /// Select this subbus and return the lock to the bus.
     ///
@@ -261,70 +2970,200 @@ where
     /// *Note:* see [`Pca9548a::select_mask_async`] for more info.
     pub async fn select_async(
         &self,
-    ) -> Result<
-        impl DerefMut<Target = Mutex::Bus> + '_,
-        Error<Mutex::Error, <Mutex::Bus as ErrorType>::Error>,
-    > {
-        self.pca.select_mask_async(self.mask).await
+    ) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.check_channel()?;
+        self.pca
+            .select_mask_inner_async(self.mask, self.exact)
+            .await
     }
 }
 
-impl<'a, Mutex> embedded_hal_async::i2c::I2c for SubBus<'a, Mutex>
+#[cfg(feature = "async")]
+impl<'a, Mutex, Coordinator, Pins, Observer, Address> embedded_hal_async::i2c::I2c
+    for SubBus<'a, Mutex, Coordinator, Pins, Observer, Address>
 where
+    Address: ControlAddress,
     Mutex: AsyncMutex,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
     Mutex::Error: core::fmt::Debug,
-    Mutex::Bus: embedded_hal_async::i2c::I2c,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
 {
     async fn transaction(
         &mut self,
         address: u8,
         operations: &mut [embedded_hal::i2c::Operation<'_>],
     ) -> Result<(), Self::Error> {
-        self.select_async()
-            .await?
-            .transaction(address, operations)
+        self.check_channel()?;
+        self.pca
+            .transaction_with_mask_inner_async(self.mask, self.exact, address, operations)
             .await
-            .map_err(Error::Bus)
     }
 
-    // TODO: Read/Write/WriteRead
+    async fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        let mut bus = self.select_async().await?;
+        trace_operation!(self.pca.address, self.mask, "read", address);
+        if read.len() <= self.max_chunk {
+            return bus
+                .read(address, read)
+                .await
+                .map_err(|e| self.pca.last_error.bus(e));
+        }
+        for chunk in read.chunks_mut(self.max_chunk) {
+            bus.read(address, chunk)
+                .await
+                .map_err(|e| self.pca.last_error.bus(e))?;
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        let mut bus = self.select_async().await?;
+        trace_operation!(self.pca.address, self.mask, "write", address);
+        if write.len() <= self.max_chunk {
+            return bus
+                .write(address, write)
+                .await
+                .map_err(|e| self.pca.last_error.bus(e));
+        }
+        for chunk in write.chunks(self.max_chunk) {
+            bus.write(address, chunk)
+                .await
+                .map_err(|e| self.pca.last_error.bus(e))?;
+        }
+        Ok(())
+    }
+
+    async fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let mut bus = self.select_async().await?;
+        trace_operation!(self.pca.address, self.mask, "write_read", address);
+        if write.len() <= self.max_chunk && read.len() <= self.max_chunk {
+            return bus
+                .write_read(address, write, read)
+                .await
+                .map_err(|e| self.pca.last_error.bus(e));
+        }
+        let (write_head, write_last) = split_last_chunk(write, self.max_chunk);
+        for chunk in write_head.chunks(self.max_chunk) {
+            bus.write(address, chunk)
+                .await
+                .map_err(|e| self.pca.last_error.bus(e))?;
+        }
+        let (read_first, read_tail) = read.split_at_mut(read.len().min(self.max_chunk));
+        bus.write_read(address, write_last, read_first)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))?;
+        for chunk in read_tail.chunks_mut(self.max_chunk) {
+            bus.read(address, chunk)
+                .await
+                .map_err(|e| self.pca.last_error.bus(e))?;
+        }
+        Ok(())
+    }
 }
 
-impl<'a, Mutex> SubBus<'a, Mutex>
+#[cfg(feature = "sync")]
+impl<'a, Mutex, Coordinator, Pins, Observer, Address>
+    SubBus<'a, Mutex, Coordinator, Pins, Observer, Address>
 where
+    Address: ControlAddress,
     Mutex: SyncMutex,
-    Mutex::Bus: embedded_hal::i2c::I2c,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
 {
     /// Select this subbus and return the lock to the bus.
     ///
     /// Use this version in a non-async context. For an async version see [`Self::select_async`].
     ///
     /// *Note:* see [`Pca9548a::select_mask`] for more info.
-    pub fn select(
-        &self,
-    ) -> Result<
-        impl DerefMut<Target = Mutex::Bus> + '_,
-        Error<Mutex::Error, <Mutex::Bus as ErrorType>::Error>,
-    > {
-        self.pca.select_mask(self.mask)
+    pub fn select(&self) -> Result<impl DerefMut<Target = Mutex::Bus> + '_, BusError<Mutex>> {
+        self.check_channel()?;
+        self.pca.select_mask_inner(self.mask, self.exact)
     }
 }
 
-impl<'a, Mutex> embedded_hal::i2c::I2c for SubBus<'a, Mutex>
+#[cfg(feature = "sync")]
+impl<'a, Mutex, Coordinator, Pins, Observer, Address> embedded_hal::i2c::I2c
+    for SubBus<'a, Mutex, Coordinator, Pins, Observer, Address>
 where
+    Address: ControlAddress,
     Mutex: SyncMutex,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
     Mutex::Error: core::fmt::Debug,
-    Mutex::Bus: embedded_hal::i2c::I2c,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
 {
     fn transaction(
         &mut self,
         address: u8,
         operations: &mut [embedded_hal::i2c::Operation<'_>],
     ) -> Result<(), Self::Error> {
-        self.select()?
-            .transaction(address, operations)
-            .map_err(Error::Bus)
+        self.check_channel()?;
+        self.pca
+            .transaction_with_mask_inner(self.mask, self.exact, address, operations)
+    }
+
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        let mut bus = self.select()?;
+        trace_operation!(self.pca.address, self.mask, "read", address);
+        if read.len() <= self.max_chunk {
+            return bus
+                .read(address, read)
+                .map_err(|e| self.pca.last_error.bus(e));
+        }
+        for chunk in read.chunks_mut(self.max_chunk) {
+            bus.read(address, chunk)
+                .map_err(|e| self.pca.last_error.bus(e))?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        let mut bus = self.select()?;
+        trace_operation!(self.pca.address, self.mask, "write", address);
+        if write.len() <= self.max_chunk {
+            return bus
+                .write(address, write)
+                .map_err(|e| self.pca.last_error.bus(e));
+        }
+        for chunk in write.chunks(self.max_chunk) {
+            bus.write(address, chunk)
+                .map_err(|e| self.pca.last_error.bus(e))?;
+        }
+        Ok(())
     }
 
-    // TODO: Read/Write/WriteRead
+    fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let mut bus = self.select()?;
+        trace_operation!(self.pca.address, self.mask, "write_read", address);
+        if write.len() <= self.max_chunk && read.len() <= self.max_chunk {
+            return bus
+                .write_read(address, write, read)
+                .map_err(|e| self.pca.last_error.bus(e));
+        }
+        let (write_head, write_last) = split_last_chunk(write, self.max_chunk);
+        for chunk in write_head.chunks(self.max_chunk) {
+            bus.write(address, chunk)
+                .map_err(|e| self.pca.last_error.bus(e))?;
+        }
+        let (read_first, read_tail) = read.split_at_mut(read.len().min(self.max_chunk));
+        bus.write_read(address, write_last, read_first)
+            .map_err(|e| self.pca.last_error.bus(e))?;
+        for chunk in read_tail.chunks_mut(self.max_chunk) {
+            bus.read(address, chunk)
+                .map_err(|e| self.pca.last_error.bus(e))?;
+        }
+        Ok(())
+    }
 }
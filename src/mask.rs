@@ -0,0 +1,200 @@
+#[cfg(any(feature = "sync", feature = "async"))]
+use crate::{latch::ErrorLatch, Error, INTERRUPT_SHIFT};
+use crate::{mask_to_channels, Channel, InvalidChannel, CHANNELS};
+
+/// A set of channels, i.e. a value of the control register.
+///
+/// Bit `i` enables channel `i`. All mask-accepting entry points take `impl Into<ChannelMask>`,
+/// so you can pass any of these forms:
+/// ```
+/// use pca9548a::{Channel, ChannelMask};
+///
+/// let raw: ChannelMask = 0b0000_0101.into();
+/// let channels: ChannelMask = [Channel::C0, Channel::C2].into();
+/// let slice: ChannelMask = (&[Channel::C0, Channel::C2][..]).into();
+/// let iter: ChannelMask = [Channel::C0, Channel::C2].into_iter().collect();
+///
+/// assert_eq!(raw, channels);
+/// assert_eq!(raw, slice);
+/// assert_eq!(raw, iter);
+/// ```
+///
+/// Raw channel ids may be out of range, so lists of them are converted with `try_from`,
+/// which fails with the first invalid id:
+/// ```
+/// use pca9548a::{Channel, ChannelMask, InvalidChannel};
+///
+/// assert_eq!(ChannelMask::try_from([0, 2]), Ok(ChannelMask::from_bits(0b0000_0101)));
+/// assert_eq!(ChannelMask::try_from(&[0, 8][..]), Err(InvalidChannel(8)));
+/// let iter: Result<ChannelMask, _> = (0..3).filter(|id| id % 2 == 0).map(Channel::try_from).collect();
+/// assert_eq!(iter, Ok(ChannelMask::from_bits(0b0000_0101)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ChannelMask(u8);
+
+impl ChannelMask {
+    /// No channel enabled.
+    pub const NONE: Self = Self(0);
+
+    /// Create a mask from the raw value of the control register.
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    /// Get the raw value of the control register.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Check whether the channel `id` is enabled.
+    ///
+    /// * `id` The id of the channel in range 0..=7
+    ///
+    /// Panics if `id` is out of range. With the no-panic feature, `false` is returned instead.
+    pub const fn contains(self, id: u8) -> bool {
+        #[cfg(not(feature = "no-panic"))]
+        assert!(id < CHANNELS as u8);
+        id < CHANNELS as u8 && self.0 & (1 << id) != 0
+    }
+
+    /// Iterate over the ids of the enabled channels, in ascending order.
+    pub fn channels(self) -> impl Iterator<Item = u8> {
+        mask_to_channels(self.0)
+    }
+}
+
+impl From<u8> for ChannelMask {
+    fn from(bits: u8) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<ChannelMask> for u8 {
+    fn from(mask: ChannelMask) -> Self {
+        mask.0
+    }
+}
+
+impl<const N: usize> TryFrom<[u8; N]> for ChannelMask {
+    type Error = InvalidChannel;
+
+    fn try_from(ids: [u8; N]) -> Result<Self, Self::Error> {
+        ids.as_slice().try_into()
+    }
+}
+
+impl TryFrom<&[u8]> for ChannelMask {
+    type Error = InvalidChannel;
+
+    fn try_from(ids: &[u8]) -> Result<Self, Self::Error> {
+        ids.iter().map(|&id| Channel::try_from(id)).collect()
+    }
+}
+
+/// Which bits of a mask are meaningful, and what to do with the others.
+#[derive(Clone, Copy)]
+pub(crate) struct MaskPolicy {
+    valid: u8,
+    pub(crate) sticky: u8,
+    pub(crate) strict: bool,
+    pub(crate) inverted: bool,
+    /// Whether the control register holds a channel id instead of a mask, see [`Self::encode`].
+    pub(crate) single_channel: bool,
+    pub(crate) split_verify: bool,
+    /// The number of consecutive reads an interrupt flag must be set in to be reported.
+    pub(crate) interrupt_debounce: u8,
+}
+
+impl MaskPolicy {
+    pub(crate) const fn new() -> Self {
+        Self {
+            valid: u8::MAX,
+            sticky: 0,
+            strict: false,
+            inverted: false,
+            single_channel: false,
+            split_verify: false,
+            interrupt_debounce: 1,
+        }
+    }
+
+    pub(crate) fn set_channel_count(&mut self, count: u8) {
+        self.valid = 1u8
+            .checked_shl(u32::from(count))
+            .map_or(u8::MAX, |bit| bit.wrapping_sub(1));
+    }
+
+    pub(crate) fn valid(&self) -> u8 {
+        self.valid
+    }
+
+    /// Check `mask` and return the mask to write.
+    #[cfg(any(feature = "sync", feature = "async"))]
+    pub(crate) fn apply<Mutex, Bus>(
+        &self,
+        latch: &ErrorLatch,
+        mask: u8,
+    ) -> Result<u8, Error<Mutex, Bus>> {
+        if self.strict && mask & !self.valid != 0 {
+            return Err(latch.invalid_mask(mask));
+        }
+        let expected = self.expected(mask);
+        if self.single_channel && expected.count_ones() > 1 {
+            return Err(latch.invalid_mask(mask));
+        }
+        Ok(expected)
+    }
+
+    /// The mask that is written for `mask` if it passes the checks of [`Self::apply`].
+    #[cfg(any(feature = "sync", feature = "async", feature = "test-internals"))]
+    pub(crate) fn expected(&self, mask: u8) -> u8 {
+        (mask | self.sticky) & self.valid
+    }
+
+    /// Turn `mask` into the byte written to the control register.
+    ///
+    /// With a single-channel layout, bit 3 enables the channel whose id is in bits 0..=2.
+    #[cfg(any(feature = "sync", feature = "async", feature = "test-internals"))]
+    pub(crate) fn encode(&self, mask: u8) -> u8 {
+        let raw = match mask {
+            _ if !self.single_channel => mask,
+            0 => 0,
+            _ => SINGLE_CHANNEL_ENABLE | mask.trailing_zeros() as u8,
+        };
+        self.invert(raw)
+    }
+
+    /// Turn a byte read from the control register into the mask.
+    #[cfg(any(feature = "sync", feature = "async", feature = "test-internals"))]
+    pub(crate) fn decode(&self, raw: u8) -> u8 {
+        let raw = self.invert(raw);
+        if !self.single_channel {
+            raw
+        } else if raw & SINGLE_CHANNEL_ENABLE != 0 {
+            1 << (raw & (SINGLE_CHANNEL_ENABLE - 1))
+        } else {
+            0
+        }
+    }
+
+    /// Get the interrupt flags from a byte read from the control register.
+    ///
+    /// They are in the upper nibble with either layout, so only the inversion applies.
+    #[cfg(any(feature = "sync", feature = "async"))]
+    pub(crate) fn decode_interrupts(&self, raw: u8) -> u8 {
+        self.invert(raw) >> INTERRUPT_SHIFT
+    }
+
+    #[cfg(any(feature = "sync", feature = "async", feature = "test-internals"))]
+    fn invert(&self, raw: u8) -> u8 {
+        if self.inverted {
+            !raw
+        } else {
+            raw
+        }
+    }
+}
+
+/// The enable bit of a single-channel control register, see [`MaskPolicy::encode`].
+#[cfg(any(feature = "sync", feature = "async", feature = "test-internals"))]
+const SINGLE_CHANNEL_ENABLE: u8 = 0b1000;
@@ -0,0 +1,194 @@
+use embedded_hal::i2c::{ErrorType, Operation, SevenBitAddress};
+
+#[cfg(feature = "async")]
+use crate::AsyncMutex;
+#[cfg(feature = "sync")]
+use crate::SyncMutex;
+use crate::{BusError, MutexBase, Pca9548a, PinConfig};
+
+/// A view of the parent bus of a mux, returned by [`Pca9548a::parent_bus`].
+///
+/// It implements [`embedded_hal::i2c::I2c`]/[`embedded_hal_async::i2c::I2c`] by locking the bus
+/// and forwarding every transaction unchanged. **It does not select any channel**: the control register
+/// is not written, so the channels selected last stay connected. This is for devices on the parent bus,
+/// which are reachable regardless of the selection; pass it to their drivers like a [`SubBus`](crate::SubBus).
+///
+/// ```
+/// # #[cfg(feature = "sync")]
+/// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) {
+/// use embedded_hal::i2c::I2c;
+/// use pca9548a::{Pca9548a, BASE_ADDRESS};
+///
+/// let pca = Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, BASE_ADDRESS);
+/// let mut parent = pca.parent_bus();
+/// parent.write(0x20, &[0x01]).unwrap(); // A device on the parent bus
+/// # }
+/// ```
+pub struct ParentBus<
+    'a,
+    Mutex,
+    Coordinator = (),
+    Pins = PinConfig,
+    Observer = (),
+    Address = SevenBitAddress,
+> {
+    pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address> Clone
+    for ParentBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address> Copy
+    for ParentBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+{
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Get a view of the parent bus that forwards transactions without selecting any channel.
+    ///
+    /// See [`ParentBus`] for more info.
+    pub fn parent_bus(&self) -> ParentBus<'_, Mutex, Coordinator, Pins, Observer, Address> {
+        ParentBus { pca: self }
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address> ErrorType
+    for ParentBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex: MutexBase,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: ErrorType,
+{
+    type Error = BusError<Mutex>;
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex, Coordinator, Pins, Observer, Address> embedded_hal::i2c::I2c
+    for ParentBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex: SyncMutex,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal::i2c::I2c,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut bus = self
+            .pca
+            .bus
+            .lock()
+            .map_err(|e| self.pca.last_error.mutex(e))?;
+        bus.transaction(address, operations)
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        let mut bus = self
+            .pca
+            .bus
+            .lock()
+            .map_err(|e| self.pca.last_error.mutex(e))?;
+        bus.read(address, read)
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+
+    fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        let mut bus = self
+            .pca
+            .bus
+            .lock()
+            .map_err(|e| self.pca.last_error.mutex(e))?;
+        bus.write(address, write)
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let mut bus = self
+            .pca
+            .bus
+            .lock()
+            .map_err(|e| self.pca.last_error.mutex(e))?;
+        bus.write_read(address, write, read)
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Mutex, Coordinator, Pins, Observer, Address> embedded_hal_async::i2c::I2c
+    for ParentBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex: AsyncMutex,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal_async::i2c::I2c,
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut bus = self
+            .pca
+            .bus
+            .lock()
+            .await
+            .map_err(|e| self.pca.last_error.mutex(e))?;
+        bus.transaction(address, operations)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+
+    async fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        let mut bus = self
+            .pca
+            .bus
+            .lock()
+            .await
+            .map_err(|e| self.pca.last_error.mutex(e))?;
+        bus.read(address, read)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+
+    async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        let mut bus = self
+            .pca
+            .bus
+            .lock()
+            .await
+            .map_err(|e| self.pca.last_error.mutex(e))?;
+        bus.write(address, write)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+
+    async fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let mut bus = self
+            .pca
+            .bus
+            .lock()
+            .await
+            .map_err(|e| self.pca.last_error.mutex(e))?;
+        bus.write_read(address, write, read)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+}
@@ -0,0 +1,401 @@
+#[cfg(any(feature = "sync", feature = "async"))]
+use embedded_hal::digital::Error as _;
+use embedded_hal::digital::{InputPin, OutputPin};
+
+#[cfg(feature = "async")]
+use crate::AsyncMutex;
+use crate::Pca9548a;
+#[cfg(feature = "sync")]
+use crate::SyncMutex;
+#[cfg(any(feature = "sync", feature = "async"))]
+use crate::{
+    BusError, ChannelMask, ControlAddress, SelectObserver, SelectionCoordinator, NO_CHANNELS,
+};
+
+/// How long the reset line is held low, in nanoseconds (datasheet: at least 6 ns).
+#[cfg(any(feature = "sync", feature = "async"))]
+const RESET_PULSE_NS: u32 = 1_000;
+
+/// How long to wait after releasing the reset line, in nanoseconds (datasheet: 500 ns).
+#[cfg(any(feature = "sync", feature = "async"))]
+const RESET_RECOVERY_NS: u32 = 1_000;
+
+/// Placeholder for an optional pin that is not connected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoPin;
+
+/// Placeholder for an optional delay provider that is not configured.
+///
+/// With the `embassy-time` feature it implements the async [`DelayNs`](embedded_hal_async::delay::DelayNs)
+/// with `embassy_time::Timer`, so the async timing-dependent methods work without [`Pca9548a::with_delay`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoDelay;
+
+#[cfg(feature = "embassy-time")]
+impl embedded_hal_async::delay::DelayNs for NoDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        embassy_time::Timer::after(embassy_time::Duration::from_nanos(ns.into())).await;
+    }
+}
+
+/// The optional pins and delay provider of a [`Pca9548a`].
+///
+/// Configure them with [`Pca9548a::with_interrupt_pin`], [`Pca9548a::with_reset_pin`] and [`Pca9548a::with_delay`].
+/// The `Delay` parameter is generic over the timer of your runtime: pass e.g. `embassy_time::Delay`
+/// on embassy or the delay of your HAL on bare metal. It stays [`NoDelay`] (a zero-sized placeholder)
+/// if you don't use any timing-dependent method.
+#[derive(Debug, Default)]
+pub struct PinConfig<IntPin = NoPin, ResetPin = NoPin, Delay = NoDelay> {
+    int: IntPin,
+    reset: ResetPin,
+    pub(crate) delay: Delay,
+}
+
+impl<IntPin, ResetPin: OutputPin, Delay> PinConfig<IntPin, ResetPin, Delay> {
+    /// Pulse the reset line and wait until the device recovered.
+    #[cfg(feature = "sync")]
+    fn pulse_reset(&mut self) -> Result<(), embedded_hal::digital::ErrorKind>
+    where
+        Delay: embedded_hal::delay::DelayNs,
+    {
+        self.reset.set_low().map_err(|e| e.kind())?;
+        self.delay.delay_ns(RESET_PULSE_NS);
+        self.reset.set_high().map_err(|e| e.kind())?;
+        self.delay.delay_ns(RESET_RECOVERY_NS);
+        Ok(())
+    }
+
+    /// Pulse the reset line and wait until the device recovered.
+    #[cfg(feature = "async")]
+    async fn pulse_reset_async(&mut self) -> Result<(), embedded_hal::digital::ErrorKind>
+    where
+        Delay: embedded_hal_async::delay::DelayNs,
+    {
+        self.reset.set_low().map_err(|e| e.kind())?;
+        self.delay.delay_ns(RESET_PULSE_NS).await;
+        self.reset.set_high().map_err(|e| e.kind())?;
+        self.delay.delay_ns(RESET_RECOVERY_NS).await;
+        Ok(())
+    }
+}
+
+impl<Mutex, Coordinator, Observer, Address, IntPin, ResetPin, Delay>
+    Pca9548a<Mutex, Coordinator, PinConfig<IntPin, ResetPin, Delay>, Observer, Address>
+{
+    /// Use `pin` as the interrupt line of the device.
+    ///
+    /// Some devices of the family (e.g. PCA9544A, PCA9545A) have an active-low `INT` output that
+    /// summarizes the interrupt inputs of all channels; the PCA9548A itself has none.
+    ///
+    /// See [`Self::interrupt_pending`] for more info.
+    pub fn with_interrupt_pin<P: InputPin>(
+        self,
+        pin: P,
+    ) -> Pca9548a<Mutex, Coordinator, PinConfig<P, ResetPin, Delay>, Observer, Address> {
+        Pca9548a {
+            bus: self.bus,
+            address: self.address,
+            coordinator: self.coordinator,
+            observer: self.observer,
+            cache: self.cache,
+            last_error: self.last_error,
+            mask_policy: self.mask_policy,
+            pins: PinConfig {
+                int: pin,
+                reset: self.pins.reset,
+                delay: self.pins.delay,
+            },
+            tag: self.tag,
+        }
+    }
+
+    /// Use `pin` to drive the active-low `RESET` input of the device.
+    ///
+    /// The pin should be high (i.e. not in reset) when it is passed in.
+    ///
+    /// See [`Self::reset_and_select`] for more info.
+    pub fn with_reset_pin<P: OutputPin>(
+        self,
+        pin: P,
+    ) -> Pca9548a<Mutex, Coordinator, PinConfig<IntPin, P, Delay>, Observer, Address> {
+        Pca9548a {
+            bus: self.bus,
+            address: self.address,
+            coordinator: self.coordinator,
+            observer: self.observer,
+            cache: self.cache,
+            last_error: self.last_error,
+            mask_policy: self.mask_policy,
+            pins: PinConfig {
+                int: self.pins.int,
+                reset: pin,
+                delay: self.pins.delay,
+            },
+            tag: self.tag,
+        }
+    }
+
+    /// Use `delay` for the timing-dependent methods, e.g. to time the reset pulse
+    /// (see [`Self::reset_and_select`]) or to wait between retries
+    /// (see [`Self::select_single_ready_async`]).
+    ///
+    /// Methods that only have `&self` use a clone of the delay, so it should be cheap to clone.
+    pub fn with_delay<D>(
+        self,
+        delay: D,
+    ) -> Pca9548a<Mutex, Coordinator, PinConfig<IntPin, ResetPin, D>, Observer, Address> {
+        Pca9548a {
+            bus: self.bus,
+            address: self.address,
+            coordinator: self.coordinator,
+            observer: self.observer,
+            cache: self.cache,
+            last_error: self.last_error,
+            mask_policy: self.mask_policy,
+            pins: PinConfig {
+                int: self.pins.int,
+                reset: self.pins.reset,
+                delay,
+            },
+            tag: self.tag,
+        }
+    }
+}
+
+impl<Mutex, Coordinator, Observer, Address, IntPin: InputPin, ResetPin, Delay>
+    Pca9548a<Mutex, Coordinator, PinConfig<IntPin, ResetPin, Delay>, Observer, Address>
+{
+    /// Check the interrupt line configured with [`Self::with_interrupt_pin`].
+    ///
+    /// Returns `true` if the (active-low) line is asserted, i.e. at least one channel signals an interrupt.
+    /// This is a cheap GPIO read; use it to check whether querying the device
+    /// (e.g. with `read_mask`) for the interrupting channel is necessary at all.
+    pub fn interrupt_pending(&mut self) -> Result<bool, IntPin::Error> {
+        self.pins.int.is_low()
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex, Coordinator, Observer, Address, IntPin, ResetPin: OutputPin, Delay>
+    Pca9548a<Mutex, Coordinator, PinConfig<IntPin, ResetPin, Delay>, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Delay: embedded_hal::delay::DelayNs,
+{
+    /// Reset the device with the reset pin and select `mask` afterwards.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::reset_and_select_async`].
+    ///
+    /// * `mask` The mask to select after the reset
+    ///
+    /// The reset pulse is timed with the delay configured with [`Self::with_delay`].
+    ///
+    /// This is a one-call recovery path, e.g. after detecting a hung bus.
+    /// The bus is locked for the whole sequence: the reset line is pulled low, released
+    /// and after the device had time to recover, `mask` is written to the control register.
+    pub fn reset_and_select(
+        &mut self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<(), BusError<Mutex>> {
+        let mut bus = self.bus.lock().map_err(|e| self.last_error.mutex(e))?;
+
+        self.cache.invalidate();
+        self.pins
+            .pulse_reset()
+            .map_err(|e| self.last_error.pin(e))?;
+        // The device powers up with all channels deselected.
+        self.cache.set(0);
+
+        self.write_mask(&mut bus, mask.into().bits())
+    }
+
+    /// Re-establish a known-good state after a suspected glitch of the device.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::reinit_async`].
+    ///
+    /// The bus is locked for the whole sequence:
+    /// 1. The cached mask is forgotten.
+    /// 2. The device is reset with the reset pin (see [`Self::reset_and_select`]).
+    ///    This step is skipped if no reset pin is configured.
+    /// 3. All channels are deselected.
+    /// 4. The control register is read back, which also confirms that the device responds.
+    ///    If it isn't `0`, [`Error::VerifyFailed`](crate::Error::VerifyFailed) is returned.
+    pub fn reinit(&mut self) -> Result<(), BusError<Mutex>> {
+        let mut bus = self.bus.lock().map_err(|e| self.last_error.mutex(e))?;
+
+        self.cache.invalidate();
+        self.pins
+            .pulse_reset()
+            .map_err(|e| self.last_error.pin(e))?;
+        self.deselect_and_confirm(&mut bus)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Mutex, Coordinator, Observer, Address, IntPin, ResetPin: OutputPin, Delay>
+    Pca9548a<Mutex, Coordinator, PinConfig<IntPin, ResetPin, Delay>, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Delay: embedded_hal_async::delay::DelayNs,
+{
+    /// Reset the device with the reset pin and select `mask` afterwards.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::reset_and_select`].
+    ///
+    /// *Note:* see [`Self::reset_and_select`] for more info.
+    pub async fn reset_and_select_async(
+        &mut self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<(), BusError<Mutex>> {
+        let mut bus = self
+            .bus
+            .lock()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+
+        self.cache.invalidate();
+        self.pins
+            .pulse_reset_async()
+            .await
+            .map_err(|e| self.last_error.pin(e))?;
+        // The device powers up with all channels deselected.
+        self.cache.set(0);
+
+        self.write_mask_async(&mut bus, mask.into().bits()).await
+    }
+
+    /// Re-establish a known-good state after a suspected glitch of the device.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::reinit`].
+    ///
+    /// *Note:* see [`Self::reinit`] for more info.
+    pub async fn reinit_async(&mut self) -> Result<(), BusError<Mutex>> {
+        let mut bus = self
+            .bus
+            .lock()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+
+        self.cache.invalidate();
+        self.pins
+            .pulse_reset_async()
+            .await
+            .map_err(|e| self.last_error.pin(e))?;
+        self.deselect_and_confirm_async(&mut bus).await
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex, Coordinator, Observer, Address, IntPin, Delay>
+    Pca9548a<Mutex, Coordinator, PinConfig<IntPin, NoPin, Delay>, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    /// Re-establish a known-good state after a suspected glitch of the device.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::reinit_async`].
+    ///
+    /// This is the recovery sequence of a device with a reset pin, without the reset step:
+    /// under one lock, the cached mask is forgotten, all channels are deselected and the control register
+    /// is read back, which also confirms that the device responds.
+    /// If it isn't `0`, [`Error::VerifyFailed`](crate::Error::VerifyFailed) is returned.
+    pub fn reinit(&mut self) -> Result<(), BusError<Mutex>> {
+        let mut bus = self.bus.lock().map_err(|e| self.last_error.mutex(e))?;
+
+        self.cache.invalidate();
+        self.deselect_and_confirm(&mut bus)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Mutex, Coordinator, Observer, Address, IntPin, Delay>
+    Pca9548a<Mutex, Coordinator, PinConfig<IntPin, NoPin, Delay>, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    /// Re-establish a known-good state after a suspected glitch of the device.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::reinit`].
+    ///
+    /// *Note:* see [`Self::reinit`] for more info.
+    pub async fn reinit_async(&mut self) -> Result<(), BusError<Mutex>> {
+        let mut bus = self
+            .bus
+            .lock()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+
+        self.cache.invalidate();
+        self.deselect_and_confirm_async(&mut bus).await
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    /// Deselect all channels (including the sticky ones) and confirm it by reading the control register back.
+    fn deselect_and_confirm(&self, bus: &mut Mutex::Bus) -> Result<(), BusError<Mutex>> {
+        self.write_mask_exact(bus, NO_CHANNELS)?;
+        let mut mask = [0];
+        embedded_hal::i2c::I2c::read(bus, self.address, &mut mask)
+            .map_err(|e| self.last_error.bus(e))?;
+        mask[0] = self.mask_policy.decode(mask[0]);
+        self.cache.set(mask[0]);
+        if mask[0] != 0 {
+            return Err(self.last_error.verify_failed(0, mask[0]));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    /// Deselect all channels (including the sticky ones) and confirm it by reading the control register back.
+    async fn deselect_and_confirm_async(
+        &self,
+        bus: &mut Mutex::Bus,
+    ) -> Result<(), BusError<Mutex>> {
+        self.write_mask_exact_async(bus, NO_CHANNELS).await?;
+        let mut mask = [0];
+        embedded_hal_async::i2c::I2c::read(bus, self.address, &mut mask)
+            .await
+            .map_err(|e| self.last_error.bus(e))?;
+        mask[0] = self.mask_policy.decode(mask[0]);
+        self.cache.set(mask[0]);
+        if mask[0] != 0 {
+            return Err(self.last_error.verify_failed(0, mask[0]));
+        }
+        Ok(())
+    }
+}
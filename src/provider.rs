@@ -0,0 +1,125 @@
+#[cfg(feature = "async")]
+use crate::AsyncMutex;
+#[cfg(feature = "sync")]
+use crate::SyncMutex;
+use crate::{ControlAddress, InvalidChannel, Pca9548a, SelectObserver, SelectionCoordinator};
+
+/// Something that provides an i2c bus for a channel.
+///
+/// This allows writing code that works both with devices behind a [`Pca9548a`] and
+/// devices connected to the bus directly:
+/// * [`Pca9548a`] returns a [`SubBus`](crate::SubBus) for the channel, and fails for ids beyond its channel
+///   count (see [`Pca9548a::with_channel_count`]).
+/// * A bus wrapped in [`DirectBus`] is passed through; the channel id is ignored.
+///
+/// Use this version in a non-async context. For a async version see [`AsyncChannelProvider`].
+///
+/// ```
+/// use embedded_hal::i2c::I2c;
+/// use pca9548a::ChannelProvider;
+///
+/// /// Read the temperature sensor on `channel`, whether it is muxed or not.
+/// fn read_temperature(provider: &mut impl ChannelProvider, channel: u8) -> Option<u8> {
+///     let mut buf = [0];
+///     provider
+///         .channel_bus(channel)
+///         .ok()?
+///         .write_read(0x48, &[0x00], &mut buf)
+///         .ok()?;
+///     Some(buf[0])
+/// }
+///
+/// # fn example(i2c_bus: impl I2c, other_bus: impl I2c) {
+/// # #[cfg(feature = "std")] {
+/// # use pca9548a::{DirectBus, Pca9548a, BASE_ADDRESS};
+/// let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, BASE_ADDRESS);
+/// read_temperature(&mut pca, 3);
+///
+/// let mut direct = DirectBus(other_bus);
+/// read_temperature(&mut direct, 0);
+/// # }
+/// # }
+/// ```
+#[cfg(feature = "sync")]
+pub trait ChannelProvider {
+    /// Get a bus for the channel `id`.
+    ///
+    /// Fails if `id` is not a valid channel for this provider.
+    fn channel_bus(&mut self, id: u8) -> Result<impl embedded_hal::i2c::I2c + '_, InvalidChannel>;
+}
+
+/// Something that provides an async i2c bus for a channel.
+///
+/// Use this version in an async context. For a non-async version see [`ChannelProvider`].
+///
+/// *Note:* see [`ChannelProvider`] for more info.
+#[cfg(feature = "async")]
+pub trait AsyncChannelProvider {
+    /// Get a bus for the channel `id`.
+    ///
+    /// Fails if `id` is not a valid channel for this provider.
+    fn channel_bus(
+        &mut self,
+        id: u8,
+    ) -> Result<impl embedded_hal_async::i2c::I2c + '_, InvalidChannel>;
+}
+
+/// A bus without a mux in front of it, to pass where a channel provider is expected.
+///
+/// Every channel id is accepted and ignored.
+#[derive(Debug)]
+pub struct DirectBus<Bus>(pub Bus);
+
+#[cfg(feature = "sync")]
+impl<Bus: embedded_hal::i2c::I2c> ChannelProvider for DirectBus<Bus> {
+    fn channel_bus(&mut self, _id: u8) -> Result<impl embedded_hal::i2c::I2c + '_, InvalidChannel> {
+        Ok(&mut self.0)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Bus: embedded_hal_async::i2c::I2c> AsyncChannelProvider for DirectBus<Bus> {
+    fn channel_bus(
+        &mut self,
+        _id: u8,
+    ) -> Result<impl embedded_hal_async::i2c::I2c + '_, InvalidChannel> {
+        Ok(&mut self.0)
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex, Coordinator, Pins, Observer, Address> ChannelProvider
+    for Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex: SyncMutex,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Address: ControlAddress,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    fn channel_bus(&mut self, id: u8) -> Result<impl embedded_hal::i2c::I2c + '_, InvalidChannel> {
+        self.device_channel(id)
+            .map(|channel| self.channel_subbus(channel))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Mutex, Coordinator, Pins, Observer, Address> AsyncChannelProvider
+    for Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex: AsyncMutex,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+    Address: ControlAddress,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    fn channel_bus(
+        &mut self,
+        id: u8,
+    ) -> Result<impl embedded_hal_async::i2c::I2c + '_, InvalidChannel> {
+        self.device_channel(id)
+            .map(|channel| self.channel_subbus(channel))
+    }
+}
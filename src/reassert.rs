@@ -0,0 +1,165 @@
+use core::ops::DerefMut;
+
+use embedded_hal::i2c::{ErrorType, Operation};
+use embedded_hal_async::i2c::I2c;
+
+use crate::{
+    AsyncMutex, BusError, ChannelMask, ControlAddress, MutexBase, Pca9548a, SelectObserver,
+    SelectionCoordinator,
+};
+
+/// A lock to the bus that writes its mask again before every transaction.
+///
+/// Returned by [`Pca9548a::reasserting_session_async`].
+///
+/// Holding the lock (e.g. with a [`MuxSession`](crate::MuxSession)) only keeps out tasks going through
+/// the same [`Pca9548a`]. If `Bus` is a shared bus (e.g. [shared_bus](https://docs.rs/shared-bus/latest/shared_bus/)),
+/// another driver may change the selection while a task is suspended at an `.await`, e.g. between
+/// two transactions or after it was cancelled and restarted. This session re-asserts the mask at the start
+/// of every transaction, so each transaction runs on the intended channels no matter what happened in between.
+/// With [`Self::with_verify`], the mask is also read back before the transaction.
+///
+/// *Note:* This costs one control-register write (and read) per transaction.
+pub struct ReassertingSession<'a, Mutex, Coordinator, Pins, Observer, Address, Guard> {
+    pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+    bus: Guard,
+    mask: u8,
+    verify: bool,
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex: AsyncMutex,
+{
+    /// Lock the bus and start a session that selects `mask` before every transaction.
+    ///
+    /// Nothing is written until the first transaction; see [`ReassertingSession`] for more info.
+    pub async fn reasserting_session_async(
+        &self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<
+        ReassertingSession<
+            '_,
+            Mutex,
+            Coordinator,
+            Pins,
+            Observer,
+            Address,
+            impl DerefMut<Target = Mutex::Bus> + '_,
+        >,
+        Mutex::Error,
+    > {
+        Ok(ReassertingSession {
+            pca: self,
+            bus: self.bus.lock().await?,
+            mask: mask.into().bits(),
+            verify: false,
+        })
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard>
+    ReassertingSession<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+{
+    /// Read the mask back after re-asserting it, and fail the transaction with
+    /// [`Error::VerifyFailed`](crate::Error::VerifyFailed) if it differs.
+    pub fn with_verify(mut self) -> Self {
+        self.verify = true;
+        self
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard>
+    ReassertingSession<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Address: ControlAddress,
+    Mutex: AsyncMutex,
+    Mutex::Bus: I2c + I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    /// Write the mask of the session, and read it back if enabled.
+    async fn reassert(&mut self) -> Result<(), BusError<Mutex>> {
+        self.pca.write_mask_async(&mut self.bus, self.mask).await?;
+        if !self.verify {
+            return Ok(());
+        }
+        let expected = self.pca.mask_policy.expected(self.mask);
+        let mut actual = [0];
+        self.bus
+            .read(self.pca.address, &mut actual)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))?;
+        actual[0] = self.pca.mask_policy.decode(actual[0]);
+        self.pca.cache.set(actual[0]);
+        if actual[0] != expected {
+            return Err(self.pca.last_error.verify_failed(expected, actual[0]));
+        }
+        Ok(())
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> ErrorType
+    for ReassertingSession<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: MutexBase,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: ErrorType,
+{
+    type Error = BusError<Mutex>;
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> I2c
+    for ReassertingSession<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Address: ControlAddress,
+    Mutex: AsyncMutex,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: I2c + I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.reassert().await?;
+        self.bus
+            .transaction(address, operations)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+
+    async fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        self.reassert().await?;
+        self.bus
+            .read(address, read)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+
+    async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        self.reassert().await?;
+        self.bus
+            .write(address, write)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+
+    async fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.reassert().await?;
+        self.bus
+            .write_read(address, write, read)
+            .await
+            .map_err(|e| self.pca.last_error.bus(e))
+    }
+}
@@ -0,0 +1,147 @@
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use embedded_hal::i2c::{ErrorType, Operation};
+
+#[cfg(feature = "async")]
+use crate::AsyncMutex;
+#[cfg(feature = "sync")]
+use crate::SyncMutex;
+use crate::{
+    mask_to_channels, BusError, ChannelMask, ControlAddress, MutexBase, Pca9548a, SelectObserver,
+    SelectionCoordinator,
+};
+
+/// A bus that moves to the next channel of a set on every transaction.
+///
+/// Returned by [`Pca9548a::round_robin`].
+///
+/// This lets a stock driver that is unaware of the mux cycle through several identical sensors,
+/// e.g. for reading them in rotation or for stress tests.
+/// The channels are used in ascending order, starting with the lowest one, and wrap around.
+///
+/// *Note:* Unlike [`SubBus`](crate::SubBus), the channel changes on **every** call,
+/// so a driver that splits one logical access into several calls (e.g. a `write` followed by a `read`)
+/// talks to different devices. Use `write_read` or `transaction` for accesses that must stay on one channel.
+/// If the set is empty, every transaction is performed with all channels deselected.
+///
+/// ```
+/// # #[cfg(feature = "sync")]
+/// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) {
+/// use embedded_hal::i2c::I2c;
+/// use pca9548a::{Channel, Pca9548a, BASE_ADDRESS};
+///
+/// let pca = Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, BASE_ADDRESS);
+/// let mut sensors = pca.round_robin([Channel::C0, Channel::C2, Channel::C5]);
+///
+/// let mut temperature = [0; 2];
+/// // Reads the sensor on channel 0, then 2, then 5, then 0 again.
+/// for _ in 0..4 {
+///     sensors.write_read(0x48, &[0x00], &mut temperature).unwrap();
+/// }
+/// # }
+/// ```
+pub struct RoundRobinBus<'a, Mutex, Coordinator, Pins, Observer, Address> {
+    pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+    channels: ChannelMask,
+    position: AtomicU8,
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Get a bus that moves to the next of the given channels on every transaction.
+    ///
+    /// * `channels` The channels to cycle through, see [`ChannelMask`] for the accepted forms
+    ///
+    /// See [`RoundRobinBus`] for more info.
+    pub fn round_robin(
+        &self,
+        channels: impl Into<ChannelMask>,
+    ) -> RoundRobinBus<'_, Mutex, Coordinator, Pins, Observer, Address> {
+        RoundRobinBus {
+            pca: self,
+            channels: channels.into(),
+            position: AtomicU8::new(0),
+        }
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    RoundRobinBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// The channel the next transaction goes to, or `None` if the set is empty.
+    pub fn next_channel(&self) -> Option<u8> {
+        let position = self.position.load(Ordering::Relaxed);
+        mask_to_channels(self.channels.bits()).nth(usize::from(position))
+    }
+
+    /// Get the mask for the next transaction and advance the rotation.
+    fn advance(&self) -> u8 {
+        let count = self.channels.bits().count_ones() as u8;
+        let Ok(position) =
+            self.position
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |position| {
+                    (count > 0).then(|| (position + 1) % count)
+                })
+        else {
+            return 0;
+        };
+        mask_to_channels(self.channels.bits())
+            .nth(usize::from(position))
+            .map_or(0, |id| 1 << id)
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address> ErrorType
+    for RoundRobinBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex: MutexBase,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: ErrorType,
+{
+    type Error = BusError<Mutex>;
+}
+
+#[cfg(feature = "async")]
+impl<Mutex, Coordinator, Pins, Observer, Address> embedded_hal_async::i2c::I2c
+    for RoundRobinBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: AsyncMutex,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mask = self.advance();
+        self.pca
+            .transaction_with_mask_async(mask, address, operations)
+            .await
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex, Coordinator, Pins, Observer, Address> embedded_hal::i2c::I2c
+    for RoundRobinBus<'_, Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mask = self.advance();
+        self.pca.transaction_with_mask(mask, address, operations)
+    }
+}
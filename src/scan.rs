@@ -0,0 +1,287 @@
+use embedded_hal::i2c::ErrorKind;
+
+#[cfg(feature = "async")]
+use crate::AsyncMutex;
+#[cfg(feature = "sync")]
+use crate::SyncMutex;
+use crate::{
+    mask_to_channels, BusError, ControlAddress, Error, MutexBase, Pca9548a, SelectObserver,
+    SelectionCoordinator, NO_CHANNELS,
+};
+#[cfg(feature = "heapless")]
+use crate::{possible_addresses, MUX_ADDRESSES};
+
+/// The addresses probed by a scan: all 7-bit addresses except the reserved ones.
+#[cfg(feature = "heapless")]
+const SCAN_ADDRESSES: core::ops::RangeInclusive<u8> = 0x08..=0x77;
+
+/// The number of addresses in [`SCAN_ADDRESSES`].
+#[cfg(feature = "heapless")]
+pub const SCAN_CAPACITY: usize = 0x77 - 0x08 + 1;
+
+/// The result of [`Pca9548a::detect_crosstalk`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CrosstalkReport {
+    /// The mask of the channels on which the device acknowledged its address
+    pub responding: u8,
+    /// Whether the device acknowledged its address with all channels deselected
+    pub responds_deselected: bool,
+}
+
+impl CrosstalkReport {
+    /// Whether the device responded on exactly one channel, and not with all channels deselected.
+    pub fn is_clean(&self) -> bool {
+        self.responding.count_ones() == 1 && !self.responds_deselected
+    }
+
+    /// Get the mask of the channels on which the device responded although it is on channel `id`.
+    pub fn unexpected(&self, id: u8) -> u8 {
+        self.responding & !(1u8.checked_shl(u32::from(id)).unwrap_or(0))
+    }
+}
+
+impl<Mutex: MutexBase, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Turn the result of probing a device into whether it is present.
+    ///
+    /// Any kind of NACK means that no device is there; other errors are real bus errors.
+    fn probed<Bus: embedded_hal::i2c::Error>(
+        &self,
+        result: Result<(), Bus>,
+    ) -> Result<bool, Error<Mutex::Error, Bus>> {
+        acknowledged(result).map_err(|e| self.last_error.bus(e))
+    }
+}
+
+/// Turn the result of probing an address into whether a device acknowledged it.
+///
+/// Any kind of NACK means that no device is there; other errors are real bus errors.
+fn acknowledged<E: embedded_hal::i2c::Error>(result: Result<(), E>) -> Result<bool, E> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) if is_nack(&e) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether `e` is any kind of NACK, i.e. no device answered, see [`acknowledged`].
+pub(crate) fn is_nack<E: embedded_hal::i2c::Error>(e: &E) -> bool {
+    matches!(e.kind(), ErrorKind::NoAcknowledge(_))
+}
+
+/// Get the addresses of all muxes of the family that respond on `bus`, in ascending order.
+///
+/// Use this version in an async context. For a non-async version see [`scan_for_muxes`].
+///
+/// *Note:* see [`scan_for_muxes`] for more info.
+#[cfg(all(feature = "async", feature = "heapless"))]
+pub async fn scan_for_muxes_async<Bus: embedded_hal_async::i2c::I2c>(
+    bus: &mut Bus,
+) -> Result<heapless::Vec<u8, MUX_ADDRESSES>, Bus::Error> {
+    let mut found = heapless::Vec::new();
+    for address in possible_addresses() {
+        if acknowledged(bus.write(address, &[]).await)? {
+            // There are only as many addresses as the capacity.
+            let _ = found.push(address);
+        }
+    }
+    Ok(found)
+}
+
+/// Get the addresses of all muxes of the family that respond on `bus`, in ascending order.
+///
+/// Use this version in a non-async context. For a async version see [`scan_for_muxes_async`].
+///
+/// Every address of [`possible_addresses`] is probed with an empty write, which doesn't change the selection
+/// of a mux; addresses that are not acknowledged are skipped, other bus errors abort the scan.
+/// Run this on the bus before wrapping it into a [`Pca9548a`], e.g. in bring-up tooling:
+/// ```
+/// # #[cfg(feature = "std")]
+/// # {
+/// use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+/// use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+/// use pca9548a::{possible_addresses, scan_for_muxes};
+///
+/// let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+/// let mut bus = Mock::new(
+///     &possible_addresses()
+///         .map(|address| {
+///             let probe = Transaction::write(address, vec![]);
+///             if address == 0x70 { probe } else { probe.with_error(nack) }
+///         })
+///         .collect::<Vec<_>>(),
+/// );
+/// assert_eq!(scan_for_muxes(&mut bus).unwrap(), [0x70]);
+/// # bus.done();
+/// # }
+/// ```
+#[cfg(all(feature = "sync", feature = "heapless"))]
+pub fn scan_for_muxes<Bus: embedded_hal::i2c::I2c>(
+    bus: &mut Bus,
+) -> Result<heapless::Vec<u8, MUX_ADDRESSES>, Bus::Error> {
+    let mut found = heapless::Vec::new();
+    for address in possible_addresses() {
+        if acknowledged(bus.write(address, &[]))? {
+            // There are only as many addresses as the capacity.
+            let _ = found.push(address);
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(feature = "async")]
+impl<Mutex: AsyncMutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    /// Check whether a device responds at `device_addr` on channel `id`.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::probe`].
+    ///
+    /// *Note:* see [`Self::probe`] for more info.
+    pub async fn probe_async(&self, id: u8, device_addr: u8) -> Result<bool, BusError<Mutex>> {
+        let mut bus = self.try_select_single_async(id).await?;
+        let result = embedded_hal_async::i2c::I2c::write(&mut *bus, device_addr, &[]).await;
+        self.probed(result)
+    }
+
+    /// Get the addresses of all devices that respond on channel `id`, in ascending order.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::scan_channel`].
+    ///
+    /// *Note:* see [`Self::scan_channel`] for more info.
+    #[cfg(feature = "heapless")]
+    pub async fn scan_channel_async(
+        &self,
+        id: u8,
+    ) -> Result<heapless::Vec<u8, SCAN_CAPACITY>, BusError<Mutex>> {
+        let mut bus = self.try_select_single_async(id).await?;
+        let mut found = heapless::Vec::new();
+        for device_addr in SCAN_ADDRESSES.filter(|&a| Some(a) != self.address.seven_bit()) {
+            let result = embedded_hal_async::i2c::I2c::write(&mut *bus, device_addr, &[]).await;
+            if self.probed(result)? {
+                // Can't fail, there is room for every address.
+                let _ = found.push(device_addr);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Check on which channels a device at `device_addr` responds, to find stuck switches or wiring faults.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::detect_crosstalk`].
+    ///
+    /// *Note:* see [`Self::detect_crosstalk`] for more info.
+    pub async fn detect_crosstalk_async(
+        &self,
+        device_addr: u8,
+    ) -> Result<CrosstalkReport, BusError<Mutex>> {
+        let mut bus = self
+            .bus_async()
+            .await
+            .map_err(|e| self.last_error.mutex(e))?;
+        let mut report = CrosstalkReport {
+            responding: 0,
+            responds_deselected: false,
+        };
+        self.write_mask_exact_async(&mut bus, NO_CHANNELS).await?;
+        let result = embedded_hal_async::i2c::I2c::write(&mut *bus, device_addr, &[]).await;
+        report.responds_deselected = self.probed(result)?;
+        for id in mask_to_channels(self.mask_policy.valid()) {
+            self.write_mask_exact_async(&mut bus, 1 << id).await?;
+            let result = embedded_hal_async::i2c::I2c::write(&mut *bus, device_addr, &[]).await;
+            if self.probed(result)? {
+                report.responding |= 1 << id;
+            }
+        }
+        self.write_mask_async(&mut bus, NO_CHANNELS).await?;
+        Ok(report)
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex: SyncMutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    /// Check whether a device responds at `device_addr` on channel `id`.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::probe_async`].
+    ///
+    /// The channel is selected and `device_addr` is probed with an empty write.
+    /// A NACK (of the address or of data, as reported by [`embedded_hal::i2c::Error::kind`]) means that
+    /// no device is there and gives `Ok(false)`, whatever error type the HAL uses.
+    /// Other bus errors, and all errors of the select, are returned.
+    pub fn probe(&self, id: u8, device_addr: u8) -> Result<bool, BusError<Mutex>> {
+        let mut bus = self.try_select_single(id)?;
+        let result = embedded_hal::i2c::I2c::write(&mut *bus, device_addr, &[]);
+        self.probed(result)
+    }
+
+    /// Get the addresses of all devices that respond on channel `id`, in ascending order.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::scan_channel_async`].
+    ///
+    /// The channel is selected once and every address in `0x08..=0x77` is probed like in [`Self::probe`]:
+    /// addresses that are not acknowledged are skipped, other bus errors abort the scan.
+    /// The address of the mux itself is skipped, as the mux responds on every channel.
+    #[cfg(feature = "heapless")]
+    pub fn scan_channel(
+        &self,
+        id: u8,
+    ) -> Result<heapless::Vec<u8, SCAN_CAPACITY>, BusError<Mutex>> {
+        let mut bus = self.try_select_single(id)?;
+        let mut found = heapless::Vec::new();
+        for device_addr in SCAN_ADDRESSES.filter(|&a| Some(a) != self.address.seven_bit()) {
+            let result = embedded_hal::i2c::I2c::write(&mut *bus, device_addr, &[]);
+            if self.probed(result)? {
+                // Can't fail, there is room for every address.
+                let _ = found.push(device_addr);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Check on which channels a device at `device_addr` responds, to find stuck switches or wiring faults.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::detect_crosstalk_async`].
+    ///
+    /// This assumes that the device is connected to exactly one channel and that no other device
+    /// with the same address is connected to the mux (or upstream of it).
+    /// Under one lock, the device is probed like in [`Self::probe`] first with all channels deselected
+    /// and then with each channel (up to the channel count, see [`Self::with_channel_count`]) selected alone.
+    /// If it responds with no channel selected or on more than one channel, a switch is stuck closed or
+    /// channels are shorted; see [`CrosstalkReport::is_clean`]. All channels are deselected afterwards.
+    ///
+    /// The sticky channels (see [`Self::set_sticky_channels`]) are disabled during the probes, so they don't
+    /// distort the report, and are enabled again by the final deselect.
+    pub fn detect_crosstalk(&self, device_addr: u8) -> Result<CrosstalkReport, BusError<Mutex>> {
+        let mut bus = self.bus().map_err(|e| self.last_error.mutex(e))?;
+        let mut report = CrosstalkReport {
+            responding: 0,
+            responds_deselected: false,
+        };
+        self.write_mask_exact(&mut bus, NO_CHANNELS)?;
+        let result = embedded_hal::i2c::I2c::write(&mut *bus, device_addr, &[]);
+        report.responds_deselected = self.probed(result)?;
+        for id in mask_to_channels(self.mask_policy.valid()) {
+            self.write_mask_exact(&mut bus, 1 << id)?;
+            let result = embedded_hal::i2c::I2c::write(&mut *bus, device_addr, &[]);
+            if self.probed(result)? {
+                report.responding |= 1 << id;
+            }
+        }
+        self.write_mask(&mut bus, NO_CHANNELS)?;
+        Ok(report)
+    }
+}
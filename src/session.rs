@@ -0,0 +1,148 @@
+#[cfg(feature = "async")]
+use core::future::Future;
+use core::ops::{Deref, DerefMut};
+
+#[cfg(feature = "async")]
+use crate::AsyncMutex;
+#[cfg(feature = "sync")]
+use crate::SyncMutex;
+#[cfg(feature = "async")]
+use crate::NO_CHANNELS;
+use crate::{
+    BusError, ChannelMask, ControlAddress, MutexBase, Pca9548a, SelectObserver,
+    SelectionCoordinator,
+};
+
+/// A lock to the bus that allows changing the selection in place.
+///
+/// Returned by [`Pca9548a::session`]/[`Pca9548a::session_async`].
+///
+/// Every select through a [`SubBus`](crate::SubBus) or [`Pca9548a::select_mask`] acquires a fresh lock.
+/// A session acquires the lock once and keeps it until it is dropped, so you can quickly alternate
+/// between channels (e.g. read sensor A, read sensor B, repeat) without other tasks interfering.
+///
+/// The session dereferences to the bus; use it to perform transactions on the current selection.
+pub struct MuxSession<'a, Mutex, Coordinator, Pins, Observer, Address, Guard> {
+    pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+    bus: Guard,
+}
+
+impl<'a, Mutex, Coordinator, Pins, Observer, Address, Guard>
+    MuxSession<'a, Mutex, Coordinator, Pins, Observer, Address, Guard>
+{
+    pub(crate) fn new(
+        pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+        bus: Guard,
+    ) -> Self {
+        Self { pca, bus }
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard>
+    MuxSession<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    /// Change the selection to `mask` without releasing the lock.
+    ///
+    /// Use this version in a non-async context. For a async version see [`Self::select_mask_async`].
+    pub fn select_mask(&mut self, mask: impl Into<ChannelMask>) -> Result<(), BusError<Mutex>> {
+        self.pca.write_mask(&mut self.bus, mask.into().bits())
+    }
+
+    /// Change the selection to a single channel without releasing the lock.
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    ///
+    /// Panics if `id` is out of range. With the no-panic feature, [`Error::InvalidChannel`](crate::Error::InvalidChannel)
+    /// is returned instead.
+    #[track_caller]
+    pub fn select_single(&mut self, id: u8) -> Result<(), BusError<Mutex>> {
+        self.pca.check_channel(id)?;
+        self.select_mask(1 << id)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'a, Mutex, Coordinator, Pins, Observer, Address, Guard>
+    MuxSession<'a, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Address: ControlAddress,
+    Mutex: AsyncMutex,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    /// Change the selection to `mask` without releasing the lock.
+    ///
+    /// Use this version in an async context. For a non-async version see [`Self::select_mask`].
+    pub async fn select_mask_async(
+        &mut self,
+        mask: impl Into<ChannelMask>,
+    ) -> Result<(), BusError<Mutex>> {
+        self.pca
+            .write_mask_async(&mut self.bus, mask.into().bits())
+            .await
+    }
+
+    /// Change the selection to a single channel without releasing the lock.
+    ///
+    /// * `id` The id of the subbus in range 0..=7
+    ///
+    /// Panics if `id` is out of range. With the no-panic feature, [`Error::InvalidChannel`](crate::Error::InvalidChannel)
+    /// is returned instead. The id is checked when this is called, not when the future is polled.
+    #[track_caller]
+    pub fn select_single_async<'s>(
+        &'s mut self,
+        id: u8,
+    ) -> impl Future<Output = Result<(), BusError<Mutex>>>
+           + use<'s, 'a, Mutex, Coordinator, Pins, Observer, Address, Guard> {
+        let checked = self.pca.check_channel(id);
+        async move {
+            checked?;
+            self.select_mask_async(1 << id).await
+        }
+    }
+
+    /// Deselect all channels and release the lock.
+    ///
+    /// A session that is dropped keeps its last selection; use this to leave the mux deselected
+    /// at the end of a scope and get the error of the deselect.
+    /// This disables all channels, including the sticky ones (see [`Pca9548a::set_sticky_channels`]).
+    pub async fn close(mut self) -> Result<(), BusError<Mutex>> {
+        self.pca
+            .write_mask_exact_async(&mut self.bus, NO_CHANNELS)
+            .await
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> Deref
+    for MuxSession<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: MutexBase,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    type Target = Mutex::Bus;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bus
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard> DerefMut
+    for MuxSession<'_, Mutex, Coordinator, Pins, Observer, Address, Guard>
+where
+    Mutex: MutexBase,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.bus
+    }
+}
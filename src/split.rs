@@ -0,0 +1,224 @@
+use core::ops::Deref;
+use std::sync::Arc;
+
+use embedded_hal::i2c::{ErrorType, Operation, SevenBitAddress};
+
+#[cfg(feature = "async")]
+use crate::AsyncMutex;
+#[cfg(feature = "sync")]
+use crate::SyncMutex;
+use crate::{
+    BusError, Channel, ControlAddress, MutexBase, Pca9548a, PinConfig, SelectObserver,
+    SelectionCoordinator, SubBus, CHANNELS,
+};
+
+/// A subbus that owns a share of its mux, so it can be moved into a driver or task.
+///
+/// Returned by [`Pca9548a::split`]. All handles of a mux share it (and thereby the mutex-protected bus)
+/// through an [`Arc`]: every call locks the bus once, selects the channel of the handle and performs
+/// the call, exactly like a [`SubBus`]. Calls through different handles are therefore serialized
+/// and can't observe each other's selection. The mux is dropped with the last handle.
+///
+/// It implements both [`embedded_hal::i2c::I2c`] and [`embedded_hal_async::i2c::I2c`] (with the respective features),
+/// so it can be handed to blocking drivers in threads as well as to async drivers in spawned tasks.
+pub struct OwnedSubBus<
+    Mutex,
+    Coordinator = (),
+    Pins = PinConfig,
+    Observer = (),
+    Address = SevenBitAddress,
+> {
+    pca: Arc<Pca9548a<Mutex, Coordinator, Pins, Observer, Address>>,
+    id: u8,
+}
+
+impl<Mutex: MutexBase, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Split the mux into one owned subbus per channel.
+    ///
+    /// Element `i` of the array is the subbus of channel `i`.
+    /// See [`OwnedSubBus`] for the sharing semantics.
+    pub fn split(self) -> [OwnedSubBus<Mutex, Coordinator, Pins, Observer, Address>; CHANNELS] {
+        let pca = Arc::new(self);
+        core::array::from_fn(|id| OwnedSubBus {
+            pca: pca.clone(),
+            id: id as u8,
+        })
+    }
+}
+
+/// A cheaply clonable handle to a mux, for sharing it between threads.
+///
+/// Returned by [`Pca9548a::into_shared`]. All clones share one mux (and thereby one bus) through an [`Arc`],
+/// so selects through different clones are serialized by the mutex of the mux, like calls on a `&Pca9548a`.
+/// If the mutex and the bus are `Send`, the handle is `Send + Sync` and can be moved into another thread.
+///
+/// The handle derefs to the [`Pca9548a`], so all of its methods are available; use [`Self::owned_subbus`]
+/// for a subbus that can be moved into a driver.
+pub struct SharedPca9548a<
+    Mutex,
+    Coordinator = (),
+    Pins = PinConfig,
+    Observer = (),
+    Address = SevenBitAddress,
+> {
+    pca: Arc<Pca9548a<Mutex, Coordinator, Pins, Observer, Address>>,
+}
+
+impl<Mutex: MutexBase, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Turn the mux into a handle that can be cloned and shared between threads.
+    ///
+    /// See [`SharedPca9548a`] for the sharing semantics.
+    pub fn into_shared(self) -> SharedPca9548a<Mutex, Coordinator, Pins, Observer, Address> {
+        SharedPca9548a {
+            pca: Arc::new(self),
+        }
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    SharedPca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Get an owned subbus of `channel`, sharing the mux with this handle.
+    ///
+    /// See [`OwnedSubBus`] for more info.
+    pub fn owned_subbus(
+        &self,
+        channel: Channel,
+    ) -> OwnedSubBus<Mutex, Coordinator, Pins, Observer, Address> {
+        OwnedSubBus {
+            pca: self.pca.clone(),
+            id: channel.id(),
+        }
+    }
+
+    /// Return the mux if this is the last handle to it (including [`OwnedSubBus`]es), otherwise `None`.
+    pub fn into_inner(self) -> Option<Pca9548a<Mutex, Coordinator, Pins, Observer, Address>> {
+        Arc::into_inner(self.pca)
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address> Clone
+    for SharedPca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    fn clone(&self) -> Self {
+        Self {
+            pca: self.pca.clone(),
+        }
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address> Deref
+    for SharedPca9548a<Mutex, Coordinator, Pins, Observer, Address>
+{
+    type Target = Pca9548a<Mutex, Coordinator, Pins, Observer, Address>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pca
+    }
+}
+
+impl<Mutex: MutexBase, Coordinator, Pins, Observer, Address>
+    OwnedSubBus<Mutex, Coordinator, Pins, Observer, Address>
+{
+    /// Get the id of the channel of this subbus.
+    pub fn channel(&self) -> u8 {
+        self.id
+    }
+
+    /// Get the shared mux, e.g. to read its control register.
+    pub fn pca(&self) -> &Pca9548a<Mutex, Coordinator, Pins, Observer, Address> {
+        &self.pca
+    }
+
+    fn subbus(&self) -> SubBus<'_, Mutex, Coordinator, Pins, Observer, Address> {
+        self.pca.subbus(1 << self.id)
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address> ErrorType
+    for OwnedSubBus<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Mutex: MutexBase,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: ErrorType,
+{
+    type Error = BusError<Mutex>;
+}
+
+#[cfg(feature = "async")]
+impl<Mutex, Coordinator, Pins, Observer, Address> embedded_hal_async::i2c::I2c
+    for OwnedSubBus<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: AsyncMutex,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal_async::i2c::I2c + embedded_hal_async::i2c::I2c<Address>,
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        embedded_hal_async::i2c::I2c::transaction(&mut self.subbus(), address, operations).await
+    }
+
+    async fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        embedded_hal_async::i2c::I2c::read(&mut self.subbus(), address, read).await
+    }
+
+    async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        embedded_hal_async::i2c::I2c::write(&mut self.subbus(), address, write).await
+    }
+
+    async fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        embedded_hal_async::i2c::I2c::write_read(&mut self.subbus(), address, write, read).await
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<Mutex, Coordinator, Pins, Observer, Address> embedded_hal::i2c::I2c
+    for OwnedSubBus<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.subbus().transaction(address, operations)
+    }
+
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        self.subbus().read(address, read)
+    }
+
+    fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        self.subbus().write(address, write)
+    }
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.subbus().write_read(address, write, read)
+    }
+}
@@ -0,0 +1,247 @@
+//! Helpers for testing code that uses this crate against [`embedded_hal_mock`], [`DryRun`] or [`NullBus`].
+
+use std::sync::{Arc, Mutex};
+use std::vec::Vec;
+
+use embedded_hal::i2c::{ErrorType, Operation};
+use embedded_hal_mock::eh1::i2c::Transaction;
+
+/// Position of the interrupt bits in the control register of devices with interrupt inputs.
+///
+/// On the PCA9543A/PCA9545A the upper nibble holds the (read-only) interrupt flags `INT3..INT0`,
+/// the lower nibble the channel enable bits.
+pub const INTERRUPT_SHIFT: u8 = crate::INTERRUPT_SHIFT;
+
+/// Encode the control register of a device with interrupt inputs.
+///
+/// * `interrupting` The ids of the channels that assert their interrupt, in range 0..=3
+/// * `mask` The currently enabled channels, in range 0..=0x0F
+pub fn interrupt_register(interrupting: &[u8], mask: u8) -> u8 {
+    assert!(mask <= 0x0F);
+    interrupting.iter().fold(mask, |register, &id| {
+        assert!(id < 4);
+        register | (1 << (id + INTERRUPT_SHIFT))
+    })
+}
+
+/// Build the mock transaction for reading the control register of a device with interrupt inputs.
+///
+/// * `address` The address of the device
+/// * `interrupting` The ids of the channels that assert their interrupt, in range 0..=3
+/// * `mask` The currently enabled channels, in range 0..=0x0F
+///
+/// ```
+/// use embedded_hal_mock::eh1::i2c::Transaction;
+/// use pca9548a::{test_util::read_interrupts, BASE_ADDRESS};
+///
+/// assert_eq!(
+///     read_interrupts(BASE_ADDRESS, &[0, 2], 1 << 1),
+///     Transaction::read(BASE_ADDRESS, vec![0b0101_0010]),
+/// );
+/// ```
+pub fn read_interrupts(address: u8, interrupting: &[u8], mask: u8) -> Transaction {
+    Transaction::read(address, vec![interrupt_register(interrupting, mask)])
+}
+
+/// Something a [`DryRun`] bus was asked to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    /// A mask was written to the control register of the mux.
+    Select(u8),
+    /// A transaction was issued to a device behind the mux.
+    Transaction {
+        /// The address of the device
+        address: u8,
+        /// The operations of the transaction
+        operations: Vec<RecordedOperation>,
+    },
+}
+
+/// An operation of a [`Record::Transaction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordedOperation {
+    /// The bytes that were written
+    Write(Vec<u8>),
+    /// The number of bytes that were read; the buffer is filled with zeros
+    Read(usize),
+}
+
+/// A bus that records the selects and transactions it would perform, without any real I2C traffic.
+///
+/// Writes of a single byte to the mux address are recorded as [`Record::Select`],
+/// reads from the mux address return the last selected mask (0 before the first select).
+/// Everything else is recorded as a [`Record::Transaction`] and always succeeds.
+///
+/// Clones share the recording, so you can keep a clone to inspect it after handing the bus to the mux:
+/// ```
+/// # #[cfg(feature = "sync")]
+/// # {
+/// use embedded_hal::i2c::I2c;
+/// use pca9548a::test_util::{DryRun, Record, RecordedOperation};
+/// use pca9548a::{Pca9548a, BASE_ADDRESS};
+///
+/// let bus = DryRun::new(BASE_ADDRESS);
+/// let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+///
+/// pca.single_subbus(2).write(0x42, &[1]).unwrap();
+///
+/// let records = bus.records();
+/// assert_eq!(
+///     records[..2],
+///     [
+///         Record::Select(1 << 2),
+///         Record::Transaction {
+///             address: 0x42,
+///             operations: vec![RecordedOperation::Write(vec![1])],
+///         },
+///     ]
+/// );
+/// # // With `deselect-on-drop`, the drop of the lock is recorded as a deselect.
+/// # let deselect: &[Record] = if cfg!(feature = "deselect-on-drop") { &[Record::Select(0)] } else { &[] };
+/// # assert_eq!(records[2..], *deselect);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DryRun {
+    mux_address: u8,
+    state: Arc<Mutex<DryRunState>>,
+}
+
+#[derive(Debug, Default)]
+struct DryRunState {
+    mask: u8,
+    records: Vec<Record>,
+}
+
+impl DryRun {
+    /// Create a new dry run bus for a mux at `mux_address`.
+    pub fn new(mux_address: u8) -> Self {
+        Self {
+            mux_address,
+            state: Arc::default(),
+        }
+    }
+
+    /// Get everything recorded so far, in order.
+    pub fn records(&self) -> Vec<Record> {
+        self.state().records.clone()
+    }
+
+    /// Get only the recorded selects, in order.
+    pub fn selects(&self) -> Vec<u8> {
+        self.state()
+            .records
+            .iter()
+            .filter_map(|record| match record {
+                Record::Select(mask) => Some(*mask),
+                Record::Transaction { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Forget everything recorded so far. The last selected mask is kept.
+    pub fn clear(&self) {
+        self.state().records.clear();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, DryRunState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record(&self, address: u8, operations: &mut [Operation<'_>]) {
+        let mut state = self.state();
+        if address == self.mux_address {
+            match operations {
+                [Operation::Write([mask])] => {
+                    state.mask = *mask;
+                    state.records.push(Record::Select(*mask));
+                    return;
+                }
+                [Operation::Read(buf)] => {
+                    let mask = state.mask;
+                    buf.fill(mask);
+                    return;
+                }
+                _ => {}
+            }
+        }
+        let operations = operations
+            .iter_mut()
+            .map(|operation| match operation {
+                Operation::Write(bytes) => RecordedOperation::Write(bytes.to_vec()),
+                Operation::Read(buf) => {
+                    buf.fill(0);
+                    RecordedOperation::Read(buf.len())
+                }
+            })
+            .collect();
+        state.records.push(Record::Transaction {
+            address,
+            operations,
+        });
+    }
+}
+
+impl ErrorType for DryRun {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_hal::i2c::I2c for DryRun {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.record(address, operations);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for DryRun {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.record(address, operations);
+        Ok(())
+    }
+}
+
+/// A bus that acknowledges everything, reads zeros and does nothing else.
+///
+/// Unlike a mock, it costs (almost) nothing, so it isolates the overhead of this crate,
+/// e.g. in the benchmarks (`cargo bench`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullBus;
+
+impl ErrorType for NullBus {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_hal::i2c::I2c for NullBus {
+    fn transaction(
+        &mut self,
+        _address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations {
+            if let Operation::Read(buf) = operation {
+                buf.fill(0);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for NullBus {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        embedded_hal::i2c::I2c::transaction(self, address, operations)
+    }
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ChannelLabels, CHANNELS};
+
+/// A description of the devices behind one mux, for field-configurable hardware.
+///
+/// Instead of compiling the board layout into the firmware, it can be stored (e.g. in a config partition)
+/// and loaded at boot with [`Self::from_bytes`].
+///
+/// Labels hold up to `L` bytes, each channel lists up to `D` expected device addresses.
+///
+/// The [postcard](https://docs.rs/postcard) encoding is, in this order:
+/// 1. `address`: one byte.
+/// 2. For each of the [`CHANNELS`] channels, in ascending order:
+///    1. `label`: `0x00` if there is none, otherwise `0x01`, the length as varint and the UTF-8 bytes.
+///    2. `devices`: the number of addresses as varint, followed by one byte per address.
+///
+/// ```
+/// use pca9548a::{MuxTopology, BASE_ADDRESS};
+///
+/// let mut topology = MuxTopology::<8, 2>::new(BASE_ADDRESS);
+/// topology.channels[1].label = Some("imu".try_into().unwrap());
+/// topology.channels[1].devices.push(0x68).unwrap();
+///
+/// let mut buf = [0; 64];
+/// let bytes = topology.to_slice(&mut buf).unwrap();
+/// assert_eq!(&bytes[..8], &[BASE_ADDRESS, 0, 0, 1, 3, b'i', b'm', b'u']);
+///
+/// let loaded = MuxTopology::<8, 2>::from_bytes(bytes).unwrap();
+/// assert_eq!(loaded, topology);
+/// assert_eq!(loaded.channel_of(0x68), Some(1));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct MuxTopology<const L: usize, const D: usize> {
+    /// The address of the mux
+    pub address: u8,
+    /// The channels, indexed by their id
+    pub channels: [ChannelTopology<L, D>; CHANNELS],
+}
+
+/// One channel of a [`MuxTopology`].
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ChannelTopology<const L: usize, const D: usize> {
+    /// The label of the channel
+    pub label: Option<heapless::String<L>>,
+    /// The addresses of the devices expected on the channel
+    pub devices: heapless::Vec<u8, D>,
+}
+
+impl<const L: usize, const D: usize> MuxTopology<L, D> {
+    /// Create a topology without any labels or devices for the mux at `address`.
+    pub fn new(address: u8) -> Self {
+        Self {
+            address,
+            ..Default::default()
+        }
+    }
+
+    /// Decode a topology from its postcard encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes(bytes)
+    }
+
+    /// Encode this topology into `buf` and return the used part of it.
+    pub fn to_slice<'b>(&self, buf: &'b mut [u8]) -> Result<&'b mut [u8], postcard::Error> {
+        postcard::to_slice(self, buf)
+    }
+
+    /// Get the labels of the channels.
+    pub fn labels(&self) -> ChannelLabels<L> {
+        let mut labels = ChannelLabels::new();
+        for (id, channel) in self.channels.iter().enumerate() {
+            if let Some(label) = &channel.label {
+                // Can't fail, both labels have the same capacity.
+                let _ = labels.label_channel(id as u8, label);
+            }
+        }
+        labels
+    }
+
+    /// Get the id of the first channel on which a device with `device_addr` is expected.
+    pub fn channel_of(&self, device_addr: u8) -> Option<u8> {
+        self.channels
+            .iter()
+            .position(|channel| channel.devices.contains(&device_addr))
+            .map(|id| id as u8)
+    }
+}
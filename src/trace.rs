@@ -0,0 +1,53 @@
+use crate::{mask_to_channels, ControlAddress, CHANNELS};
+
+/// The address of the control register, formatted as hex (or `10-bit` for a [`TenBitAddress`](crate::TenBitAddress)).
+struct Address(Option<u8>);
+
+impl defmt::Format for Address {
+    fn format(&self, f: defmt::Formatter) {
+        match self.0 {
+            Some(address) => defmt::write!(f, "{=u8:#04x}", address),
+            None => defmt::write!(f, "10-bit"),
+        }
+    }
+}
+
+/// A mask, formatted as the list of the enabled channel ids.
+struct Channels(u8);
+
+impl defmt::Format for Channels {
+    fn format(&self, f: defmt::Formatter) {
+        let mut ids = [0; CHANNELS];
+        let mut len = 0;
+        for id in mask_to_channels(self.0) {
+            ids[len] = id;
+            len += 1;
+        }
+        defmt::write!(f, "{=[u8]}", &ids[..len]);
+    }
+}
+
+/// Trace a select of `mask` on the mux at `address`.
+pub(crate) fn select(address: impl ControlAddress, mask: u8) {
+    defmt::trace!(
+        "pca9548a@{}: select channels {}",
+        Address(address.seven_bit()),
+        Channels(mask)
+    );
+}
+
+/// Trace an operation of kind `kind` on the device at `device_addr`, behind the channels of `mask`.
+pub(crate) fn operation(
+    address: impl ControlAddress,
+    mask: u8,
+    kind: &'static str,
+    device_addr: u8,
+) {
+    defmt::trace!(
+        "pca9548a@{}: {=str} {=u8:#04x} on channels {}",
+        Address(address.seven_bit()),
+        kind,
+        device_addr,
+        Channels(mask)
+    );
+}
@@ -0,0 +1,249 @@
+use core::ops::DerefMut;
+
+use embedded_hal::i2c::{ErrorType, Operation, SevenBitAddress};
+
+use crate::{
+    BusError, ControlAddress, Pca9548a, PinConfig, SelectObserver, SelectionCoordinator, SyncMutex,
+    CHANNELS, NO_CHANNELS,
+};
+
+/// A typestate session with no channel selected.
+///
+/// Returned by [`Pca9548a::open`]. The session moves between two states:
+/// [`Self::select`] turns it into a [`Selected`] session, which implements [`embedded_hal::i2c::I2c`]
+/// for the selected channel, and [`Selected::close`] deselects the channel and turns it back into an `Idle` one.
+/// Both transitions consume the session, so the compiler rejects code that selects a second channel
+/// before the first one was closed, or that keeps using a channel after it was closed.
+/// The channel is a const parameter; an out-of-range channel is a compile-time error.
+///
+/// This is for single-channel-at-a-time designs that want the strongest static guarantees.
+/// The flexible API (e.g. [`Pca9548a::subbus`]) stays available for everything else.
+///
+/// Example:
+/// ```
+/// # #[cfg(feature = "sync")]
+/// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) -> Result<(), Box<dyn std::error::Error>> {
+/// use embedded_hal::i2c::I2c;
+/// use pca9548a::{Pca9548a, BASE_ADDRESS};
+///
+/// let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, BASE_ADDRESS);
+///
+/// let idle = pca.open();
+/// let mut sensor_a = idle.select::<0>().map_err(|_| "select")?;
+/// sensor_a.write(0x48, &[0x01]).map_err(|_| "write")?;
+/// // `idle.select::<1>()` would not compile here: `idle` was moved into `sensor_a`.
+///
+/// let idle = sensor_a.close().map_err(|_| "close")?;
+/// let mut sensor_b = idle.select::<1>().map_err(|_| "select")?;
+/// sensor_b.write(0x48, &[0x01]).map_err(|_| "write")?;
+/// sensor_b.close().map_err(|_| "close")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Idle<
+    'a,
+    Mutex,
+    Coordinator = (),
+    Pins = PinConfig,
+    Observer = (),
+    Address = SevenBitAddress,
+> {
+    pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+}
+
+/// A typestate session with channel `CH` selected; see [`Idle`].
+///
+/// The bus is locked for the lifetime of the session.
+/// If it is dropped instead of closed, the channel is deselected on a best-effort basis (errors are ignored).
+pub struct Selected<'a, Mutex, Coordinator, Pins, Observer, Address, Guard, const CH: u8>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    pca: &'a Pca9548a<Mutex, Coordinator, Pins, Observer, Address>,
+    bus: Option<Guard>,
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address>
+    Pca9548a<Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    /// Start a typestate session; see [`Idle`].
+    ///
+    /// The session borrows the mux mutably, so there is only one session at a time.
+    /// This also means that a mux which is shared, e.g. through a `&'static` reference or between tasks,
+    /// can't be opened; use the lock-based API (e.g. [`Pca9548a::select_single`]) there.
+    ///
+    /// ```compile_fail
+    /// # fn example(i2c_bus: impl embedded_hal::i2c::I2c) {
+    /// # let mut pca = pca9548a::Pca9548a::<std::sync::Mutex<_>>::new(i2c_bus, pca9548a::BASE_ADDRESS);
+    /// let first = pca.open();
+    /// let second = pca.open();
+    /// # drop((first, second));
+    /// # }
+    /// ```
+    pub fn open(&mut self) -> Idle<'_, Mutex, Coordinator, Pins, Observer, Address> {
+        Idle { pca: self }
+    }
+}
+
+impl<'a, Mutex, Coordinator, Pins, Observer, Address>
+    Idle<'a, Mutex, Coordinator, Pins, Observer, Address>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+{
+    /// Get the cached mask of the mux; see [`Pca9548a::cached_mask`].
+    ///
+    /// The session borrows the mux, so this is the way to look at it in between.
+    pub fn cached_mask(&self) -> Option<u8> {
+        self.pca.cached_mask()
+    }
+
+    /// Lock the bus and select channel `CH`.
+    #[allow(clippy::type_complexity)]
+    pub fn select<const CH: u8>(
+        self,
+    ) -> Result<
+        Selected<
+            'a,
+            Mutex,
+            Coordinator,
+            Pins,
+            Observer,
+            Address,
+            impl DerefMut<Target = Mutex::Bus> + 'a,
+            CH,
+        >,
+        BusError<Mutex>,
+    > {
+        const { assert!((CH as usize) < CHANNELS, "CH must be less than CHANNELS") };
+        let mut bus = self.pca.bus().map_err(|e| self.pca.last_error.mutex(e))?;
+        self.pca.write_mask(&mut bus, 1 << CH)?;
+        Ok(Selected {
+            pca: self.pca,
+            bus: Some(bus),
+        })
+    }
+}
+
+impl<'a, Mutex, Coordinator, Pins, Observer, Address, Guard, const CH: u8>
+    Selected<'a, Mutex, Coordinator, Pins, Observer, Address, Guard, CH>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    /// Deselect the channel and release the lock.
+    ///
+    /// This disables all channels, including the sticky ones (see [`Pca9548a::set_sticky_channels`]).
+    ///
+    /// If the deselect fails, the lock is released anyway and the error is returned;
+    /// start a new session with [`Pca9548a::open`] to retry.
+    pub fn close(
+        mut self,
+    ) -> Result<Idle<'a, Mutex, Coordinator, Pins, Observer, Address>, BusError<Mutex>> {
+        let mut bus = self.bus.take().expect("bus lock is only taken on close");
+        self.pca.write_mask_exact(&mut bus, NO_CHANNELS)?;
+        Ok(Idle { pca: self.pca })
+    }
+
+    fn bus(&mut self) -> &mut Mutex::Bus {
+        self.bus.as_mut().expect("bus lock is only taken on close")
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard, const CH: u8> Drop
+    for Selected<'_, Mutex, Coordinator, Pins, Observer, Address, Guard, CH>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    fn drop(&mut self) {
+        if let Some(mut bus) = self.bus.take() {
+            let _ = self.pca.write_mask_exact(&mut bus, NO_CHANNELS);
+        }
+    }
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard, const CH: u8> ErrorType
+    for Selected<'_, Mutex, Coordinator, Pins, Observer, Address, Guard, CH>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    type Error = BusError<Mutex>;
+}
+
+impl<Mutex, Coordinator, Pins, Observer, Address, Guard, const CH: u8> embedded_hal::i2c::I2c
+    for Selected<'_, Mutex, Coordinator, Pins, Observer, Address, Guard, CH>
+where
+    Address: ControlAddress,
+    Mutex: SyncMutex,
+    Mutex::Error: core::fmt::Debug,
+    Mutex::Bus: embedded_hal::i2c::I2c + embedded_hal::i2c::I2c<Address>,
+    Coordinator: SelectionCoordinator,
+    Observer: SelectObserver,
+    Guard: DerefMut<Target = Mutex::Bus>,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let pca = self.pca;
+        self.bus()
+            .transaction(address, operations)
+            .map_err(|e| pca.last_error.bus(e))
+    }
+
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        let pca = self.pca;
+        self.bus()
+            .read(address, read)
+            .map_err(|e| pca.last_error.bus(e))
+    }
+
+    fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        let pca = self.pca;
+        self.bus()
+            .write(address, write)
+            .map_err(|e| pca.last_error.bus(e))
+    }
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let pca = self.pca;
+        self.bus()
+            .write_read(address, write, read)
+            .map_err(|e| pca.last_error.bus(e))
+    }
+}
@@ -0,0 +1,1395 @@
+#![cfg(feature = "async")]
+// `MuxGroup::mux` is deprecated with the no-panic feature, but still tested.
+#![cfg_attr(feature = "no-panic", allow(deprecated))]
+
+mod common;
+
+use common::TestMutex;
+use embassy_futures::block_on;
+use embedded_hal_async::i2c::I2c;
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+use pca9548a::{MuxGroup, Pca9548a, BASE_ADDRESS};
+
+#[test]
+fn select_single_async_writes_mask() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+        Transaction::write(0x42, vec![1, 2]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        let mut guard = pca.select_single_async(5).await.unwrap();
+        guard.write(0x42, &[1, 2]).await.unwrap();
+    });
+
+    bus.done();
+}
+
+#[test]
+fn subbus_selects_before_transaction_async() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1]),
+        Transaction::transaction_start(0x42),
+        Transaction::write(0x42, vec![7]),
+        Transaction::transaction_end(0x42),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut subbus = pca.single_subbus(0);
+    block_on(subbus.transaction(0x42, &mut [embedded_hal::i2c::Operation::Write(&[7])])).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn enabled_subbuses_async_yields_enabled_channels() {
+    let mut bus = Mock::new(&[Transaction::read(BASE_ADDRESS, vec![0b0001_1000])]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let ids: Vec<_> = block_on(pca.enabled_subbuses_async())
+        .unwrap()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(ids, [3, 4]);
+
+    bus.done();
+}
+
+#[test]
+fn cancelled_future_deselects() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let transaction = async {
+        let mut guard = pca.select_mask_cancel_safe_async(1 << 2).await.unwrap();
+        guard.write(0x42, &[1]).await.unwrap();
+        core::future::pending::<()>().await;
+        guard.write(0x42, &[2]).await.unwrap();
+    };
+    // Poll once, then drop the unfinished future.
+    assert!(embassy_futures::poll_once(transaction).is_pending());
+
+    bus.done();
+}
+
+#[test]
+fn cancelled_select_mask_async_leaves_a_sane_state() {
+    use core::cell::Cell;
+    use embedded_hal::i2c::{ErrorKind, ErrorType, Operation};
+
+    /// Never completes its first transaction, like a write cut short by a cancel.
+    struct StallingBus {
+        stall_next: bool,
+        writes: Vec<Vec<u8>>,
+    }
+
+    impl ErrorType for StallingBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for StallingBus {
+        async fn transaction(
+            &mut self,
+            _address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            if core::mem::take(&mut self.stall_next) {
+                core::future::pending::<()>().await;
+            }
+            for operation in operations {
+                if let Operation::Write(bytes) = operation {
+                    self.writes.push(bytes.to_vec());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Counts the coordinator hooks that are not released yet.
+    #[derive(Default)]
+    struct Pending(Cell<i32>);
+
+    impl pca9548a::SelectionCoordinator for Pending {
+        fn acquire(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+
+        fn release(&self) {
+            self.0.set(self.0.get() - 1);
+        }
+    }
+
+    let bus = StallingBus {
+        stall_next: false,
+        writes: Vec::new(),
+    };
+    let pca = Pca9548a::<TestMutex<_>>::new(bus, BASE_ADDRESS).with_coordinator(Pending::default());
+
+    block_on(async {
+        drop(pca.select_mask_async(1 << 4).await.unwrap());
+        assert_eq!(pca.cached_mask(), Some(1 << 4));
+
+        pca.bus_async().await.unwrap().stall_next = true;
+        // Poll once, then drop the select in the middle of its write.
+        assert!(embassy_futures::poll_once(pca.select_mask_async(1 << 3)).is_pending());
+        assert_eq!(pca.cached_mask(), None);
+        assert_eq!(pca.coordinator().0.get(), 0);
+
+        // The next select writes again, even for the same mask, and the lock was released.
+        drop(pca.select_mask_async(1 << 3).await.unwrap());
+        assert_eq!(pca.cached_mask(), Some(1 << 3));
+        assert_eq!(pca.bus_async().await.unwrap().writes, [[1 << 4], [1 << 3]]);
+    });
+}
+
+#[test]
+fn dropped_guard_deselects_like_any_other_write() {
+    use embedded_hal::i2c::ErrorKind;
+    use pca9548a::LastError;
+
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<u8>>);
+
+    impl pca9548a::SelectObserver for &Recorder {
+        fn on_select(&self, mask: u8) {
+            self.0.lock().unwrap().push(mask);
+        }
+    }
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::write(BASE_ADDRESS, vec![0]).with_error(ErrorKind::Bus),
+    ]);
+    let recorder = Recorder::default();
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_observer(&recorder)
+        .with_error_latch();
+
+    block_on(async {
+        drop(pca.select_mask_cancel_safe_async(1 << 2).await.unwrap());
+        assert_eq!(pca.cached_mask(), Some(0));
+        drop(pca.select_mask_cancel_safe_async(1 << 3).await.unwrap());
+    });
+    // The deselect is observed, and its error is latched.
+    assert_eq!(*recorder.0.lock().unwrap(), [1 << 2, 0, 1 << 3]);
+    assert_eq!(pca.last_error(), Some(LastError::Bus(ErrorKind::Bus)));
+    assert_eq!(pca.cached_mask(), None);
+
+    bus.done();
+}
+
+#[test]
+fn keep_selection_skips_deselect() {
+    let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![1])]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        let guard = pca.select_mask_cancel_safe_async(1).await.unwrap();
+        drop(guard.keep_selection());
+    });
+
+    bus.done();
+}
+
+#[test]
+fn broadcast_write_async_writes_each_channel() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write(0x42, vec![9, 8]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::write(0x42, vec![9, 8]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+        Transaction::write(0x42, vec![9, 8]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(pca.broadcast_write_async(0x42, &[9, 8], &[0, 3, 6])).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn broadcast_write_async_stops_on_error() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write(0x42, vec![9]).with_error(embedded_hal::i2c::ErrorKind::Bus),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(matches!(
+        block_on(pca.broadcast_write_async(0x42, &[9], &[0, 1])),
+        Err(pca9548a::Error::Bus(embedded_hal::i2c::ErrorKind::Bus))
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn select_single_ready_async_retries_until_ack() {
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+
+    let nack = ErrorKind::NoAcknowledge(embedded_hal::i2c::NoAcknowledgeSource::Address);
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![]).with_error(nack),
+        Transaction::write(0x42, vec![]).with_error(nack),
+        Transaction::write(0x42, vec![]),
+        Transaction::write(0x42, vec![1]),
+    ]);
+    let mut delay = CheckedDelay::new(&[
+        DelayTransaction::async_delay_us(500),
+        DelayTransaction::async_delay_us(500),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS).with_delay(delay.clone());
+
+    block_on(async {
+        let mut guard = pca
+            .select_single_ready_async(2, 0x42, 500, 3)
+            .await
+            .unwrap();
+        guard.write(0x42, &[1]).await.unwrap();
+    });
+
+    delay.done();
+    bus.done();
+}
+
+#[test]
+fn select_single_ready_async_gives_up() {
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+
+    let nack = ErrorKind::NoAcknowledge(embedded_hal::i2c::NoAcknowledgeSource::Address);
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1]),
+        Transaction::write(0x42, vec![]).with_error(nack),
+        Transaction::write(0x42, vec![]).with_error(nack),
+    ]);
+    let mut delay = CheckedDelay::new(&[DelayTransaction::async_delay_us(10)]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS).with_delay(delay.clone());
+
+    let result = block_on(async {
+        pca.select_single_ready_async(0, 0x42, 10, 1)
+            .await
+            .map(|_| ())
+    });
+    assert!(matches!(result, Err(pca9548a::Error::Bus(e)) if e == nack));
+
+    delay.done();
+    bus.done();
+}
+
+#[test]
+fn select_single_ready_async_returns_other_errors_right_away() {
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::delay::CheckedDelay;
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+        Transaction::write(0x42, vec![]).with_error(ErrorKind::ArbitrationLoss),
+    ]);
+    let mut delay = CheckedDelay::new(&[]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS).with_delay(delay.clone());
+
+    let result = block_on(async {
+        pca.select_single_ready_async(5, 0x42, 10, 3)
+            .await
+            .map(|_| ())
+    });
+    assert!(matches!(
+        result,
+        Err(pca9548a::Error::Bus(ErrorKind::ArbitrationLoss))
+    ));
+
+    delay.done();
+    bus.done();
+}
+
+#[test]
+fn transaction_with_mask_async_multi_channel() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0b11]),
+        Transaction::transaction_start(0x42),
+        Transaction::write(0x42, vec![1]),
+        Transaction::transaction_end(0x42),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(pca.transaction_with_mask_async(
+        0b11,
+        0x42,
+        &mut [embedded_hal::i2c::Operation::Write(&[1])],
+    ))
+    .unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn session_async_switches_channels_under_one_lock() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 4]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+        Transaction::write(0x42, vec![2]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        let mut session = pca.session_async().await.unwrap();
+        session.select_single_async(4).await.unwrap();
+        session.write(0x42, &[1]).await.unwrap();
+        session.select_single_async(5).await.unwrap();
+        session.write(0x42, &[2]).await.unwrap();
+    });
+
+    assert_eq!(common::lock_count(), 1);
+    bus.done();
+}
+
+#[test]
+fn mux_group_deselects_other_muxes_async() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS + 1, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![1]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS + 1, vec![1 << 7]),
+        Transaction::write(0x42, vec![2]),
+    ]);
+    let group = MuxGroup::<TestMutex<_>, 2>::new(bus.clone(), [BASE_ADDRESS, BASE_ADDRESS + 1]);
+
+    block_on(async {
+        group
+            .mux(0)
+            .select_single_async(0)
+            .await
+            .unwrap()
+            .write(0x42, &[1])
+            .await
+            .unwrap();
+        group
+            .mux(1)
+            .single_subbus(7)
+            .write(0x42, &[2])
+            .await
+            .unwrap();
+    });
+
+    bus.done();
+}
+
+#[test]
+fn mux_group_subbus_forwards_operations_async() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS + 1, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write_read(0x42, vec![1], vec![2]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS + 1, vec![0b1000_0001]),
+        Transaction::read(0x42, vec![3]),
+    ]);
+    let group = MuxGroup::<TestMutex<_>, 2>::from_muxes(
+        bus.clone(),
+        [
+            Pca9548a::group_member(BASE_ADDRESS),
+            Pca9548a::group_member(BASE_ADDRESS + 1),
+        ],
+    );
+
+    block_on(async {
+        let mut read = [0];
+        group
+            .mux(0)
+            .subbus([Channel::C2])
+            .write_read(0x42, &[1], &mut read)
+            .await
+            .unwrap();
+        assert_eq!(read, [2]);
+        group
+            .mux(1)
+            .subbus([Channel::C0, Channel::C7])
+            .read(0x42, &mut read)
+            .await
+            .unwrap();
+        assert_eq!(read, [3]);
+    });
+
+    bus.done();
+}
+
+#[test]
+fn dual_mux_routes_channels_and_deselects_the_other_mux_async() {
+    use pca9548a::DualMux;
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS + 1, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS + 1, vec![1 << 7]),
+        Transaction::write(0x42, vec![2]),
+    ]);
+    let mux = DualMux::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS, BASE_ADDRESS + 1);
+
+    block_on(async {
+        mux.select_single_async(1)
+            .await
+            .unwrap()
+            .write(0x42, &[1])
+            .await
+            .unwrap();
+        mux.single_subbus(15).write(0x42, &[2]).await.unwrap();
+    });
+
+    bus.done();
+}
+
+#[test]
+fn subbus_empty_transaction_only_selects_async() {
+    let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![1 << 5])]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(pca.single_subbus(5).transaction(0x42, &mut [])).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn subbus_mixed_operations_select_once_async() {
+    use embedded_hal::i2c::Operation;
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+        Transaction::transaction_start(0x42),
+        Transaction::write(0x42, vec![0x10]),
+        Transaction::read(0x42, vec![0xaa]),
+        Transaction::write(0x42, vec![0x12]),
+        Transaction::transaction_end(0x42),
+        Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+        Transaction::write_read(0x42, vec![0x11], vec![0xcc]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut subbus = pca.single_subbus(6);
+
+    block_on(async {
+        let mut buf = [0];
+        subbus
+            .transaction(
+                0x42,
+                &mut [
+                    Operation::Write(&[0x10]),
+                    Operation::Read(&mut buf),
+                    Operation::Write(&[0x12]),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(buf, [0xaa]);
+        assert_eq!(common::lock_count(), 1);
+
+        subbus.write_read(0x42, &[0x11], &mut buf).await.unwrap();
+        assert_eq!(buf, [0xcc]);
+        assert_eq!(common::lock_count(), 2);
+    });
+
+    bus.done();
+}
+
+#[test]
+fn dry_run_records_async() {
+    use pca9548a::test_util::{DryRun, Record, RecordedOperation};
+
+    let bus = DryRun::new(BASE_ADDRESS);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(pca.single_subbus(5).write(0x42, &[1])).unwrap();
+
+    assert_eq!(
+        bus.records(),
+        [
+            Record::Select(1 << 5),
+            Record::Transaction {
+                address: 0x42,
+                operations: vec![RecordedOperation::Write(vec![1])],
+            },
+        ]
+    );
+}
+
+#[test]
+fn select_mask_async_accepts_channel_ids() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0b0000_1010]),
+        Transaction::write(BASE_ADDRESS, vec![0b0000_0001]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        drop(
+            pca.select_mask_async([Channel::C1, Channel::C3])
+                .await
+                .unwrap(),
+        );
+        let mut session = pca.session_async().await.unwrap();
+        session.select_mask_async(1).await.unwrap();
+    });
+
+    bus.done();
+}
+
+#[test]
+fn select_mask_verified_guard_async_fails_on_mismatch() {
+    let mut bus = Mock::new(&[Transaction::write_read(
+        BASE_ADDRESS,
+        vec![1 << 1],
+        vec![1 << 4],
+    )]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let result = block_on(async { pca.select_mask_verified_guard_async(1 << 1).await.map(drop) });
+    assert!(matches!(
+        result,
+        Err(pca9548a::Error::VerifyFailed {
+            expected: 0b0000_0010,
+            actual: 0b0001_0000
+        })
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn subbus_chunks_long_transfers_async() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write_read(0x50, vec![0x00, 0x10], vec![1, 2, 3]),
+        Transaction::read(0x50, vec![4, 5, 6]),
+        Transaction::read(0x50, vec![7]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut subbus = pca.single_subbus(2).with_max_chunk(3);
+
+    let mut buf = [0; 7];
+    block_on(subbus.write_read(0x50, &[0x00, 0x10], &mut buf)).unwrap();
+    assert_eq!(buf, [1, 2, 3, 4, 5, 6, 7]);
+
+    bus.done();
+}
+
+#[test]
+fn raw_bus_async_does_not_select() {
+    let mut bus = Mock::new(&[Transaction::write(0x20, vec![1])]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        pca.raw_bus_async()
+            .await
+            .unwrap()
+            .write(0x20, &[1])
+            .await
+            .unwrap();
+    });
+    assert_eq!(pca.cached_mask(), None);
+
+    bus.done();
+}
+
+#[test]
+fn error_latch_records_verify_failure_async() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::read(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_error_latch()
+        .with_split_verify();
+
+    assert!(
+        block_on(async { pca.select_mask_verified_guard_async(1 << 1).await.map(drop) }).is_err()
+    );
+    assert_eq!(
+        pca.last_error(),
+        Some(pca9548a::LastError::VerifyFailed {
+            expected: 1 << 1,
+            actual: 0
+        })
+    );
+
+    bus.done();
+}
+
+#[test]
+fn round_robin_advances_async() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::transaction_start(0x48),
+        Transaction::write(0x48, vec![1]),
+        Transaction::transaction_end(0x48),
+        Transaction::write(BASE_ADDRESS, vec![1 << 7]),
+        Transaction::transaction_start(0x48),
+        Transaction::write(0x48, vec![2]),
+        Transaction::transaction_end(0x48),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut sensors = pca.round_robin([Channel::C0, Channel::C7]);
+
+    block_on(async {
+        sensors.write(0x48, &[1]).await.unwrap();
+        sensors.write(0x48, &[2]).await.unwrap();
+    });
+    assert_eq!(sensors.next_channel(), Some(0));
+
+    bus.done();
+}
+
+#[cfg(not(feature = "no-panic"))]
+#[test]
+fn invalid_channel_panics_at_call_site_async() {
+    let bus = Mock::new(&[]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(
+        common::panic_location(|| drop(pca.select_single_async(8))),
+        file!()
+    );
+    // Checked before the first channel is written.
+    assert_eq!(
+        common::panic_location(|| drop(pca.broadcast_write_async(0x42, &[1], &[1, 8]))),
+        file!()
+    );
+    let session = std::panic::AssertUnwindSafe(block_on(pca.session_async()).unwrap());
+    assert_eq!(
+        common::panic_location(move || {
+            let mut session = session;
+            drop(session.select_single_async(8));
+        }),
+        file!()
+    );
+    let mut delay = embedded_hal_mock::eh1::delay::CheckedDelay::new(&[]);
+    let pca = pca.with_delay(delay.clone());
+    assert_eq!(
+        common::panic_location(|| drop(pca.select_single_ready_async(8, 0x42, 10, 1))),
+        file!()
+    );
+    delay.done();
+
+    bus.clone().done();
+}
+
+#[test]
+fn select_channel_async_selects_channel() {
+    let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![1 << 3])]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        drop(
+            pca.select_channel_async(pca9548a::Channel::C3)
+                .await
+                .unwrap(),
+        )
+    });
+
+    bus.done();
+}
+
+#[test]
+fn select_channels_iter_async_selects_all_channels() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![0b1010_0000])]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let channels = (5..8).filter_map(Channel::new).filter(|c| c.id() != 6);
+    block_on(async { drop(pca.select_channels_iter_async(channels).await.unwrap()) });
+
+    bus.done();
+}
+
+#[test]
+fn async_channel_provider_muxed_and_direct() {
+    use pca9548a::{AsyncChannelProvider, DirectBus, InvalidChannel};
+
+    async fn poke(provider: &mut impl AsyncChannelProvider, channel: u8) {
+        provider
+            .channel_bus(channel)
+            .unwrap()
+            .write(0x42, &[channel])
+            .await
+            .unwrap();
+    }
+
+    let mut muxed = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+        Transaction::write(0x42, vec![5]),
+    ]);
+    let mut pca = Pca9548a::<TestMutex<_>>::new(muxed.clone(), BASE_ADDRESS);
+    block_on(poke(&mut pca, 5));
+    assert!(matches!(pca.channel_bus(8), Err(InvalidChannel(8))));
+    muxed.done();
+
+    let mut unused = Mock::new(&[]);
+    let mut four =
+        Pca9548a::<TestMutex<_>>::new(unused.clone(), BASE_ADDRESS).with_channel_count(4);
+    assert!(matches!(four.channel_bus(6), Err(InvalidChannel(6))));
+    unused.done();
+
+    let mut direct = DirectBus(Mock::new(&[Transaction::write(0x42, vec![2])]));
+    block_on(poke(&mut direct, 2));
+    direct.0.done();
+}
+
+#[test]
+fn fair_mutex_serves_waiters_in_order() {
+    use embassy_futures::{join::join3, yield_now};
+    use pca9548a::{AsyncMutex, FairMutex, MutexBase};
+
+    let mutex = FairMutex::<TestMutex<Vec<char>>>::new(Vec::new());
+
+    block_on(join3(
+        async {
+            let mut log = mutex.lock().await.unwrap();
+            log.push('a');
+            for _ in 0..3 {
+                yield_now().await;
+            }
+        },
+        async {
+            // Polled before the first waiter once the lock is free, but queued after it.
+            yield_now().await;
+            mutex.lock().await.unwrap().push('c');
+        },
+        async {
+            mutex.lock().await.unwrap().push('b');
+        },
+    ));
+
+    assert_eq!(mutex.into_inner(), ['a', 'b', 'c']);
+}
+
+#[test]
+fn fair_mutex_skips_cancelled_waiters() {
+    use embassy_futures::poll_once;
+    use pca9548a::{AsyncMutex, FairMutex, MutexBase};
+
+    let mutex = FairMutex::<TestMutex<u8>>::new(0);
+
+    block_on(async {
+        let guard = mutex.lock().await.unwrap();
+        {
+            let mut waiter = core::pin::pin!(mutex.lock());
+            assert!(poll_once(waiter.as_mut()).is_pending());
+        }
+        drop(guard);
+        *mutex.lock().await.unwrap() += 1;
+    });
+
+    assert_eq!(mutex.into_inner(), 1);
+}
+
+#[test]
+fn fair_mutex_lock_timeout_gives_up_its_place() {
+    use pca9548a::{AsyncMutex, FairMutex, MutexBase};
+
+    let mutex = FairMutex::<TestMutex<u8>>::new(0);
+
+    block_on(async {
+        let guard = mutex.lock().await.unwrap();
+        assert!(mutex.lock_timeout(core::future::ready(())).await.is_none());
+        drop(guard);
+        *mutex.lock().await.unwrap() += 1;
+    });
+
+    assert_eq!(mutex.into_inner(), 1);
+}
+
+#[test]
+fn fair_mutex_serves_more_waiters_than_places() {
+    use embassy_futures::{join::join_array, yield_now};
+    use pca9548a::{AsyncMutex, FairMutex, MutexBase};
+
+    let mutex = FairMutex::<TestMutex<Vec<usize>>>::new(Vec::new());
+
+    block_on(join_array(core::array::from_fn::<_, 40, _>(|i| {
+        let mutex = &mutex;
+        async move {
+            let mut log = mutex.lock().await.unwrap();
+            log.push(i);
+            yield_now().await;
+        }
+    })));
+
+    assert_eq!(mutex.into_inner(), (0..40).collect::<Vec<_>>());
+}
+
+/// A waker that counts how often it was woken.
+struct CountingWaker(std::sync::atomic::AtomicUsize);
+
+impl CountingWaker {
+    fn new() -> std::sync::Arc<Self> {
+        std::sync::Arc::new(Self(std::sync::atomic::AtomicUsize::new(0)))
+    }
+
+    fn wakes(&self) -> usize {
+        self.0.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+impl std::task::Wake for CountingWaker {
+    fn wake(self: std::sync::Arc<Self>) {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn fair_mutex_wakes_only_the_next_waiter() {
+    use core::future::Future;
+    use core::task::{Context, Waker};
+    use pca9548a::{AsyncMutex, FairMutex, MutexBase};
+
+    let mutex = FairMutex::<TestMutex<u8>>::new(0);
+    let (first, second) = (CountingWaker::new(), CountingWaker::new());
+    let first_waker = Waker::from(first.clone());
+    let second_waker = Waker::from(second.clone());
+
+    let guard = block_on(mutex.lock()).unwrap();
+    let mut waiter = core::pin::pin!(mutex.lock());
+    let mut next = core::pin::pin!(mutex.lock());
+    assert!(waiter
+        .as_mut()
+        .poll(&mut Context::from_waker(&first_waker))
+        .is_pending());
+    assert!(next
+        .as_mut()
+        .poll(&mut Context::from_waker(&second_waker))
+        .is_pending());
+    // Waiting doesn't poll again on its own.
+    assert_eq!((first.wakes(), second.wakes()), (0, 0));
+
+    drop(guard);
+    assert_eq!((first.wakes(), second.wakes()), (1, 0));
+    match waiter.as_mut().poll(&mut Context::from_waker(&first_waker)) {
+        core::task::Poll::Ready(guard) => *guard.unwrap() += 1,
+        core::task::Poll::Pending => panic!("the first waiter should hold the lock"),
+    }
+    assert_eq!(second.wakes(), 1);
+    assert!(next
+        .as_mut()
+        .poll(&mut Context::from_waker(&second_waker))
+        .is_ready());
+}
+
+#[test]
+fn fair_mutex_wakes_the_waiter_after_a_cancelled_one() {
+    use core::future::Future;
+    use core::task::{Context, Waker};
+    use pca9548a::{AsyncMutex, FairMutex, MutexBase};
+
+    let mutex = FairMutex::<TestMutex<u8>>::new(0);
+    let (cancelled, next) = (CountingWaker::new(), CountingWaker::new());
+    let next_waker = Waker::from(next.clone());
+
+    let guard = block_on(mutex.lock()).unwrap();
+    let mut waiter = core::pin::pin!(mutex.lock());
+    {
+        let mut cancelled_waiter = core::pin::pin!(mutex.lock());
+        let cancelled_waker = Waker::from(cancelled.clone());
+        let mut cx = Context::from_waker(&cancelled_waker);
+        assert!(cancelled_waiter.as_mut().poll(&mut cx).is_pending());
+    }
+    assert!(waiter
+        .as_mut()
+        .poll(&mut Context::from_waker(&next_waker))
+        .is_pending());
+
+    drop(guard);
+    // The cancelled ticket was ahead in the queue, but only the remaining waiter is woken.
+    assert_eq!((cancelled.wakes(), next.wakes()), (0, 1));
+    assert!(waiter
+        .as_mut()
+        .poll(&mut Context::from_waker(&next_waker))
+        .is_ready());
+}
+
+#[test]
+fn lock_timeout_waits_for_the_lock_by_default() {
+    use pca9548a::{AsyncMutex, MutexBase};
+
+    let mutex = TestMutex::new(0u8);
+
+    block_on(async {
+        // The timeout has already expired, but `TestMutex` doesn't override `lock_timeout`.
+        *mutex
+            .lock_timeout(core::future::ready(()))
+            .await
+            .unwrap()
+            .unwrap() += 1;
+    });
+
+    assert_eq!(mutex.into_inner(), 1);
+}
+
+#[test]
+fn read_registers_async_selects_once() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write_read(0x42, vec![0x20], vec![0xAB]),
+        Transaction::write_read(0x42, vec![0x21], vec![0xCD]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut values = [0; 2];
+    block_on(pca.read_registers_async(Channel::C1, 0x42, &[0x20, 0x21], &mut values)).unwrap();
+    assert_eq!(values, [0xAB, 0xCD]);
+
+    bus.done();
+}
+
+#[test]
+fn read_registers_async_rejects_mismatched_lengths() {
+    let mut bus = Mock::new(&[]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(matches!(
+        block_on(pca.read_registers_async(pca9548a::Channel::C0, 0x42, &[0x00], &mut [0; 2])),
+        Err(pca9548a::Error::InvalidLength { regs: 1, out: 2 })
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn is_default_state_async_reads_the_control_register() {
+    let mut bus = Mock::new(&[Transaction::read(BASE_ADDRESS, vec![0])]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(block_on(pca.is_default_state_async()).unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn selected_channels_async_reads_mask() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![0b0100_0100]),
+        Transaction::read(BASE_ADDRESS, vec![0b0100_0100]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        assert_eq!(pca.selected_count_async().await.unwrap(), 2);
+        assert_eq!(pca.selected_channels_async().await.unwrap(), [2, 6]);
+    });
+
+    bus.done();
+}
+
+#[test]
+fn read_selection_then_async_reads_mask_and_device() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![0b11]),
+        Transaction::read(0x42, vec![7]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut buf = [0];
+    let mask = block_on(pca.read_selection_then_async(0x42, &mut buf)).unwrap();
+    assert_eq!((mask, buf), (0b11, [7]));
+
+    bus.done();
+}
+
+#[test]
+fn reset_and_select_async_uses_carried_delay() {
+    let log = common::Log::default();
+    let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![1])]);
+    let mut pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_reset_pin(common::LoggingPin(log.clone()))
+        .with_delay(common::LoggingDelay(log.clone()));
+
+    block_on(pca.reset_and_select_async(1)).unwrap();
+
+    assert_eq!(
+        *log.borrow(),
+        ["pin low", "delay 1000ns", "pin high", "delay 1000ns"]
+    );
+    bus.done();
+}
+
+#[test]
+fn close_reports_deselect_error() {
+    use embedded_hal::i2c::ErrorKind;
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 4]),
+        Transaction::write(BASE_ADDRESS, vec![0]).with_error(ErrorKind::Bus),
+        Transaction::write(BASE_ADDRESS, vec![1 << 4]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        let guard = pca.select_mask_cancel_safe_async(1 << 4).await.unwrap();
+        assert!(matches!(
+            guard.close().await,
+            Err(pca9548a::Error::Bus(ErrorKind::Bus))
+        ));
+        assert_eq!(pca.cached_mask(), None);
+
+        let mut session = pca.session_async().await.unwrap();
+        session.select_mask_async(1 << 4).await.unwrap();
+        session.close().await.unwrap();
+        assert_eq!(pca.cached_mask(), Some(0));
+    });
+
+    bus.done();
+}
+
+#[test]
+fn health_check_async_reads_control_register() {
+    let mut bus = Mock::new(&[Transaction::read(BASE_ADDRESS, vec![0b1001])]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let health = block_on(pca.health_check_async()).unwrap();
+    assert!(health.responds && health.matches_cache);
+    assert_eq!(health.control_register, 0b1001);
+
+    bus.done();
+}
+
+#[test]
+fn read_paged_async_handles_partial_page() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1]),
+        Transaction::write_read(0x50, vec![0x00, 0x00], vec![1, 2]),
+        Transaction::write_read(0x50, vec![0x00, 0x02], vec![3]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut buf = [0; 3];
+    block_on(pca.read_paged_async(pca9548a::Channel::C0, 0x50, 0, &mut buf, 2)).unwrap();
+    assert_eq!(buf, [1, 2, 3]);
+
+    bus.done();
+}
+
+#[test]
+fn probe_async_treats_nack_as_absent() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)),
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![]).with_error(ErrorKind::Overrun),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        assert!(!pca.probe_async(2, 0x42).await.unwrap());
+        assert!(matches!(
+            pca.probe_async(2, 0x42).await,
+            Err(pca9548a::Error::Bus(ErrorKind::Overrun))
+        ));
+    });
+
+    bus.done();
+}
+
+#[test]
+fn reinit_async_without_reset_pin() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::read(BASE_ADDRESS, vec![0]),
+    ]);
+    let mut pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(pca.reinit_async()).unwrap();
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn reinit_async_disables_sticky_channels() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::read(BASE_ADDRESS, vec![0]),
+    ]);
+    let mut pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    pca.set_sticky_channels(1 << 7);
+
+    block_on(pca.reinit_async()).unwrap();
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn explicit_async_deselects_disable_sticky_channels() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let mut bus = Mock::new(&[
+        // Session close
+        Transaction::write(BASE_ADDRESS, vec![0b1001_0000]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        // Parent subbus
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(0x42, vec![1]),
+        // Crosstalk check with 2 channels
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(0x48, vec![]).with_error(nack),
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write(0x48, vec![]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(0x48, vec![]).with_error(nack),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+    ]);
+    let mut pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    pca.set_sticky_channels(1 << 7);
+
+    block_on(async {
+        let mut session = pca.session_async().await.unwrap();
+        session.select_mask_async(1 << 4).await.unwrap();
+        session.close().await.unwrap();
+        assert_eq!(pca.cached_mask(), Some(0));
+
+        pca.parent_subbus().write(0x42, &[1]).await.unwrap();
+        assert_eq!(pca.cached_mask(), Some(0));
+    });
+
+    let mut pca = pca.with_channel_count(2);
+    pca.set_sticky_channels(1 << 1);
+    let report = block_on(pca.detect_crosstalk_async(0x48)).unwrap();
+    assert_eq!(report.responding, 1 << 0);
+    assert!(!report.responds_deselected);
+
+    bus.done();
+}
+
+#[test]
+fn detect_crosstalk_async_reports_a_clean_channel() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(0x48, vec![]).with_error(nack),
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write(0x48, vec![]).with_error(nack),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(0x48, vec![]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS).with_channel_count(2);
+
+    let report = block_on(pca.detect_crosstalk_async(0x48)).unwrap();
+    assert!(report.is_clean());
+    assert_eq!(report.unexpected(1), 0);
+
+    bus.done();
+}
+
+#[cfg(feature = "reassert")]
+#[test]
+fn reasserting_session_recovers_from_a_select_in_between() {
+    use embassy_futures::{join::join, yield_now};
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::read(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![1]),
+        // Another driver on the shared bus selects a different channel.
+        Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+        Transaction::write(0x42, vec![9]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::read(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![2]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let other = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(join(
+        async {
+            let mut session = pca
+                .reasserting_session_async(1 << 2)
+                .await
+                .unwrap()
+                .with_verify();
+            session.write(0x42, &[1]).await.unwrap();
+            // The task is suspended here, e.g. cancelled and resumed later.
+            yield_now().await;
+            session.write(0x42, &[2]).await.unwrap();
+        },
+        async {
+            other.single_subbus(5).write(0x42, &[9]).await.unwrap();
+        },
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn select_mask_prev_async_returns_the_register_before_the_select() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(BASE_ADDRESS, vec![0b11 << 4]),
+        Transaction::write(0x42, vec![3]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        let (previous, mut guard) = pca.select_mask_prev_async(0b11 << 4).await.unwrap();
+        assert_eq!(previous, 1 << 1);
+        guard.write(0x42, &[3]).await.unwrap();
+    });
+    assert_eq!(pca.cached_mask(), Some(0b11 << 4));
+
+    bus.done();
+}
+
+#[test]
+fn parent_bus_forwards_without_selecting_async() {
+    let mut bus = Mock::new(&[Transaction::write_read(0x20, vec![1], vec![2, 3])]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut read = [0; 2];
+    block_on(pca.parent_bus().write_read(0x20, &[1], &mut read)).unwrap();
+    assert_eq!(read, [2, 3]);
+
+    bus.done();
+}
+
+#[test]
+fn switch_to_async_writes_only_on_a_change() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 7]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        assert!(pca.switch_to_async(Channel::C7).await.unwrap());
+        assert!(!pca.switch_to_async(Channel::C7).await.unwrap());
+        assert!(pca.switch_to_async(Channel::C0).await.unwrap());
+    });
+
+    bus.done();
+}
+
+#[test]
+fn select_all_and_deselect_async() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0x0F]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS).with_channel_count(4);
+
+    block_on(async {
+        pca.select_all_async()
+            .await
+            .unwrap()
+            .write(0x42, &[1])
+            .await
+            .unwrap();
+        pca.deselect_async().await.unwrap();
+    });
+
+    bus.done();
+}
+
+#[test]
+fn read_interrupts_async_only_reads_the_control_register() {
+    use pca9548a::test_util::read_interrupts;
+
+    let mut bus = Mock::new(&[read_interrupts(BASE_ADDRESS, &[1], 0b0011)]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(block_on(pca.read_interrupts_async()).unwrap(), 1 << 1);
+
+    bus.done();
+}
+
+#[test]
+fn poll_interrupts_async_drops_flickering_flags() {
+    use pca9548a::test_util::read_interrupts;
+
+    let mut bus = Mock::new(&[
+        read_interrupts(BASE_ADDRESS, &[1, 3], 0),
+        read_interrupts(BASE_ADDRESS, &[1], 0),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS).with_interrupt_debounce(2);
+
+    assert_eq!(block_on(pca.poll_interrupts_async()).unwrap(), 1 << 1);
+
+    bus.done();
+}
+
+#[test]
+fn transaction_retry_async_gives_up_after_the_retries() {
+    use common::FlakyBus;
+    use embedded_hal::i2c::{ErrorKind, Operation};
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]).with_error(ErrorKind::Bus),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+    ]);
+    let pca = Pca9548a::<TestMutex<_>>::new(
+        FlakyBus::new(bus.clone(), &[ErrorKind::ArbitrationLoss]),
+        BASE_ADDRESS,
+    );
+
+    let result = block_on(pca.transaction_retry_async(
+        pca9548a::Channel::C1,
+        0x42,
+        &mut [Operation::Write(&[7])],
+        1,
+    ));
+    assert!(matches!(
+        result,
+        Err(pca9548a::Error::Bus(ErrorKind::ArbitrationLoss))
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn scan_for_muxes_async_aborts_on_a_bus_error() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+    use pca9548a::scan_for_muxes_async;
+
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let mut bus = Mock::new(&[
+        Transaction::write(0x70, vec![]),
+        Transaction::write(0x71, vec![]).with_error(nack),
+        Transaction::write(0x72, vec![]).with_error(ErrorKind::Bus),
+    ]);
+
+    assert_eq!(
+        block_on(scan_for_muxes_async(&mut bus)),
+        Err(ErrorKind::Bus)
+    );
+
+    bus.done();
+}
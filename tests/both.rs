@@ -0,0 +1,32 @@
+#![cfg(all(feature = "sync", feature = "async"))]
+
+mod common;
+
+use common::TestMutex;
+use embassy_futures::block_on;
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+use pca9548a::{Pca9548a, BASE_ADDRESS};
+
+#[test]
+fn subbus_is_usable_sync_and_async() {
+    let mut expectations = vec![
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(0x42, vec![1]),
+    ];
+    // Only the sync selects deselect on drop.
+    if cfg!(feature = "deselect-on-drop") {
+        expectations.push(Transaction::write(BASE_ADDRESS, vec![0]));
+    }
+    expectations.extend([
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(0x42, vec![2]),
+    ]);
+    let mut bus = Mock::new(&expectations);
+    let pca = Pca9548a::<TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut subbus = pca.single_subbus(1);
+
+    embedded_hal::i2c::I2c::write(&mut subbus, 0x42, &[1]).unwrap();
+    block_on(embedded_hal_async::i2c::I2c::write(&mut subbus, 0x42, &[2])).unwrap();
+
+    bus.done();
+}
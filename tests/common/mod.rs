@@ -0,0 +1,248 @@
+#![allow(dead_code)]
+
+use core::{cell::Cell, ops::DerefMut};
+
+use pca9548a::MutexBase;
+
+/// A `std::sync::Mutex` that is usable through the async mutex interface.
+///
+/// With the `sync` feature enabled it also implements `SyncMutex`, so the same
+/// instance can be driven from both worlds.
+///
+/// Every lock is counted, see [`lock_count`].
+pub struct TestMutex<T>(std::sync::Mutex<T>);
+
+thread_local! {
+    static LOCKS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// How often a `TestMutex` was locked on the current thread (i.e. in the current test).
+pub fn lock_count() -> usize {
+    LOCKS.get()
+}
+
+impl<T> TestMutex<T> {
+    fn lock_std(&self) -> Result<impl DerefMut<Target = T> + '_, ()> {
+        LOCKS.set(LOCKS.get() + 1);
+        self.0.lock().or(Err(()))
+    }
+}
+
+impl<T> MutexBase for TestMutex<T> {
+    type Bus = T;
+    type Error = ();
+
+    fn new(v: Self::Bus) -> Self {
+        Self(std::sync::Mutex::new(v))
+    }
+
+    fn into_inner(self) -> Self::Bus {
+        self.0.into_inner().unwrap()
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<T> pca9548a::SyncMutex for TestMutex<T> {
+    fn lock(&self) -> Result<impl DerefMut<Target = Self::Bus>, Self::Error> {
+        self.lock_std()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> pca9548a::AsyncMutex for TestMutex<T> {
+    async fn lock(&self) -> Result<impl DerefMut<Target = Self::Bus>, Self::Error> {
+        self.lock_std()
+    }
+}
+
+/// A shared, ordered log of events from several mocks.
+pub type Log = std::rc::Rc<std::cell::RefCell<Vec<String>>>;
+
+/// Wraps a blocking bus and logs every call before forwarding it.
+pub struct LoggingBus<B>(pub B, pub Log);
+
+impl<B: embedded_hal::i2c::ErrorType> embedded_hal::i2c::ErrorType for LoggingBus<B> {
+    type Error = B::Error;
+}
+
+impl<B: embedded_hal::i2c::I2c> embedded_hal::i2c::I2c for LoggingBus<B> {
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        self.1.borrow_mut().push(format!("read {address:#x}"));
+        self.0.read(address, read)
+    }
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.1
+            .borrow_mut()
+            .push(format!("write {address:#x} {bytes:?}"));
+        self.0.write(address, bytes)
+    }
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.1
+            .borrow_mut()
+            .push(format!("write_read {address:#x} {bytes:?}"));
+        self.0.write_read(address, bytes, read)
+    }
+
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.1
+            .borrow_mut()
+            .push(format!("transaction {address:#x}"));
+        self.0.transaction(address, operations)
+    }
+}
+
+/// Wraps a bus and fails its next transactions with the given errors, without forwarding them.
+///
+/// `embedded-hal-mock` can't fail a `transaction`, so this stands in for a glitch on the bus.
+pub struct FlakyBus<B> {
+    pub bus: B,
+    pub failures: std::collections::VecDeque<embedded_hal::i2c::ErrorKind>,
+}
+
+impl<B> FlakyBus<B> {
+    pub fn new(bus: B, failures: &[embedded_hal::i2c::ErrorKind]) -> Self {
+        Self {
+            bus,
+            failures: failures.iter().copied().collect(),
+        }
+    }
+}
+
+impl<B> embedded_hal::i2c::ErrorType for FlakyBus<B> {
+    type Error = embedded_hal::i2c::ErrorKind;
+}
+
+impl<B: embedded_hal::i2c::I2c<Error = embedded_hal::i2c::ErrorKind>> embedded_hal::i2c::I2c
+    for FlakyBus<B>
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        match self.failures.pop_front() {
+            Some(e) => Err(e),
+            None => self.bus.transaction(address, operations),
+        }
+    }
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.bus.write(address, bytes)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<B: embedded_hal_async::i2c::I2c<Error = embedded_hal::i2c::ErrorKind>>
+    embedded_hal_async::i2c::I2c for FlakyBus<B>
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        match self.failures.pop_front() {
+            Some(e) => Err(e),
+            None => self.bus.transaction(address, operations).await,
+        }
+    }
+
+    async fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.bus.write(address, bytes).await
+    }
+}
+
+/// An output pin that logs its level changes.
+pub struct LoggingPin(pub Log);
+
+impl embedded_hal::digital::ErrorType for LoggingPin {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_hal::digital::OutputPin for LoggingPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push("pin low".into());
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push("pin high".into());
+        Ok(())
+    }
+}
+
+/// A delay that returns immediately and logs the requested duration.
+#[derive(Clone)]
+pub struct LoggingDelay(pub Log);
+
+impl embedded_hal::delay::DelayNs for LoggingDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.borrow_mut().push(format!("delay {ns}ns"));
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for LoggingDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.borrow_mut().push(format!("delay {ns}ns"));
+    }
+}
+
+/// Run `f`, which must panic, and return the file in which the panic was reported.
+pub fn panic_location(f: impl FnOnce() + std::panic::UnwindSafe) -> String {
+    static LOCATION: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+    static HOOK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    let _hook = HOOK.lock().unwrap_or_else(|e| e.into_inner());
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|info| {
+        if let Some(location) = info.location() {
+            *LOCATION.lock().unwrap() = Some(location.file().to_string());
+        }
+    }));
+    let result = std::panic::catch_unwind(f);
+    std::panic::set_hook(previous);
+
+    assert!(result.is_err(), "expected a panic");
+    LOCATION.lock().unwrap().take().unwrap()
+}
+
+/// The write that deselects all channels when a sync lock is dropped with the `deselect-on-drop` feature;
+/// empty without it. Splice it into mock expectations with e.g. `[vec![..], deselect_on_drop(0x70)].concat()`.
+pub fn deselect_on_drop(address: u8) -> Vec<embedded_hal_mock::eh1::i2c::Transaction> {
+    deselect_on_drop_as(address, 0)
+}
+
+/// Like [`deselect_on_drop`], for a mux that encodes the empty mask as `control` (e.g. an inverted one).
+pub fn deselect_on_drop_as(
+    address: u8,
+    control: u8,
+) -> Vec<embedded_hal_mock::eh1::i2c::Transaction> {
+    if cfg!(feature = "deselect-on-drop") {
+        vec![embedded_hal_mock::eh1::i2c::Transaction::write(
+            address,
+            vec![control],
+        )]
+    } else {
+        vec![]
+    }
+}
+
+/// The cached mask after a sync lock selecting `mask` was dropped: `0` with the `deselect-on-drop` feature.
+pub fn after_drop(mask: u8) -> u8 {
+    if cfg!(feature = "deselect-on-drop") {
+        0
+    } else {
+        mask
+    }
+}
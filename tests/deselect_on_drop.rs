@@ -0,0 +1,105 @@
+#![cfg(all(feature = "deselect-on-drop", feature = "sync"))]
+
+use embedded_hal::i2c::I2c;
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+use pca9548a::{Pca9548a, BASE_ADDRESS};
+
+#[test]
+fn select_guard_deselects_when_dropped() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut guard = pca.select_single(3).unwrap();
+    guard.write(0x42, &[1]).unwrap();
+    assert_eq!(pca.cached_mask(), Some(1 << 3));
+    drop(guard);
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn subbus_deselects_after_every_operation() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0b11]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![0b11]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut subbus = pca.subbus(0b11);
+
+    subbus.write(0x42, &[1]).unwrap();
+    drop(subbus.select().unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn deselect_writes_only_once() {
+    let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![0])]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.deselect().unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn with_lock_deselects_before_releasing_the_lock() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.with_lock(|session| {
+        session.select_single(2).unwrap();
+        session.write(0x42, &[1]).unwrap();
+    })
+    .unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn drop_deselects_sticky_channels_too() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0b1000_0100]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![0b1000_0010]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    pca.set_sticky_channels(1 << 7);
+
+    drop(pca.select_single(2).unwrap());
+    pca.with_lock(|session| session.select_single(1).unwrap())
+        .unwrap();
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn select_mask_prev_deselects_when_dropped() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![1 << 4]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let (previous, guard) = pca.select_mask_prev(1 << 1).unwrap();
+    assert_eq!(previous, 1 << 4);
+    drop(guard);
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
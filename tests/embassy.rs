@@ -0,0 +1,264 @@
+#![cfg(feature = "embassy")]
+
+use embassy_futures::{block_on, join::join, yield_now};
+use embassy_sync::{
+    blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex},
+    mutex::Mutex,
+};
+use embedded_hal_async::i2c::I2c;
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+use pca9548a::{Pca9548a, BASE_ADDRESS};
+
+#[test]
+fn critical_section_mutex_selects() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::write(0x42, vec![1]),
+    ]);
+    let pca = Pca9548a::<Mutex<CriticalSectionRawMutex, _>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        pca.select_single_async(3)
+            .await
+            .unwrap()
+            .write(0x42, &[1])
+            .await
+            .unwrap();
+    });
+
+    bus.done();
+}
+
+#[test]
+fn noop_mutex_subbus() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1]),
+        Transaction::write(0x42, vec![1]),
+    ]);
+    let pca = Pca9548a::<Mutex<NoopRawMutex, _>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(pca.single_subbus(0).write(0x42, &[1])).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn from_mutex_uses_the_given_mutex() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+        Transaction::write(0x42, vec![2]),
+    ]);
+    let mutex = Mutex::<NoopRawMutex, _>::new(bus.clone());
+    let pca = Pca9548a::from_mutex(mutex, BASE_ADDRESS);
+
+    block_on(pca.single_subbus(5).write(0x42, &[2])).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn guard_is_held_across_await_points() {
+    // The first task holds the lock across a yield; the second one must wait until it is released,
+    // so its select cannot be interleaved with the first task's transactions.
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(0x42, vec![2]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(0x42, vec![3]),
+    ]);
+    let pca = Pca9548a::<Mutex<CriticalSectionRawMutex, _>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(join(
+        async {
+            let mut guard = pca.select_single_async(0).await.unwrap();
+            guard.write(0x42, &[1]).await.unwrap();
+            yield_now().await;
+            guard.write(0x42, &[2]).await.unwrap();
+        },
+        async {
+            pca.select_single_async(1)
+                .await
+                .unwrap()
+                .write(0x42, &[3])
+                .await
+                .unwrap();
+        },
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn map_bus_keeps_embassy_mutex() {
+    let mut bus = Mock::new(&[]);
+    let pca = Pca9548a::<Mutex<NoopRawMutex, _>>::new(bus.clone(), BASE_ADDRESS);
+    let _pca = pca.map_bus::<Mutex<NoopRawMutex, _>>(|bus| bus);
+
+    bus.done();
+}
+
+#[cfg(feature = "async-to-sync")]
+#[test]
+fn select_mask_blocking_from_sync_context() {
+    use pca9548a::Channel;
+
+    let mut expectations = vec![
+        Transaction::write(BASE_ADDRESS, vec![0b101]),
+        Transaction::write(0x42, vec![1]),
+    ];
+    if cfg!(feature = "deselect-on-drop") {
+        expectations.push(Transaction::write(BASE_ADDRESS, vec![0]));
+    }
+    let mut bus = Mock::new(&expectations);
+    let pca = Pca9548a::<Mutex<CriticalSectionRawMutex, _>>::new(bus.clone(), BASE_ADDRESS);
+
+    embedded_hal::i2c::I2c::write(
+        &mut *pca
+            .select_mask_blocking([Channel::C0, Channel::C2])
+            .unwrap(),
+        0x42,
+        &[1],
+    )
+    .unwrap();
+    let expected = if cfg!(feature = "deselect-on-drop") {
+        0
+    } else {
+        0b101
+    };
+    assert_eq!(pca.cached_mask(), Some(expected));
+
+    bus.done();
+}
+
+#[test]
+fn lock_map_projects_into_bus_field() {
+    use pca9548a::AsyncMutexMap;
+
+    let mut bus = Mock::new(&[Transaction::write(0x42, vec![1])]);
+    let mutex = Mutex::<NoopRawMutex, _>::new((bus.clone(), 0u32));
+
+    block_on(async {
+        let mut i2c = mutex.lock_map(|(i2c, _)| i2c).await.unwrap();
+        i2c.write(0x42, &[1]).await.unwrap();
+    });
+    block_on(async { *mutex.lock_map(|(_, count)| count).await.unwrap() += 1 });
+    assert_eq!(mutex.into_inner().1, 1);
+
+    bus.done();
+}
+
+#[test]
+fn static_subbus_can_be_moved_into_a_static_future() {
+    fn spawn<F: core::future::Future + 'static>(task: F) -> F {
+        task
+    }
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![1]),
+    ]);
+    let pca: &'static Pca9548a<Mutex<NoopRawMutex, Mock>> =
+        Box::leak(Box::new(Pca9548a::new(bus.clone(), BASE_ADDRESS)));
+
+    let mut subbus = pca.static_subbus(1 << 2);
+    block_on(spawn(async move { subbus.write(0x42, &[1]).await })).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn lock_timeout_expires_before_the_lock_is_acquired() {
+    use pca9548a::AsyncMutex;
+
+    let mutex = Mutex::<NoopRawMutex, u8>::new(0);
+
+    block_on(async {
+        let mut guard = AsyncMutex::lock(&mutex).await.unwrap();
+        assert!(mutex.lock_timeout(yield_now()).await.is_none());
+        *guard += 1;
+        drop(guard);
+        *mutex.lock_timeout(yield_now()).await.unwrap().unwrap() += 1;
+    });
+
+    assert_eq!(mutex.into_inner(), 2);
+}
+
+#[test]
+fn owned_and_static_subbuses_drive_async_drivers_in_tasks() {
+    /// An async driver that only knows about the `I2c` trait.
+    async fn read_id(i2c: &mut impl I2c) -> u8 {
+        let mut id = [0];
+        i2c.write_read(0x48, &[0x0F], &mut id).await.unwrap();
+        id[0]
+    }
+
+    fn spawn<F: core::future::Future + 'static>(task: F) -> F {
+        task
+    }
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::write_read(0x48, vec![0x0F], vec![0xA3]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+        Transaction::write_read(0x48, vec![0x0F], vec![0xA6]),
+    ]);
+    let pca: &'static Pca9548a<Mutex<NoopRawMutex, Mock>> =
+        Box::leak(Box::new(Pca9548a::new(bus.clone(), BASE_ADDRESS)));
+    let mut static_subbus = pca.static_subbus(1 << 3);
+    let [.., mut owned_subbus, _] =
+        Pca9548a::<Mutex<NoopRawMutex, Mock>>::new(bus.clone(), BASE_ADDRESS).split();
+
+    let static_task = spawn(async move { read_id(&mut static_subbus).await });
+    let owned_task = spawn(async move { read_id(&mut owned_subbus).await });
+    assert_eq!(block_on(static_task), 0xA3);
+    assert_eq!(block_on(owned_task), 0xA6);
+
+    bus.done();
+}
+
+#[test]
+fn select_mask_timeout_gives_up_while_the_lock_is_held() {
+    use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+    ]);
+    let mut delay = CheckedDelay::new(&[DelayTransaction::async_delay_us(500)]);
+    let pca = Pca9548a::<Mutex<NoopRawMutex, _>>::new(bus.clone(), BASE_ADDRESS)
+        .with_delay(delay.clone());
+
+    block_on(async {
+        let held = pca.select_mask_async(1 << 0).await.unwrap();
+        assert!(matches!(
+            pca.select_mask_timeout_async(1 << 1, 500).await,
+            Err(pca9548a::Error::Timeout)
+        ));
+        drop(held);
+        // The delay mock has no more expectations, so this must not time out.
+        drop(pca.select_mask_async(1 << 1).await.unwrap());
+    });
+
+    bus.done();
+    delay.done();
+}
+
+#[cfg(feature = "embassy-time")]
+#[test]
+fn select_mask_timeout_uses_embassy_time_without_a_delay() {
+    let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![1 << 0])]);
+    let pca = Pca9548a::<Mutex<NoopRawMutex, _>>::new(bus.clone(), BASE_ADDRESS);
+
+    block_on(async {
+        let _held = pca.select_mask_async(1 << 0).await.unwrap();
+        let start = embassy_time::Instant::now();
+        assert!(matches!(
+            pca.select_mask_timeout_async(1 << 1, 2_000).await,
+            Err(pca9548a::Error::Timeout)
+        ));
+        assert!(start.elapsed() >= embassy_time::Duration::from_micros(2_000));
+    });
+
+    bus.done();
+}
@@ -0,0 +1,73 @@
+#![cfg(all(feature = "test-internals", feature = "sync"))]
+
+use pca9548a::test_util::DryRun;
+use pca9548a::{Pca9548a, BASE_ADDRESS, CHANNELS};
+
+/// A xorshift generator, so the cases are reproducible without a property testing crate.
+struct Rng(u32);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    fn below(&mut self, n: u32) -> u8 {
+        (self.next() % n) as u8
+    }
+}
+
+#[test]
+fn read_mask_returns_the_effective_mask_after_any_select() {
+    let mut rng = Rng(0x9548);
+    for case in 0..500 {
+        let count = rng.below(CHANNELS as u32) + 1;
+        let bus = DryRun::new(BASE_ADDRESS);
+        let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+            .with_channel_count(count);
+        if rng.below(2) == 1 {
+            pca = pca.with_inverted_mask();
+        }
+        pca.set_sticky_channels(rng.below(256) & rng.below(256));
+
+        for _ in 0..4 {
+            let id = rng.below(u32::from(count));
+            drop(pca.select_single(id).unwrap());
+            // With deselect-on-drop, dropping the lock deselected all channels, including the sticky ones.
+            let effective = if cfg!(feature = "deselect-on-drop") {
+                0
+            } else {
+                pca.effective_mask(1 << id)
+            };
+            let state = pca.internal_state();
+
+            assert_eq!(state.cached_mask, Some(effective), "case {case}: {state:?}");
+            assert_eq!(
+                bus.selects().last(),
+                Some(&pca.encode_control(effective)),
+                "case {case}: {state:?}"
+            );
+            assert_eq!(
+                pca.read_mask().unwrap(),
+                effective,
+                "case {case}: {state:?}"
+            );
+            assert_eq!(effective & !state.valid_mask, 0, "case {case}: {state:?}");
+        }
+    }
+}
+
+#[test]
+fn decoding_inverts_encoding() {
+    let bus = DryRun::new(BASE_ADDRESS);
+    let plain = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let inverted = Pca9548a::<std::sync::Mutex<_>>::new(bus, BASE_ADDRESS).with_inverted_mask();
+
+    for mask in 0..=u8::MAX {
+        assert_eq!(plain.decode_control(plain.encode_control(mask)), mask);
+        assert_eq!(inverted.decode_control(inverted.encode_control(mask)), mask);
+        assert_eq!(inverted.encode_control(mask), !mask);
+    }
+}
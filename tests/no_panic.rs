@@ -0,0 +1,102 @@
+#![cfg(all(feature = "no-panic", feature = "sync"))]
+
+use embedded_hal::i2c::I2c;
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+use pca9548a::{
+    Channel, ChannelMask, DualMux, Error, InvalidChannel, MuxGroup, Pca9548a, BASE_ADDRESS,
+};
+
+#[test]
+fn invalid_channels_are_errors() {
+    let mut bus = Mock::new(&[]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(matches!(pca.try_single_subbus(8), Err(InvalidChannel(8))));
+    assert!(matches!(
+        pca.try_select_single(9).map(drop),
+        Err(Error::InvalidChannel(9))
+    ));
+    // All ids are checked before the first channel is written.
+    assert!(matches!(
+        pca.broadcast_write(0x42, &[1], &[1, 8]),
+        Err(Error::InvalidChannel(8))
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn invalid_ids_and_lengths_are_ignored() {
+    let mut expectations = vec![
+        Transaction::write(BASE_ADDRESS, vec![1]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(0x42, vec![2]),
+    ];
+    if cfg!(feature = "deselect-on-drop") {
+        expectations.push(Transaction::write(BASE_ADDRESS, vec![0]));
+    }
+    let mut bus = Mock::new(&expectations);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(!ChannelMask::from_bits(0xff).contains(8));
+
+    let mut subbus = pca.subbus(Channel::C0).with_max_chunk(0);
+    subbus.write(0x42, &[1, 2]).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn id_taking_methods_fail_instead_of_panicking() {
+    // `with_lock` deselects when it is done.
+    let expectations = if cfg!(feature = "deselect-on-drop") {
+        vec![Transaction::write(BASE_ADDRESS, vec![0])]
+    } else {
+        vec![]
+    };
+    let mut bus = Mock::new(&expectations);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_error_latch();
+
+    assert!(matches!(
+        pca.single_subbus(8).write(0x42, &[1]),
+        Err(Error::InvalidChannel(8))
+    ));
+    assert!(matches!(
+        pca.select_single(9).map(drop),
+        Err(Error::InvalidChannel(9))
+    ));
+    assert_eq!(
+        pca.last_error(),
+        Some(pca9548a::LastError::InvalidChannel(9))
+    );
+    pca.with_lock(|session| {
+        assert!(matches!(
+            session.select_single(10),
+            Err(Error::InvalidChannel(10))
+        ));
+    })
+    .unwrap();
+
+    let group = MuxGroup::<std::sync::Mutex<_>, 1>::new(bus.clone(), [BASE_ADDRESS]);
+    let mux = group.try_mux(0).unwrap();
+    assert!(matches!(
+        mux.single_subbus(8).write(0x42, &[1]),
+        Err(Error::InvalidChannel(8))
+    ));
+    assert!(matches!(
+        mux.select_single(8).map(drop),
+        Err(Error::InvalidChannel(8))
+    ));
+
+    let dual = DualMux::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS, BASE_ADDRESS + 1);
+    assert!(matches!(
+        dual.single_subbus(16).write(0x42, &[1]),
+        Err(Error::InvalidChannel(16))
+    ));
+    assert!(matches!(
+        dual.select_single(16).map(drop),
+        Err(Error::InvalidChannel(16))
+    ));
+
+    bus.done();
+}
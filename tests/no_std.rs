@@ -0,0 +1,161 @@
+//! The construction paths of a `no_std` firmware, using only `core` APIs of this crate.
+//!
+//! Without `std`, there is no `std::sync::Mutex`: pick an implementation of the mutex traits instead.
+//! * async: `embassy_sync::mutex::Mutex` with the embassy feature, with e.g. a `CriticalSectionRawMutex`
+//!   to share the mux between tasks and interrupts, or a `NoopRawMutex` within one executor.
+//! * sync: implement [`SyncMutex`] yourself, e.g. for a `RefCell` on a single core.
+//!
+//! Run with `cargo test --no-default-features --features embassy --test no_std`
+//! (the sync part with `--features sync,embassy`).
+#![no_std]
+#![cfg(any(feature = "sync", feature = "embassy"))]
+
+use embedded_hal::i2c::{ErrorKind, ErrorType, Operation};
+
+/// A bus that records up to 8 written bytes with their address.
+#[derive(Default)]
+struct Bus {
+    writes: [(u8, u8); 8],
+    len: usize,
+}
+
+impl Bus {
+    fn record(&mut self, address: u8, operations: &mut [Operation<'_>]) {
+        for operation in operations {
+            if let Operation::Write(bytes) = operation {
+                self.writes[self.len] = (address, bytes[0]);
+                self.len += 1;
+            }
+        }
+    }
+
+    fn writes(&self) -> &[(u8, u8)] {
+        &self.writes[..self.len]
+    }
+}
+
+impl ErrorType for Bus {
+    type Error = ErrorKind;
+}
+
+impl embedded_hal::i2c::I2c for Bus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.record(address, operations);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for Bus {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.record(address, operations);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embassy")]
+#[test]
+fn embassy_mutex_without_std() {
+    use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+    use embedded_hal_async::i2c::I2c;
+    use pca9548a::{Pca9548a, BASE_ADDRESS};
+
+    let mut pca = Pca9548a::<Mutex<CriticalSectionRawMutex, _>>::new(Bus::default(), BASE_ADDRESS);
+
+    embassy_futures::block_on(async {
+        pca.try_single_subbus(2)
+            .unwrap()
+            .write(0x42, &[7])
+            .await
+            .unwrap();
+        pca.select_mask_async(0b11)
+            .await
+            .unwrap()
+            .write(0x43, &[8])
+            .await
+            .unwrap();
+    });
+
+    let bus = pca.swap_bus(Bus::default());
+    assert_eq!(
+        bus.writes(),
+        [
+            (BASE_ADDRESS, 1 << 2),
+            (0x42, 7),
+            (BASE_ADDRESS, 0b11),
+            (0x43, 8)
+        ]
+    );
+}
+
+#[cfg(feature = "sync")]
+mod refcell {
+    use core::{cell::RefCell, ops::DerefMut};
+
+    use embedded_hal::i2c::I2c;
+    use pca9548a::{MutexBase, Pca9548a, SyncMutex, BASE_ADDRESS};
+
+    use super::Bus;
+
+    /// A mutex for a single core without interrupts using the mux: locking fails instead of blocking.
+    struct RefCellMutex<T>(RefCell<T>);
+
+    impl<T> MutexBase for RefCellMutex<T> {
+        type Bus = T;
+        type Error = core::cell::BorrowMutError;
+
+        fn new(v: Self::Bus) -> Self {
+            Self(RefCell::new(v))
+        }
+
+        fn into_inner(self) -> Self::Bus {
+            self.0.into_inner()
+        }
+    }
+
+    impl<T> SyncMutex for RefCellMutex<T> {
+        fn lock(&self) -> Result<impl DerefMut<Target = Self::Bus>, Self::Error> {
+            self.0.try_borrow_mut()
+        }
+    }
+
+    #[test]
+    fn refcell_mutex_without_std() {
+        let mut pca = Pca9548a::<RefCellMutex<_>>::new(Bus::default(), BASE_ADDRESS);
+
+        let mut guard = pca.try_select_single(5).unwrap();
+        guard.write(0x42, &[1]).unwrap();
+        // The bus is still locked by the guard.
+        assert!(pca.try_select_single(0).is_err());
+        drop(guard);
+        pca.try_single_subbus(0).unwrap().write(0x42, &[2]).unwrap();
+
+        let bus = pca.swap_bus(Bus::default());
+        let expected: &[(u8, u8)] = if cfg!(feature = "deselect-on-drop") {
+            &[
+                (BASE_ADDRESS, 1 << 5),
+                (0x42, 1),
+                (BASE_ADDRESS, 0),
+                (BASE_ADDRESS, 1),
+                (0x42, 2),
+                (BASE_ADDRESS, 0),
+            ]
+        } else {
+            &[
+                (BASE_ADDRESS, 1 << 5),
+                (0x42, 1),
+                (BASE_ADDRESS, 1),
+                (0x42, 2),
+            ]
+        };
+        assert_eq!(bus.writes(), expected);
+    }
+}
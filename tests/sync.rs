@@ -0,0 +1,2715 @@
+#![cfg(feature = "sync")]
+// `MuxGroup::mux` is deprecated with the no-panic feature, but still tested.
+#![cfg_attr(feature = "no-panic", allow(deprecated))]
+
+mod common;
+
+use embedded_hal::i2c::I2c;
+use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+use pca9548a::{MuxGroup, Pca9548a, BASE_ADDRESS};
+
+#[test]
+fn select_single_writes_mask() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+                Transaction::write(0x42, vec![1, 2]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.select_single(3).unwrap().write(0x42, &[1, 2]).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn subbus_selects_before_transaction() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS + 1, vec![0b0000_0110]),
+                Transaction::transaction_start(0x42),
+                Transaction::write(0x42, vec![7]),
+                Transaction::transaction_end(0x42),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS + 1),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS + 1);
+
+    let mut subbus = pca.subbus(0b0000_0110);
+    subbus
+        .transaction(0x42, &mut [embedded_hal::i2c::Operation::Write(&[7])])
+        .unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn enabled_subbuses_yields_enabled_channels() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::read(BASE_ADDRESS, vec![0b1000_0101]),
+                Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut subbuses: Vec<_> = pca.enabled_subbuses().unwrap().collect();
+    assert_eq!(
+        subbuses.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+        [0, 2, 7]
+    );
+    subbuses[1].1.write(0x42, &[1]).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn enabled_subbuses_empty_when_all_off() {
+    let mut bus = Mock::new(&[Transaction::read(BASE_ADDRESS, vec![0])]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(pca.enabled_subbuses().unwrap().count(), 0);
+
+    bus.done();
+}
+
+#[test]
+fn coordinator_brackets_control_write() {
+    struct Recorder(common::Log);
+
+    impl pca9548a::SelectionCoordinator for Recorder {
+        fn acquire(&self) {
+            self.0.borrow_mut().push("acquire".into());
+        }
+
+        fn release(&self) {
+            self.0.borrow_mut().push("release".into());
+        }
+    }
+
+    let log = common::Log::default();
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![2])
+                .with_error(embedded_hal::i2c::ErrorKind::Bus)],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(
+        common::LoggingBus(bus.clone(), log.clone()),
+        BASE_ADDRESS,
+    )
+    .with_coordinator(Recorder(log.clone()));
+
+    drop(pca.select_mask(1).unwrap());
+    assert!(pca.select_mask(2).is_err());
+
+    let mut expected = vec!["acquire", "write 0x70 [1]", "release"];
+    if cfg!(feature = "deselect-on-drop") {
+        expected.extend(["acquire", "write 0x70 [0]", "release"]);
+    }
+    expected.extend(["acquire", "write 0x70 [2]", "release"]);
+    assert_eq!(*log.borrow(), expected);
+    bus.done();
+}
+
+#[test]
+fn broadcast_write_writes_each_channel() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(0x42, vec![5]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![5]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.broadcast_write(0x42, &[5], &[1, 2]).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn cached_mask_reflects_last_select() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 4])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![0b11])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1])
+                    .with_error(embedded_hal::i2c::ErrorKind::Bus),
+                Transaction::read(BASE_ADDRESS, vec![0b101]),
+            ],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(pca.cached_mask(), None);
+    drop(pca.select_single(4).unwrap());
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(1 << 4)));
+    drop(pca.select_mask(0b11).unwrap());
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(0b11)));
+    assert!(pca.select_mask(1).is_err());
+    assert_eq!(pca.cached_mask(), None);
+    assert_eq!(pca.read_mask().unwrap(), 0b101);
+    assert_eq!(pca.cached_mask(), Some(0b101));
+
+    bus.done();
+}
+
+#[test]
+fn sticky_channels_are_added_to_every_select() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![0b1000_0100])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![0b1000_0011])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![0b1000_0000])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write_read(
+                BASE_ADDRESS,
+                vec![0b1000_0010],
+                vec![0b1000_0010],
+            )],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![1])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    pca.set_sticky_channels(1 << 7);
+
+    drop(pca.select_single(2).unwrap());
+    drop(pca.select_mask(0b11).unwrap());
+    drop(pca.select_mask(0).unwrap());
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(0b1000_0000)));
+    drop(pca.select_mask_verified_guard(1 << 1).unwrap());
+
+    pca.set_sticky_channels(0);
+    drop(pca.select_single(0).unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn interrupt_pending_reads_active_low_pin() {
+    use embedded_hal_mock::eh1::digital::{Mock as PinMock, State, Transaction as PinTransaction};
+
+    let mut pin = PinMock::new(&[
+        PinTransaction::get(State::High),
+        PinTransaction::get(State::Low),
+    ]);
+    let mut bus = Mock::new(&[]);
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_interrupt_pin(pin.clone());
+
+    assert!(!pca.interrupt_pending().unwrap());
+    assert!(pca.interrupt_pending().unwrap());
+
+    pin.done();
+    bus.done();
+}
+
+#[test]
+fn map_bus_wraps_the_bus() {
+    struct Counting<B>(B, usize);
+
+    impl<B: embedded_hal::i2c::ErrorType> embedded_hal::i2c::ErrorType for Counting<B> {
+        type Error = B::Error;
+    }
+
+    impl<B: I2c> I2c for Counting<B> {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [embedded_hal::i2c::Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            self.1 += 1;
+            self.0.transaction(address, operations)
+        }
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+            self.1 += 1;
+            self.0.write(address, bytes)
+        }
+    }
+
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![2])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    drop(pca.select_mask(1).unwrap());
+
+    let pca = pca.map_bus::<std::sync::Mutex<_>>(|bus| Counting(bus, 0));
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(1)));
+    drop(pca.select_mask(2).unwrap());
+    let writes = if cfg!(feature = "deselect-on-drop") {
+        2
+    } else {
+        1
+    };
+    assert_eq!(pca.bus().unwrap().1, writes);
+
+    bus.done();
+}
+
+#[test]
+fn transaction_with_mask_single_and_multi_channel() {
+    use embedded_hal::i2c::Operation;
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+                Transaction::transaction_start(0x42),
+                Transaction::write(0x42, vec![1]),
+                Transaction::read(0x42, vec![0xab]),
+                Transaction::transaction_end(0x42),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![0b0101_0101]),
+                Transaction::transaction_start(0x00),
+                Transaction::write(0x00, vec![0x06]),
+                Transaction::transaction_end(0x00),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut buf = [0];
+    pca.transaction_with_mask(
+        1 << 6,
+        0x42,
+        &mut [Operation::Write(&[1]), Operation::Read(&mut buf)],
+    )
+    .unwrap();
+    assert_eq!(buf, [0xab]);
+    pca.transaction_with_mask(0b0101_0101, 0x00, &mut [Operation::Write(&[0x06])])
+        .unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn session_switches_channels_under_one_lock() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::read(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::read(0x42, vec![2]),
+    ]);
+    let pca = Pca9548a::<common::TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut session = pca.session().unwrap();
+    let mut buf = [0];
+    session.select_single(0).unwrap();
+    session.read(0x42, &mut buf).unwrap();
+    assert_eq!(buf, [1]);
+    session.select_single(1).unwrap();
+    session.read(0x42, &mut buf).unwrap();
+    assert_eq!(buf, [2]);
+    drop(session);
+
+    assert_eq!(common::lock_count(), 1);
+    bus.done();
+}
+
+#[test]
+fn channels_const_indexes_by_channel() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut subbuses = pca.channels_const::<{ pca9548a::CHANNELS }>();
+    subbuses[3].write(0x42, &[1]).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn single_subbus_const_selects_the_channel() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 7]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.single_subbus_const::<7>().write(0x42, &[1]).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn channel_provider_muxed_and_direct() {
+    use pca9548a::{ChannelProvider, DirectBus, InvalidChannel};
+
+    fn poke(provider: &mut impl ChannelProvider, channel: u8) {
+        provider
+            .channel_bus(channel)
+            .unwrap()
+            .write(0x42, &[channel])
+            .unwrap();
+    }
+
+    let mut muxed = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+                Transaction::write(0x42, vec![5]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(muxed.clone(), BASE_ADDRESS);
+    poke(&mut pca, 5);
+    assert!(matches!(pca.channel_bus(8), Err(InvalidChannel(8))));
+    muxed.done();
+
+    let mut unused = Mock::new(&[]);
+    let mut four =
+        Pca9548a::<std::sync::Mutex<_>>::new(unused.clone(), BASE_ADDRESS).with_channel_count(4);
+    assert!(matches!(four.channel_bus(6), Err(InvalidChannel(6))));
+    unused.done();
+
+    let mut direct = DirectBus(Mock::new(&[Transaction::write(0x42, vec![2])]));
+    poke(&mut direct, 2);
+    direct.0.done();
+}
+
+#[test]
+fn reset_and_select_pulses_reset_before_select() {
+    let log = common::Log::default();
+    let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![1 << 2])]);
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(
+        common::LoggingBus(bus.clone(), log.clone()),
+        BASE_ADDRESS,
+    )
+    .with_reset_pin(common::LoggingPin(log.clone()))
+    .with_delay(common::LoggingDelay(log.clone()));
+
+    pca.reset_and_select(1 << 2).unwrap();
+
+    assert_eq!(
+        *log.borrow(),
+        [
+            "pin low",
+            "delay 1000ns",
+            "pin high",
+            "delay 1000ns",
+            "write 0x70 [4]"
+        ]
+    );
+    assert_eq!(pca.cached_mask(), Some(1 << 2));
+    bus.done();
+}
+
+#[test]
+fn test_util_encodes_interrupt_bits() {
+    use pca9548a::test_util::{read_interrupts, INTERRUPT_SHIFT};
+
+    let mut bus = Mock::new(&[read_interrupts(BASE_ADDRESS, &[1, 3], 0b0001)]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let register = pca.read_mask().unwrap();
+    assert_eq!(register >> INTERRUPT_SHIFT, 0b1010);
+    assert_eq!(register & 0x0F, 0b0001);
+
+    bus.done();
+}
+
+#[test]
+fn read_interrupts_only_reads_the_control_register() {
+    use pca9548a::test_util::read_interrupts;
+
+    // The mock fails on any write, so this also checks that the selection is left alone.
+    let mut bus = Mock::new(&[read_interrupts(BASE_ADDRESS, &[0, 2], 1 << 3)]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_channel_count(4);
+
+    assert_eq!(pca.read_interrupts().unwrap(), 0b0101);
+    assert_eq!(pca.cached_mask(), None);
+
+    bus.done();
+}
+
+#[test]
+fn max_simultaneous_channels_depends_on_the_variant() {
+    let mut bus = Mock::new(&[]);
+    let pca9548a = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    assert_eq!(
+        pca9548a.max_simultaneous_channels(),
+        pca9548a::CHANNELS as u8
+    );
+
+    let pca9546a =
+        Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_channel_count(4);
+    assert_eq!(pca9546a.max_simultaneous_channels(), 4);
+
+    let pca9547 =
+        Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_single_channel();
+    assert_eq!(pca9547.max_simultaneous_channels(), 1);
+
+    bus.done();
+}
+
+#[test]
+fn single_channel_layout_writes_the_channel_id() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![0b1000 | 5])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::read(BASE_ADDRESS, vec![0b1000 | 2]),
+                Transaction::write(BASE_ADDRESS, vec![0]),
+                Transaction::read(BASE_ADDRESS, vec![0]),
+            ],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_single_channel();
+
+    drop(pca.select_single(5).unwrap());
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(1 << 5)));
+    assert_eq!(pca.read_mask().unwrap(), 1 << 2);
+    pca.deselect().unwrap();
+    assert_eq!(pca.read_mask().unwrap(), 0);
+    // Several channels can't be encoded, so nothing is written.
+    assert!(matches!(
+        pca.select_mask(0b11).map(drop),
+        Err(pca9548a::Error::InvalidMask(0b11))
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn read_registers_selects_once() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+                Transaction::write_read(0x42, vec![0x00], vec![0x11]),
+                Transaction::write_read(0x42, vec![0x05], vec![0x22]),
+                Transaction::write_read(0x42, vec![0x0F], vec![0x33]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut values = [0; 3];
+    pca.read_registers(Channel::C6, 0x42, &[0x00, 0x05, 0x0F], &mut values)
+        .unwrap();
+    assert_eq!(values, [0x11, 0x22, 0x33]);
+
+    bus.done();
+}
+
+#[test]
+fn read_registers_rejects_mismatched_lengths() {
+    let mut bus = Mock::new(&[]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_error_latch();
+
+    assert!(matches!(
+        pca.read_registers(pca9548a::Channel::C0, 0x42, &[0x00, 0x01], &mut [0]),
+        Err(pca9548a::Error::InvalidLength { regs: 2, out: 1 })
+    ));
+    assert_eq!(pca.last_error(), Some(pca9548a::LastError::InvalidLength));
+
+    bus.done();
+}
+
+#[test]
+fn poll_interrupts_reports_flags_once_they_are_stable() {
+    use pca9548a::test_util::read_interrupts;
+
+    let mut bus = Mock::new(&[
+        // Channel 2 flickers, channel 0 is stable.
+        read_interrupts(BASE_ADDRESS, &[0, 2], 1),
+        read_interrupts(BASE_ADDRESS, &[0], 1),
+        read_interrupts(BASE_ADDRESS, &[0, 2], 1),
+        // Now channel 2 is stable too.
+        read_interrupts(BASE_ADDRESS, &[0, 2], 1),
+        read_interrupts(BASE_ADDRESS, &[0, 2], 1),
+        read_interrupts(BASE_ADDRESS, &[0, 2], 1),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_channel_count(4)
+        .with_interrupt_debounce(3);
+
+    assert_eq!(pca.poll_interrupts().unwrap(), 1 << 0);
+    assert_eq!(pca.poll_interrupts().unwrap(), (1 << 0) | (1 << 2));
+
+    bus.done();
+}
+
+#[test]
+fn poll_interrupts_reads_once_by_default() {
+    use pca9548a::test_util::read_interrupts;
+
+    let mut bus = Mock::new(&[read_interrupts(BASE_ADDRESS, &[3], 0)]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(pca.poll_interrupts().unwrap(), 1 << 3);
+
+    bus.done();
+}
+
+#[test]
+fn ack_interrupts_reads_the_device_and_returns_remaining_flags() {
+    use pca9548a::{test_util::read_interrupts, Channel};
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+                Transaction::write_read(0x42, vec![0x10], vec![0xAA]),
+                read_interrupts(BASE_ADDRESS, &[3], 1 << 2),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_channel_count(4);
+
+    let mut status = [0];
+    let remaining = pca
+        .ack_interrupts(Channel::C2, 0x42, &[0x10], &mut status)
+        .unwrap();
+    assert_eq!(status, [0xAA]);
+    assert_eq!(remaining, 1 << 3);
+
+    bus.done();
+}
+
+#[test]
+fn with_temporary_mask_restores_previous_selection() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        // The second time, the previous mask is known from the cache.
+        Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+        Transaction::write(0x42, vec![2]).with_error(embedded_hal::i2c::ErrorKind::Bus),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(
+        pca.with_temporary_mask(1 << 5, |bus| bus.write(0x42, &[1]))
+            .unwrap(),
+        Ok(())
+    );
+    assert!(pca
+        .with_temporary_mask(1 << 5, |bus| bus.write(0x42, &[2]))
+        .unwrap()
+        .is_err());
+    assert_eq!(pca.cached_mask(), Some(1 << 1));
+
+    bus.done();
+}
+
+#[test]
+fn with_temporary_mask_restores_on_panic() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 0])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 7]),
+                Transaction::write(BASE_ADDRESS, vec![common::after_drop(1 << 0)]),
+            ],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    drop(pca.select_single(0).unwrap());
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        pca.with_temporary_mask(1 << 7, |_| panic!("device driver bug"))
+    }));
+    assert!(result.is_err());
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(1 << 0)));
+
+    bus.done();
+}
+
+#[test]
+fn mux_group_deselects_other_muxes() {
+    let mut bus = Mock::new(&[
+        // First select on mux 0: the state of mux 1 is unknown, so it is deselected.
+        Transaction::write(BASE_ADDRESS + 1, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(0x42, vec![1]),
+        // Mux 1 is known to be deselected, so only mux 0 is written again.
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::write(0x42, vec![2]),
+        // Switching to mux 1 deselects mux 0 first.
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS + 1, vec![1 << 5]),
+        Transaction::write(0x42, vec![3]),
+    ]);
+    let group =
+        MuxGroup::<std::sync::Mutex<_>, 2>::new(bus.clone(), [BASE_ADDRESS, BASE_ADDRESS + 1]);
+    let (mux0, mux1) = (group.mux(0), group.mux(1));
+    assert_eq!(mux1.address(), BASE_ADDRESS + 1);
+
+    mux0.single_subbus(2).write(0x42, &[1]).unwrap();
+    mux0.select_single(3).unwrap().write(0x42, &[2]).unwrap();
+    mux1.single_subbus(5).write(0x42, &[3]).unwrap();
+
+    assert_eq!(mux0.cached_mask(), Some(0));
+    assert_eq!(mux1.cached_mask(), Some(1 << 5));
+    bus.done();
+}
+
+#[test]
+fn dual_mux_routes_channels_and_deselects_the_other_mux() {
+    use pca9548a::DualMux;
+
+    let mut bus = Mock::new(&[
+        // Channel 10 is channel 2 of the second mux.
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS + 1, vec![1 << 2]),
+        Transaction::write(0x42, vec![1]),
+        // Channel 3 is on the first mux.
+        Transaction::write(BASE_ADDRESS + 1, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::write(0x42, vec![2]),
+        // The second mux is known to be deselected already.
+        Transaction::write(BASE_ADDRESS, vec![1 << 7]),
+        Transaction::write(0x42, vec![3]),
+    ]);
+    let mux = DualMux::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS, BASE_ADDRESS + 1);
+
+    mux.single_subbus(10).write(0x42, &[1]).unwrap();
+    mux.select_single(3).unwrap().write(0x42, &[2]).unwrap();
+    mux.select_single(7).unwrap().write(0x42, &[3]).unwrap();
+
+    assert_eq!(mux.group().mux(0).cached_mask(), Some(1 << 7));
+    assert_eq!(mux.group().mux(1).cached_mask(), Some(0));
+    assert!(mux.try_single_subbus(15).is_some());
+    assert!(mux.try_single_subbus(16).is_none());
+    bus.done();
+}
+
+#[test]
+fn mux_group_failed_deselect_is_retried() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS + 1, vec![0])
+            .with_error(embedded_hal::i2c::ErrorKind::Other),
+        Transaction::write(BASE_ADDRESS + 1, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![1]),
+    ]);
+    let group =
+        MuxGroup::<std::sync::Mutex<_>, 2>::new(bus.clone(), [BASE_ADDRESS, BASE_ADDRESS + 1]);
+
+    assert!(group.mux(0).select_single(0).is_err());
+    assert_eq!(group.mux(1).cached_mask(), None);
+    drop(group.mux(0).select_single(0).unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn mux_group_members_keep_their_configuration() {
+    use pca9548a::{Error, LastError};
+
+    let mut bus = Mock::new(&[
+        // Mux 1 is inverted, so its deselect writes all ones.
+        Transaction::write(BASE_ADDRESS + 1, vec![0xFF]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::write_read(0x42, vec![1], vec![2]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS + 1, vec![!(1 << 6)]),
+        Transaction::read(0x42, vec![3]),
+    ]);
+    let group = MuxGroup::<std::sync::Mutex<_>, 2>::from_muxes(
+        bus.clone(),
+        [
+            Pca9548a::group_member(BASE_ADDRESS)
+                .with_channel_count(4)
+                .with_strict_mask()
+                .with_error_latch(),
+            Pca9548a::group_member(BASE_ADDRESS + 1).with_inverted_mask(),
+        ],
+    );
+    let (mux0, mux1) = (group.mux(0), group.mux(1));
+
+    assert!(matches!(
+        mux0.select_mask(1 << 5),
+        Err(Error::InvalidMask(0b0010_0000))
+    ));
+    assert_eq!(
+        mux0.member().last_error(),
+        Some(LastError::InvalidMask(1 << 5))
+    );
+
+    let mut read = [0];
+    mux0.single_subbus(3)
+        .write_read(0x42, &[1], &mut read)
+        .unwrap();
+    assert_eq!(read, [2]);
+    mux1.single_subbus(6).read(0x42, &mut read).unwrap();
+    assert_eq!(read, [3]);
+
+    assert_eq!(mux0.cached_mask(), Some(0));
+    assert_eq!(mux1.cached_mask(), Some(1 << 6));
+    bus.done();
+}
+
+#[test]
+fn subbus_empty_transaction_only_selects() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 2])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.single_subbus(2).transaction(0x42, &mut []).unwrap();
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(1 << 2)));
+
+    bus.done();
+}
+
+#[test]
+fn subbus_mixed_operations_select_once() {
+    use embedded_hal::i2c::Operation;
+
+    let mut bus = Mock::new(
+        &[
+            // A write_read expressed as one transaction: one select, then the device ops.
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 4]),
+                Transaction::transaction_start(0x42),
+                Transaction::write(0x42, vec![0x10]),
+                Transaction::read(0x42, vec![0xaa, 0xbb]),
+                Transaction::transaction_end(0x42),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            // The direct methods are forwarded as the same operation.
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 4]),
+                Transaction::write_read(0x42, vec![0x11], vec![0xcc]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 4]),
+                Transaction::read(0x42, vec![0xdd]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<common::TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut subbus = pca.single_subbus(4);
+
+    let mut buf = [0; 2];
+    subbus
+        .transaction(
+            0x42,
+            &mut [Operation::Write(&[0x10]), Operation::Read(&mut buf)],
+        )
+        .unwrap();
+    assert_eq!(buf, [0xaa, 0xbb]);
+    assert_eq!(common::lock_count(), 1);
+
+    let mut buf = [0];
+    subbus.write_read(0x42, &[0x11], &mut buf).unwrap();
+    assert_eq!(buf, [0xcc]);
+    subbus.read(0x42, &mut buf).unwrap();
+    assert_eq!(buf, [0xdd]);
+    assert_eq!(common::lock_count(), 3);
+
+    bus.done();
+}
+
+#[test]
+fn dry_run_records_selects_and_transactions() {
+    use pca9548a::test_util::{DryRun, Record, RecordedOperation};
+
+    let bus = DryRun::new(BASE_ADDRESS);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.broadcast_write(0x42, &[7], &[1, 3]).unwrap();
+    assert_eq!(pca.read_mask().unwrap(), 1 << 3);
+    let mut buf = [0xff; 2];
+    pca.single_subbus(0)
+        .write_read(0x42, &[0x10], &mut buf)
+        .unwrap();
+    assert_eq!(buf, [0, 0]);
+
+    let mut selects = vec![1 << 1, 1 << 3, 1 << 0];
+    if cfg!(feature = "deselect-on-drop") {
+        selects.push(0);
+    }
+    assert_eq!(bus.selects(), selects);
+    assert_eq!(
+        bus.records()[5],
+        Record::Transaction {
+            address: 0x42,
+            operations: vec![
+                RecordedOperation::Write(vec![0x10]),
+                RecordedOperation::Read(2)
+            ],
+        }
+    );
+    bus.clear();
+    assert!(bus.records().is_empty());
+}
+
+#[test]
+fn select_mask_accepts_all_mask_forms() {
+    use pca9548a::{Channel, ChannelMask};
+
+    let deselect = || common::deselect_on_drop(BASE_ADDRESS);
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![0b0000_0101])],
+            deselect(),
+            vec![Transaction::write(BASE_ADDRESS, vec![0b0000_0110])],
+            deselect(),
+            vec![Transaction::write(BASE_ADDRESS, vec![0b1000_0001])],
+            deselect(),
+            vec![Transaction::write(BASE_ADDRESS, vec![0b0011_0000])],
+            deselect(),
+            vec![Transaction::write(BASE_ADDRESS, vec![0b0000_1000])],
+            deselect(),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![0b0100_0000]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            deselect(),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    drop(pca.select_mask(0b0000_0101).unwrap());
+    drop(
+        pca.select_mask(ChannelMask::from_bits(0b0000_0110))
+            .unwrap(),
+    );
+    drop(pca.select_mask([Channel::C0, Channel::C7]).unwrap());
+    drop(pca.select_mask(&[Channel::C4, Channel::C5][..]).unwrap());
+    drop(
+        pca.select_mask((3..4).filter_map(Channel::new).collect::<ChannelMask>())
+            .unwrap(),
+    );
+    pca.subbus([Channel::C6]).write(0x42, &[1]).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn channel_mask_rejects_invalid_ids() {
+    use pca9548a::{ChannelMask, InvalidChannel};
+
+    assert_eq!(ChannelMask::try_from([8]), Err(InvalidChannel(8)));
+    // The first invalid id is reported.
+    assert_eq!(
+        ChannelMask::try_from(&[0, 9, 8][..]),
+        Err(InvalidChannel(9))
+    );
+    assert_eq!(
+        ChannelMask::try_from([0, 7]),
+        Ok(ChannelMask::from_bits(0b1000_0001))
+    );
+}
+
+#[test]
+fn split_verify_writes_and_reads_separately() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+                Transaction::read(BASE_ADDRESS, vec![1 << 2]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+                Transaction::read(BASE_ADDRESS, vec![0]),
+            ],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_split_verify();
+
+    pca.select_mask_verified_guard(1 << 2)
+        .unwrap()
+        .write(0x42, &[1])
+        .unwrap();
+    assert!(matches!(
+        pca.select_mask_verified_guard(1 << 3),
+        Err(pca9548a::Error::VerifyFailed {
+            expected: 0b0000_1000,
+            actual: 0
+        })
+    ));
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn select_mask_verified_guard_checks_read_back() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write_read(BASE_ADDRESS, vec![1 << 2], vec![1 << 2]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write_read(BASE_ADDRESS, vec![1 << 3], vec![0])],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.select_mask_verified_guard(1 << 2)
+        .unwrap()
+        .write(0x42, &[1])
+        .unwrap();
+    assert!(matches!(
+        pca.select_mask_verified_guard(1 << 3),
+        Err(pca9548a::Error::VerifyFailed {
+            expected: 0b0000_1000,
+            actual: 0
+        })
+    ));
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn subbus_chunks_long_transfers() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1]),
+                Transaction::read(0x50, vec![0, 1, 2, 3]),
+                Transaction::read(0x50, vec![4, 5, 6, 7]),
+                Transaction::read(0x50, vec![8, 9]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1]),
+                Transaction::write(0x50, vec![1, 2, 3, 4]),
+                Transaction::write(0x50, vec![5]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1]),
+                Transaction::write(0x50, vec![0, 1, 2, 3]),
+                Transaction::write_read(0x50, vec![4], vec![10, 11, 12, 13]),
+                Transaction::read(0x50, vec![14, 15]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            // A probe with an empty write is not dropped.
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1]),
+                Transaction::write(0x50, vec![]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<common::TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut subbus = pca.single_subbus(0).with_max_chunk(4);
+
+    let mut buf = [0; 10];
+    subbus.read(0x50, &mut buf).unwrap();
+    assert_eq!(buf, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    subbus.write(0x50, &[1, 2, 3, 4, 5]).unwrap();
+    let mut buf = [0; 6];
+    subbus.write_read(0x50, &[0, 1, 2, 3, 4], &mut buf).unwrap();
+    assert_eq!(buf, [10, 11, 12, 13, 14, 15]);
+    subbus.write(0x50, &[]).unwrap();
+
+    // One lock (and select) per call.
+    assert_eq!(common::lock_count(), 4);
+    bus.done();
+}
+
+#[test]
+fn raw_bus_does_not_select_and_forgets_cached_mask() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(0x20, vec![1]),
+                Transaction::write(BASE_ADDRESS, vec![0b11]),
+            ],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    drop(pca.select_single(0).unwrap());
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(1)));
+
+    let mut raw = pca.raw_bus().unwrap();
+    raw.write(0x20, &[1]).unwrap();
+    raw.write(BASE_ADDRESS, &[0b11]).unwrap();
+    drop(raw);
+    assert_eq!(pca.cached_mask(), None);
+
+    bus.done();
+}
+
+#[test]
+fn error_latch_keeps_most_recent_error() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+    use pca9548a::LastError;
+
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1]),
+                Transaction::write(0x42, vec![1]).with_error(nack),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 1])
+                    .with_error(ErrorKind::ArbitrationLoss),
+                Transaction::write(BASE_ADDRESS, vec![1]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_error_latch();
+    assert_eq!(pca.last_error(), None);
+
+    // The driver only sees the mapped error, the latch keeps the cause.
+    assert!(pca.single_subbus(0).write(0x42, &[1]).is_err());
+    assert_eq!(pca.last_error(), Some(LastError::Bus(nack)));
+    assert!(pca.select_single(1).is_err());
+    assert_eq!(
+        pca.last_error(),
+        Some(LastError::Bus(ErrorKind::ArbitrationLoss))
+    );
+
+    // Successful calls don't clear the latch.
+    pca.single_subbus(0).write(0x42, &[1]).unwrap();
+    assert!(pca.last_error().is_some());
+    pca.clear_last_error();
+    assert_eq!(pca.last_error(), None);
+
+    bus.done();
+}
+
+#[test]
+fn error_latch_is_disabled_by_default() {
+    let mut bus =
+        Mock::new(&[Transaction::write(BASE_ADDRESS, vec![1])
+            .with_error(embedded_hal::i2c::ErrorKind::Other)]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(pca.select_single(0).is_err());
+    assert_eq!(pca.last_error(), None);
+
+    bus.done();
+}
+
+#[test]
+fn try_variants_reject_invalid_channels() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 7]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_error_latch();
+
+    assert!(matches!(
+        pca.try_single_subbus(8),
+        Err(pca9548a::InvalidChannel(8))
+    ));
+    assert!(matches!(
+        pca.try_select_single(8).map(drop),
+        Err(pca9548a::Error::InvalidChannel(8))
+    ));
+    assert_eq!(
+        pca.last_error(),
+        Some(pca9548a::LastError::InvalidChannel(8))
+    );
+    pca.try_single_subbus(7).unwrap().write(0x42, &[1]).unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn round_robin_advances_on_every_transaction() {
+    use pca9548a::Channel;
+
+    let mut expectations = vec![];
+    for (mask, reading) in [(1 << 1, 10), (1 << 4, 40), (1 << 1, 11)] {
+        expectations.extend([
+            Transaction::write(BASE_ADDRESS, vec![mask]),
+            Transaction::transaction_start(0x48),
+            Transaction::write(0x48, vec![0]),
+            Transaction::read(0x48, vec![reading]),
+            Transaction::transaction_end(0x48),
+        ]);
+        expectations.extend(common::deselect_on_drop(BASE_ADDRESS));
+    }
+    let mut bus = Mock::new(&expectations);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut sensors = pca.round_robin([Channel::C4, Channel::C1]);
+
+    let mut readings = vec![];
+    for _ in 0..3 {
+        let mut buf = [0];
+        sensors.write_read(0x48, &[0], &mut buf).unwrap();
+        readings.push(buf[0]);
+    }
+    assert_eq!(readings, [10, 40, 11]);
+    assert_eq!(sensors.next_channel(), Some(4));
+
+    bus.done();
+}
+
+#[test]
+fn round_robin_without_channels_deselects() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![0]),
+                Transaction::transaction_start(0x48),
+                Transaction::write(0x48, vec![1]),
+                Transaction::transaction_end(0x48),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut sensors = pca.round_robin(0);
+
+    assert_eq!(sensors.next_channel(), None);
+    sensors.write(0x48, &[1]).unwrap();
+
+    bus.done();
+}
+
+#[cfg(not(feature = "no-panic"))]
+#[test]
+fn invalid_channel_panics_at_call_site() {
+    let bus = Mock::new(&[]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(
+        common::panic_location(|| {
+            let _ = pca.single_subbus(8);
+        }),
+        file!()
+    );
+    assert_eq!(
+        common::panic_location(|| drop(pca.select_single(8))),
+        file!()
+    );
+    // Checked before the first channel is written.
+    assert_eq!(
+        common::panic_location(|| drop(pca.broadcast_write(0x42, &[1], &[1, 8]))),
+        file!()
+    );
+
+    bus.clone().done();
+}
+
+#[test]
+fn channel_newtype_selects_without_checks() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+                Transaction::write(0x42, vec![2]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![0b1000_0001])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.select_channel(Channel::C6)
+        .unwrap()
+        .write(0x42, &[1])
+        .unwrap();
+    let channel = Channel::new(2).unwrap();
+    pca.channel_subbus(channel).write(0x42, &[2]).unwrap();
+    drop(pca.select_mask([Channel::C0, Channel::C7]).unwrap());
+
+    assert_eq!(Channel::new(8), None);
+    assert_eq!(u8::from(Channel::C4), 4);
+    bus.done();
+}
+
+#[test]
+fn select_channels_iter_selects_all_channels() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![0b0000_1001]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![0])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.select_channels_iter([Channel::C0, Channel::C3])
+        .unwrap()
+        .write(0x42, &[1])
+        .unwrap();
+    drop(pca.select_channels_iter([]).unwrap());
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn selected_count_and_channels_of_none() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![0]),
+        Transaction::read(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(pca.selected_count().unwrap(), 0);
+    assert!(pca.selected_channels().unwrap().is_empty());
+
+    bus.done();
+}
+
+#[test]
+fn selected_count_and_channels_of_one() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![1 << 4]),
+        Transaction::read(BASE_ADDRESS, vec![1 << 4]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(pca.selected_count().unwrap(), 1);
+    assert_eq!(pca.selected_channels().unwrap(), [4]);
+
+    bus.done();
+}
+
+#[test]
+fn selected_count_and_channels_of_many() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![0b1010_0011]),
+        Transaction::read(BASE_ADDRESS, vec![0b1010_0011]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(pca.selected_count().unwrap(), 4);
+    assert_eq!(pca.selected_channels().unwrap(), [0, 1, 5, 7]);
+
+    bus.done();
+}
+
+#[test]
+fn is_default_state_detects_a_reset_mux() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::read(BASE_ADDRESS, vec![0]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(!pca.is_default_state().unwrap());
+    assert!(pca.is_default_state().unwrap());
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn observer_records_successful_selects() {
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<u8>>);
+
+    impl pca9548a::SelectObserver for &Recorder {
+        fn on_select(&self, mask: u8) {
+            self.0.lock().unwrap().push(mask);
+        }
+    }
+
+    let recorder = Recorder::default();
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 2])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1])
+                    .with_error(embedded_hal::i2c::ErrorKind::Bus),
+                Transaction::write(BASE_ADDRESS, vec![0b11]),
+                Transaction::write(0x42, vec![7]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca =
+        Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_observer(&recorder);
+
+    drop(pca.select_single(2).unwrap());
+    assert!(pca.select_mask(1).is_err());
+    pca.subbus(0b11).write(0x42, &[7]).unwrap();
+
+    // The deselects on drop are selects of the empty mask.
+    let expected: &[u8] = if cfg!(feature = "deselect-on-drop") {
+        &[1 << 2, 0, 0b11, 0]
+    } else {
+        &[1 << 2, 0b11]
+    };
+    assert_eq!(*recorder.0.lock().unwrap(), expected);
+    bus.done();
+}
+
+#[test]
+fn select_history_keeps_the_last_selects() {
+    use pca9548a::{SelectHistory, SelectRecord};
+
+    let deselect = || common::deselect_on_drop(BASE_ADDRESS);
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1])],
+            deselect(),
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 1])
+                .with_error(embedded_hal::i2c::ErrorKind::Bus)],
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 2])],
+            deselect(),
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 3])],
+            deselect(),
+            vec![Transaction::write(BASE_ADDRESS, vec![0])],
+            deselect(),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_observer(SelectHistory::<3>::new());
+
+    assert!(pca.select_history().is_empty());
+    drop(pca.select_single(0).unwrap());
+    assert!(pca.select_single(1).is_err());
+    drop(pca.select_single(2).unwrap());
+    drop(pca.select_single(3).unwrap());
+    drop(pca.select_mask(0).unwrap());
+
+    // The deselects on drop are recorded as selects of the empty mask.
+    let expected = if cfg!(feature = "deselect-on-drop") {
+        [(5, 0), (6, 0), (7, 0)]
+    } else {
+        [(1, 1 << 2), (2, 1 << 3), (3, 0)]
+    };
+    assert_eq!(
+        pca.select_history(),
+        expected.map(|(sequence, mask)| SelectRecord { sequence, mask })
+    );
+    bus.done();
+}
+
+#[test]
+fn subbus_transaction_fills_read_buffer_once() {
+    use embedded_hal::i2c::Operation;
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 1])
+                    .with_error(embedded_hal::i2c::ErrorKind::Bus),
+                Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+                Transaction::transaction_start(0x42),
+                Transaction::write(0x42, vec![0x10]),
+                Transaction::read(0x42, vec![1, 2, 3]),
+                Transaction::transaction_end(0x42),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let mut subbus = pca.subbus(1 << 1);
+
+    let mut buf = [0xff; 3];
+    assert!(subbus
+        .transaction(
+            0x42,
+            &mut [Operation::Write(&[0x10]), Operation::Read(&mut buf)]
+        )
+        .is_err());
+    assert_eq!(buf, [0xff; 3]);
+
+    subbus
+        .transaction(
+            0x42,
+            &mut [Operation::Write(&[0x10]), Operation::Read(&mut buf)],
+        )
+        .unwrap();
+    assert_eq!(buf, [1, 2, 3]);
+
+    bus.done();
+}
+
+#[test]
+fn read_selection_then_reads_mask_and_device() {
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![1 << 6]),
+        Transaction::read(0x42, vec![0xab, 0xcd]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut buf = [0; 2];
+    assert_eq!(pca.read_selection_then(0x42, &mut buf).unwrap(), 1 << 6);
+    assert_eq!(buf, [0xab, 0xcd]);
+    assert_eq!(pca.cached_mask(), Some(1 << 6));
+
+    bus.done();
+}
+
+#[test]
+fn split_handles_share_the_bus() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+                Transaction::read(0x42, vec![2]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let [_, mut first, _, _, _, mut second, _, _] = pca.split();
+    assert_eq!((first.channel(), second.channel()), (1, 5));
+
+    std::thread::spawn(move || first.write(0x42, &[1]).unwrap())
+        .join()
+        .unwrap();
+    let mut buf = [0];
+    second.read(0x42, &mut buf).unwrap();
+    assert_eq!(buf, [2]);
+    assert_eq!(second.pca().cached_mask(), Some(common::after_drop(1 << 5)));
+
+    bus.done();
+}
+
+#[test]
+fn shared_handle_serializes_selects_across_threads() {
+    use embedded_hal::i2c::{ErrorKind, ErrorType, Operation};
+    use pca9548a::Channel;
+
+    /// Records the written bytes of every operation along with its address.
+    #[derive(Default)]
+    struct RecordingBus(Vec<(u8, u8)>);
+
+    impl ErrorType for RecordingBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for RecordingBus {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            for operation in operations {
+                if let Operation::Write(bytes) = operation {
+                    self.0.push((address, bytes[0]));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let shared =
+        Pca9548a::<std::sync::Mutex<_>>::new(RecordingBus::default(), BASE_ADDRESS).into_shared();
+
+    let threads = [1, 6].map(|id| {
+        let shared = shared.clone();
+        std::thread::spawn(move || {
+            let mut subbus = shared.owned_subbus(Channel::new(id).unwrap());
+            for _ in 0..50 {
+                shared
+                    .select_single(id)
+                    .unwrap()
+                    .write(0x42, &[id])
+                    .unwrap();
+                subbus.write(0x42, &[id]).unwrap();
+            }
+        })
+    });
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let pca = shared.into_inner().expect("all other handles are dropped");
+    let writes = std::mem::take(&mut pca.bus().unwrap().0);
+    // With deselect-on-drop, every lock ends with a deselect.
+    let per_lock = if cfg!(feature = "deselect-on-drop") {
+        3
+    } else {
+        2
+    };
+    assert_eq!(writes.len(), 2 * 2 * 50 * per_lock);
+    // Every device write directly follows the select of its own thread's channel.
+    for lock in writes.chunks(per_lock) {
+        let [(BASE_ADDRESS, mask), (0x42, id), deselect @ ..] = lock else {
+            panic!("unexpected writes {lock:?}");
+        };
+        assert_eq!(*mask, 1 << id);
+        assert!(deselect.iter().all(|&write| write == (BASE_ADDRESS, 0)));
+    }
+}
+
+#[test]
+fn tag_err_attributes_errors_to_the_mux() {
+    use embedded_hal::i2c::{Error as _, ErrorKind};
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 4]).with_error(ErrorKind::Other),
+                Transaction::write(BASE_ADDRESS + 1, vec![1 << 4]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS + 1),
+        ]
+        .concat(),
+    );
+    let left = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_tag("left");
+    let right =
+        Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS + 1).with_tag("right");
+
+    let error = left.tag_err(left.select_single(4)).err().unwrap();
+    assert_eq!(error.tag, "left");
+    assert!(matches!(
+        error.error,
+        pca9548a::Error::Bus(ErrorKind::Other)
+    ));
+    assert_eq!(error.kind(), ErrorKind::Other);
+    assert_eq!(error.to_string(), "left: bus error: Other");
+    assert!(right.tag_err(right.select_single(4)).is_ok());
+    assert_eq!(right.tag(), "right");
+
+    bus.done();
+}
+
+#[test]
+fn strict_mask_rejects_channels_beyond_count() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![0x08])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_channel_count(4)
+        .with_strict_mask()
+        .with_error_latch();
+
+    assert!(matches!(
+        pca.select_mask(0x10).map(drop),
+        Err(pca9548a::Error::InvalidMask(0x10))
+    ));
+    assert_eq!(
+        pca.last_error(),
+        Some(pca9548a::LastError::InvalidMask(0x10))
+    );
+    drop(pca.select_mask(0x08).unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn non_strict_mask_clears_channels_beyond_count() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![0x01])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_channel_count(4);
+
+    drop(pca.select_mask(0x11).unwrap());
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(0x01)));
+
+    bus.done();
+}
+
+#[test]
+fn health_check_of_healthy_mux() {
+    let selected = common::after_drop(1 << 3);
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 3])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::read(BASE_ADDRESS, vec![selected]),
+                Transaction::read(BASE_ADDRESS, vec![1 << 5]),
+            ],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    drop(pca.select_single(3).unwrap());
+    let health = pca.health_check().unwrap();
+    assert!(health.responds && health.matches_cache);
+    assert_eq!(health.control_register, selected);
+
+    // Changed behind our back, e.g. by another bus master; the cache is left alone.
+    assert!(!pca.health_check().unwrap().matches_cache);
+    assert_eq!(pca.cached_mask(), Some(selected));
+
+    bus.done();
+}
+
+#[test]
+fn health_check_of_non_responding_mux() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    let mut bus = Mock::new(&[
+        Transaction::read(BASE_ADDRESS, vec![0])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        Transaction::read(BASE_ADDRESS, vec![0]).with_error(ErrorKind::ArbitrationLoss),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let health = pca.health_check().unwrap();
+    assert!(!health.responds && !health.matches_cache);
+    assert!(matches!(
+        pca.health_check(),
+        Err(pca9548a::Error::Bus(ErrorKind::ArbitrationLoss))
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn parent_subbus_deselects_before_transaction() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![0]),
+                Transaction::write_read(0x42, vec![0x10], vec![7]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.single_subbus(2).write(0x42, &[1]).unwrap();
+    let mut buf = [0];
+    pca.parent_subbus()
+        .write_read(0x42, &[0x10], &mut buf)
+        .unwrap();
+    assert_eq!(buf, [7]);
+
+    bus.done();
+}
+
+#[test]
+fn parent_subbus_disables_sticky_channels() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![0b1000_0100])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![0]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![0]),
+                Transaction::transaction_start(0x42),
+                Transaction::write(0x42, vec![2]),
+                Transaction::transaction_end(0x42),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    pca.set_sticky_channels(1 << 7);
+
+    drop(pca.select_single(2).unwrap());
+    let mut parent = pca.parent_subbus();
+    parent.write(0x42, &[1]).unwrap();
+    assert_eq!(pca.cached_mask(), Some(0));
+    parent
+        .transaction(0x42, &mut [embedded_hal::i2c::Operation::Write(&[2])])
+        .unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn typestate_session_selects_and_closes() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+        Transaction::write(0x42, vec![1]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+        Transaction::read(0x42, vec![2]),
+        // Dropped instead of closed.
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut selected = pca.open().select::<3>().unwrap();
+    selected.write(0x42, &[1]).unwrap();
+    let idle = selected.close().unwrap();
+    assert_eq!(idle.cached_mask(), Some(0));
+
+    let mut selected = idle.select::<6>().unwrap();
+    let mut buf = [0];
+    selected.read(0x42, &mut buf).unwrap();
+    drop(selected);
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn typestate_close_disables_sticky_channels() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0b1000_1000]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![0b1100_0000]),
+        // Dropped instead of closed.
+        Transaction::write(BASE_ADDRESS, vec![0]),
+    ]);
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    pca.set_sticky_channels(1 << 7);
+
+    let idle = pca.open().select::<3>().unwrap().close().unwrap();
+    assert_eq!(idle.cached_mask(), Some(0));
+    drop(idle.select::<6>().unwrap());
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn read_paged_reads_pages_with_one_select() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+                Transaction::write_read(0x50, vec![0x01, 0xfc], vec![1, 2, 3, 4]),
+                Transaction::write_read(0x50, vec![0x02, 0x00], vec![5, 6, 7, 8]),
+                Transaction::write_read(0x50, vec![0x02, 0x04], vec![9, 10]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut buf = [0; 10];
+    pca.read_paged(pca9548a::Channel::C1, 0x50, 0x01fc, &mut buf, 4)
+        .unwrap();
+    assert_eq!(buf, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+
+    bus.done();
+}
+
+#[test]
+fn probe_treats_every_nack_as_absent() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    let mut expectations = vec![];
+    for probe in [
+        Transaction::write(0x42, vec![]),
+        Transaction::write(0x43, vec![])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        Transaction::write(0x44, vec![])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)),
+        Transaction::write(0x45, vec![])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)),
+        Transaction::write(0x46, vec![]).with_error(ErrorKind::ArbitrationLoss),
+    ] {
+        expectations.extend([Transaction::write(BASE_ADDRESS, vec![1 << 1]), probe]);
+        expectations.extend(common::deselect_on_drop(BASE_ADDRESS));
+    }
+    let mut bus = Mock::new(&expectations);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(pca.probe(1, 0x42).unwrap());
+    assert!(!pca.probe(1, 0x43).unwrap());
+    assert!(!pca.probe(1, 0x44).unwrap());
+    assert!(!pca.probe(1, 0x45).unwrap());
+    assert!(matches!(
+        pca.probe(1, 0x46),
+        Err(pca9548a::Error::Bus(ErrorKind::ArbitrationLoss))
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn scan_channel_skips_absent_devices_and_the_mux() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    let mut expectations = vec![Transaction::write(BASE_ADDRESS, vec![1 << 4])];
+    for addr in (0x08..=0x77).filter(|&a| a != BASE_ADDRESS) {
+        let probe = Transaction::write(addr, vec![]);
+        expectations.push(match addr {
+            0x20 | 0x68 => probe,
+            0x50 => probe.with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)),
+            _ => probe.with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        });
+    }
+    expectations.extend(common::deselect_on_drop(BASE_ADDRESS));
+    let mut bus = Mock::new(&expectations);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert_eq!(pca.scan_channel(4).unwrap(), [0x20, 0x68]);
+
+    bus.done();
+}
+
+#[test]
+fn scan_channel_stops_at_bus_errors() {
+    use embedded_hal::i2c::ErrorKind;
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 4]),
+                Transaction::write(0x08, vec![]).with_error(ErrorKind::Bus),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(matches!(
+        pca.scan_channel(4),
+        Err(pca9548a::Error::Bus(ErrorKind::Bus))
+    ));
+
+    bus.done();
+}
+
+/// A write recorded by [`AddressModeBus`]: the address mode, the address and the data.
+type RecordedWrite = (&'static str, u16, Vec<u8>);
+
+/// A bus that records the address mode of every write and acknowledges everything.
+#[derive(Clone, Default)]
+struct AddressModeBus {
+    writes: std::sync::Arc<std::sync::Mutex<Vec<RecordedWrite>>>,
+}
+
+impl embedded_hal::i2c::ErrorType for AddressModeBus {
+    type Error = embedded_hal::i2c::ErrorKind;
+}
+
+impl I2c<embedded_hal::i2c::SevenBitAddress> for AddressModeBus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        for op in operations {
+            if let embedded_hal::i2c::Operation::Write(bytes) = op {
+                let write = ("7-bit", address.into(), bytes.to_vec());
+                self.writes.lock().unwrap().push(write);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl I2c<embedded_hal::i2c::TenBitAddress> for AddressModeBus {
+    fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        for op in operations {
+            match op {
+                embedded_hal::i2c::Operation::Write(bytes) => {
+                    let write = ("10-bit", address, bytes.to_vec());
+                    self.writes.lock().unwrap().push(write);
+                }
+                embedded_hal::i2c::Operation::Read(buf) => buf.fill(0b101),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn ten_bit_control_address() {
+    let bus = AddressModeBus::default();
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new_ten_bit(bus.clone(), 0x270);
+    assert_eq!(pca.to_string(), "PCA9548A@0x270");
+
+    pca.subbus(0b101).write(0x42, &[1]).unwrap();
+    assert_eq!(pca.read_mask().unwrap(), 0b101);
+
+    let mut writes = vec![("10-bit", 0x270, vec![0b101]), ("7-bit", 0x42, vec![1])];
+    if cfg!(feature = "deselect-on-drop") {
+        writes.push(("10-bit", 0x270, vec![0]));
+    }
+    assert_eq!(*bus.writes.lock().unwrap(), writes);
+}
+
+#[test]
+fn errors_convert_into_an_application_error() {
+    use embedded_hal::i2c::ErrorKind;
+
+    #[derive(Debug)]
+    struct AppError(String);
+
+    impl<M: core::fmt::Debug, B: core::fmt::Debug> From<pca9548a::Error<M, B>> for AppError {
+        fn from(e: pca9548a::Error<M, B>) -> Self {
+            AppError(e.to_string())
+        }
+    }
+
+    let mut bus =
+        Mock::new(&[Transaction::write(BASE_ADDRESS, vec![1 << 5]).with_error(ErrorKind::Bus)]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let select = || -> Result<(), AppError> {
+        pca.select_mask(1 << 5)?;
+        Ok(())
+    };
+    assert_eq!(select().unwrap_err().0, "bus error: Bus");
+
+    bus.done();
+}
+
+#[test]
+fn reinit_resets_deselects_and_confirms() {
+    let log = common::Log::default();
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::read(BASE_ADDRESS, vec![0]),
+    ]);
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(
+        common::LoggingBus(bus.clone(), log.clone()),
+        BASE_ADDRESS,
+    )
+    .with_reset_pin(common::LoggingPin(log.clone()))
+    .with_delay(common::LoggingDelay(log.clone()));
+
+    pca.reinit().unwrap();
+
+    assert_eq!(
+        *log.borrow(),
+        [
+            "pin low",
+            "delay 1000ns",
+            "pin high",
+            "delay 1000ns",
+            "write 0x70 [0]",
+            "read 0x70"
+        ]
+    );
+    assert_eq!(pca.cached_mask(), Some(0));
+    bus.done();
+}
+
+#[test]
+fn reinit_disables_sticky_channels() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![0]),
+                Transaction::read(BASE_ADDRESS, vec![0]),
+                Transaction::write(BASE_ADDRESS, vec![0b1000_0001]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    pca.set_sticky_channels(1 << 7);
+
+    pca.reinit().unwrap();
+    assert_eq!(pca.cached_mask(), Some(0));
+    // The next select enables the sticky channels again.
+    drop(pca.select_single(0).unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn reinit_without_reset_pin_reports_stuck_selection() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::read(BASE_ADDRESS, vec![1 << 3]),
+    ]);
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(matches!(
+        pca.reinit(),
+        Err(pca9548a::Error::VerifyFailed {
+            expected: 0,
+            actual: 0b1000
+        })
+    ));
+    assert_eq!(pca.cached_mask(), Some(1 << 3));
+    bus.done();
+}
+
+#[test]
+fn shared_subbus_is_used_through_a_copy() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+                Transaction::write(0x43, vec![2]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+    let subbus = pca.single_subbus(6);
+
+    fn write_through(
+        shared: &pca9548a::SubBus<'_, std::sync::Mutex<Mock>>,
+        address: u8,
+        value: u8,
+    ) {
+        let mut bus = *shared;
+        bus.write(address, &[value]).unwrap();
+    }
+    write_through(&subbus, 0x42, 1);
+    write_through(&subbus, 0x43, 2);
+
+    bus.done();
+}
+
+#[test]
+fn inverted_mask_is_inverted_on_the_bus_only() {
+    let selected = common::after_drop(1 << 2);
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![!(1 << 2)]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop_as(BASE_ADDRESS, !0),
+            vec![Transaction::read(BASE_ADDRESS, vec![!selected])],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_inverted_mask();
+
+    pca.single_subbus(2).write(0x42, &[1]).unwrap();
+    assert_eq!(pca.cached_mask(), Some(selected));
+    assert_eq!(pca.read_mask().unwrap(), selected);
+
+    bus.done();
+}
+
+#[test]
+fn detect_crosstalk_flags_unexpected_channels() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let mut expectations = vec![
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(0x48, vec![]).with_error(nack),
+    ];
+    for id in 0..4 {
+        expectations.push(Transaction::write(BASE_ADDRESS, vec![1 << id]));
+        // The device is on channel 1, channel 3 is shorted to it.
+        let probe = Transaction::write(0x48, vec![]);
+        expectations.push(if id == 1 || id == 3 {
+            probe
+        } else {
+            probe.with_error(nack)
+        });
+    }
+    expectations.push(Transaction::write(BASE_ADDRESS, vec![0]));
+    let mut bus = Mock::new(&expectations);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_channel_count(4);
+
+    let report = pca.detect_crosstalk(0x48).unwrap();
+    assert_eq!(report.responding, 0b1010);
+    assert!(!report.responds_deselected);
+    assert!(!report.is_clean());
+    assert_eq!(report.unexpected(1), 0b1000);
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn swap_bus_returns_the_old_bus_and_forgets_the_cache() {
+    let first = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 0])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let mut second = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 1])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let mut pca = Pca9548a::<std::sync::Mutex<_>>::new(first, BASE_ADDRESS);
+
+    drop(pca.select_mask(1 << 0).unwrap());
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(1 << 0)));
+
+    let mut old = pca.swap_bus(second.clone());
+    assert_eq!(pca.cached_mask(), None);
+    drop(pca.select_mask(1 << 1).unwrap());
+
+    old.done();
+    second.done();
+}
+
+#[test]
+fn inner_mutex_locks_the_bus_directly() {
+    use embedded_hal::i2c::I2c;
+
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 3])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(0x42, vec![1])],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    drop(pca.select_single(3).unwrap());
+    let mutex = pca.inner_mutex();
+    assert!(!mutex.is_poisoned());
+    mutex.lock().unwrap().write(0x42, &[1]).unwrap();
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(1 << 3)));
+
+    bus.done();
+}
+
+#[test]
+fn select_mask_prev_returns_the_register_before_the_select() {
+    use embedded_hal::i2c::I2c;
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::read(BASE_ADDRESS, vec![0b1001]),
+                Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![0b1001])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let (previous, mut guard) = pca.select_mask_prev(1 << 6).unwrap();
+    assert_eq!(previous, 0b1001);
+    guard.write(0x42, &[1]).unwrap();
+    drop(guard);
+    drop(pca.select_mask(previous).unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn parent_bus_forwards_without_selecting() {
+    use embedded_hal::i2c::{I2c, Operation};
+
+    let mut bus = Mock::new(&[
+        Transaction::write(0x20, vec![1]),
+        Transaction::transaction_start(0x21),
+        Transaction::write(0x21, vec![2]),
+        Transaction::read(0x21, vec![3]),
+        Transaction::transaction_end(0x21),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let mut parent = pca.parent_bus();
+    parent.write(0x20, &[1]).unwrap();
+    let mut read = [0];
+    parent
+        .transaction(
+            0x21,
+            &mut [Operation::Write(&[2]), Operation::Read(&mut read)],
+        )
+        .unwrap();
+    assert_eq!(read, [3]);
+    assert_eq!(pca.cached_mask(), None);
+
+    bus.done();
+}
+
+#[test]
+fn select_all_and_deselect_write_the_full_and_empty_mask() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(
+                BASE_ADDRESS,
+                vec![pca9548a::ALL_CHANNELS],
+            )],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(
+                BASE_ADDRESS,
+                vec![pca9548a::NO_CHANNELS],
+            )],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    drop(pca.select_all().unwrap());
+    pca.deselect().unwrap();
+    assert_eq!(pca.cached_mask(), Some(0));
+
+    bus.done();
+}
+
+#[test]
+fn switch_to_skips_the_write_when_already_on_the_channel() {
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                // Unknown cache: always written.
+                Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+                Transaction::write(BASE_ADDRESS, vec![1 << 5]),
+                // Channel 2 plus others is not "only channel 2".
+                Transaction::write(BASE_ADDRESS, vec![(1 << 2) | (1 << 3)]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 2])],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(pca.switch_to(Channel::C2).unwrap());
+    assert!(!pca.switch_to(Channel::C2).unwrap());
+    assert!(pca.switch_to(Channel::C5).unwrap());
+    drop(pca.select_mask((1 << 2) | (1 << 3)).unwrap());
+    assert!(pca.switch_to(Channel::C2).unwrap());
+    assert!(!pca.switch_to(Channel::C2).unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn min_select_interval_delays_a_too_soon_select() {
+    use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+    use pca9548a::MinSelectInterval;
+    use std::{cell::Cell, rc::Rc};
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 2]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+    ]);
+    // Only the second select comes too soon: 200us after the first one.
+    let mut delay = CheckedDelay::new(&[DelayTransaction::blocking_delay_us(300)]);
+    let now = Rc::new(Cell::new(1_000));
+    let clock = {
+        let now = now.clone();
+        move || now.get()
+    };
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_coordinator(MinSelectInterval::new(clock, delay.clone(), 500));
+
+    // The first select is never delayed.
+    pca.switch_to(pca9548a::Channel::C0).unwrap();
+    now.set(1_200);
+    pca.switch_to(pca9548a::Channel::C1).unwrap();
+    now.set(2_000);
+    pca.switch_to(pca9548a::Channel::C2).unwrap();
+    pca.coordinator().set_min_select_interval(0);
+    pca.switch_to(pca9548a::Channel::C3).unwrap();
+
+    bus.done();
+    delay.done();
+}
+
+#[test]
+fn min_select_interval_counts_deselects_as_selects() {
+    use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
+    use pca9548a::MinSelectInterval;
+    use std::{cell::Cell, rc::Rc};
+
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+    ]);
+    // The deselect comes 100us after the select, and the next select right after the deselect.
+    let mut delay = CheckedDelay::new(&[
+        DelayTransaction::blocking_delay_us(400),
+        DelayTransaction::blocking_delay_us(500),
+    ]);
+    let now = Rc::new(Cell::new(1_000));
+    let clock = {
+        let now = now.clone();
+        move || now.get()
+    };
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_coordinator(MinSelectInterval::new(clock, delay.clone(), 500));
+
+    pca.switch_to(pca9548a::Channel::C0).unwrap();
+    now.set(1_100);
+    pca.deselect().unwrap();
+    pca.switch_to(pca9548a::Channel::C1).unwrap();
+
+    bus.done();
+    delay.done();
+}
+
+#[test]
+fn assume_mask_only_updates_the_cache() {
+    let mut bus = Mock::new(&[]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.assume_mask(1 << 4);
+    assert_eq!(pca.cached_mask(), Some(1 << 4));
+    // The assumed selection is trusted, so this needs no write either.
+    assert!(!pca.switch_to(pca9548a::Channel::C4).unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn forget_cached_mask_makes_the_next_select_write() {
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+    ]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    assert!(pca.switch_to(pca9548a::Channel::C1).unwrap());
+    assert!(!pca.switch_to(pca9548a::Channel::C1).unwrap());
+    // E.g. the mux may have been reset.
+    pca.forget_cached_mask();
+    assert_eq!(pca.cached_mask(), None);
+    assert!(pca.switch_to(pca9548a::Channel::C1).unwrap());
+
+    bus.done();
+}
+
+#[test]
+fn switch_to_rejects_channels_beyond_count_in_strict_mode() {
+    let mut bus = Mock::new(&[Transaction::write(BASE_ADDRESS, vec![0])]);
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_channel_count(4)
+        .with_strict_mask();
+
+    pca.deselect().unwrap();
+    assert!(matches!(
+        pca.switch_to(pca9548a::Channel::C6),
+        Err(pca9548a::Error::InvalidMask(_))
+    ));
+
+    bus.done();
+}
+
+#[test]
+fn select_all_respects_the_channel_count() {
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![0x0F])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![Transaction::write(BASE_ADDRESS, vec![0])],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS)
+        .with_channel_count(4)
+        .with_strict_mask();
+
+    drop(pca.select_all().unwrap());
+    pca.deselect().unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn transaction_retry_reselects_after_a_bus_error() {
+    use common::FlakyBus;
+    use embedded_hal::i2c::{ErrorKind, Operation};
+
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1 << 4])],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 4]),
+                Transaction::transaction_start(0x42),
+                Transaction::write(0x42, vec![7]),
+                Transaction::transaction_end(0x42),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(
+        FlakyBus::new(bus.clone(), &[ErrorKind::Bus]),
+        BASE_ADDRESS,
+    );
+
+    pca.transaction_retry(
+        pca9548a::Channel::C4,
+        0x42,
+        &mut [Operation::Write(&[7])],
+        3,
+    )
+    .unwrap();
+
+    bus.done();
+}
+
+#[test]
+fn transaction_retry_returns_a_nack_right_away() {
+    use common::FlakyBus;
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource, Operation};
+
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let mut bus = Mock::new(
+        &[
+            vec![Transaction::write(BASE_ADDRESS, vec![1])],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca =
+        Pca9548a::<std::sync::Mutex<_>>::new(FlakyBus::new(bus.clone(), &[nack]), BASE_ADDRESS);
+
+    let result = pca.transaction_retry(
+        pca9548a::Channel::C0,
+        0x42,
+        &mut [Operation::Write(&[7])],
+        3,
+    );
+    assert!(matches!(result, Err(pca9548a::Error::Bus(e)) if e == nack));
+
+    bus.done();
+}
+
+#[test]
+fn scan_for_muxes_finds_the_acknowledging_addresses() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+    use pca9548a::{possible_addresses, scan_for_muxes};
+
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let expectations: Vec<_> = possible_addresses()
+        .map(|address| match address {
+            0x71 | 0x76 => Transaction::write(address, vec![]),
+            _ => Transaction::write(address, vec![]).with_error(nack),
+        })
+        .collect();
+    let mut bus = Mock::new(&expectations);
+
+    assert_eq!(scan_for_muxes(&mut bus).unwrap(), [0x71, 0x76]);
+    assert!(possible_addresses().eq(0x70..=0x77));
+
+    bus.done();
+}
+
+#[test]
+fn with_lock_selects_several_channels_under_one_lock() {
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+                Transaction::write(0x42, vec![0]),
+                Transaction::write(BASE_ADDRESS, vec![1 << 3]),
+                Transaction::write(0x42, vec![3]),
+                Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+                Transaction::write(0x42, vec![1]),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<common::TestMutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    let written = pca
+        .with_lock(|session| {
+            for id in [0, 3, 1] {
+                session.select_single(id).unwrap();
+                session.write(0x42, &[id]).unwrap();
+            }
+            3
+        })
+        .unwrap();
+    assert_eq!(written, 3);
+    assert_eq!(common::lock_count(), 1);
+    assert_eq!(pca.cached_mask(), Some(common::after_drop(1 << 1)));
+
+    bus.done();
+}
+
+#[test]
+fn detect_crosstalk_ignores_sticky_channels() {
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+    let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+    let mut bus = Mock::new(&[
+        Transaction::write(BASE_ADDRESS, vec![0]),
+        Transaction::write(0x48, vec![]).with_error(nack),
+        Transaction::write(BASE_ADDRESS, vec![1 << 0]),
+        Transaction::write(0x48, vec![]),
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+        Transaction::write(0x48, vec![]).with_error(nack),
+        // The final deselect keeps the sticky channel.
+        Transaction::write(BASE_ADDRESS, vec![1 << 1]),
+    ]);
+    let mut pca =
+        Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS).with_channel_count(2);
+    pca.set_sticky_channels(1 << 1);
+
+    let report = pca.detect_crosstalk(0x48).unwrap();
+    assert_eq!(report.responding, 1 << 0);
+    assert!(report.is_clean());
+    assert_eq!(pca.cached_mask(), Some(1 << 1));
+
+    bus.done();
+}
+
+#[test]
+fn mask_taking_methods_accept_channel_lists() {
+    use embedded_hal::i2c::Operation;
+    use pca9548a::Channel;
+
+    let mut bus = Mock::new(
+        &[
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![0b0000_0101]),
+                Transaction::transaction_start(0x42),
+                Transaction::write(0x42, vec![1]),
+                Transaction::transaction_end(0x42),
+            ],
+            common::deselect_on_drop(BASE_ADDRESS),
+            vec![
+                Transaction::write(BASE_ADDRESS, vec![1 << 6]),
+                Transaction::write(0x42, vec![2]),
+                // Restores the selection from before the temporary one.
+                Transaction::write(BASE_ADDRESS, vec![common::after_drop(0b0000_0101)]),
+            ],
+        ]
+        .concat(),
+    );
+    let pca = Pca9548a::<std::sync::Mutex<_>>::new(bus.clone(), BASE_ADDRESS);
+
+    pca.transaction_with_mask(
+        [Channel::C0, Channel::C2],
+        0x42,
+        &mut [Operation::Write(&[1])],
+    )
+    .unwrap();
+    pca.with_temporary_mask(Channel::C6, |bus| bus.write(0x42, &[2]))
+        .unwrap()
+        .unwrap();
+
+    bus.done();
+}
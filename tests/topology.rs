@@ -0,0 +1,56 @@
+#![cfg(feature = "postcard")]
+
+use pca9548a::{address, MuxTopology};
+
+fn board() -> MuxTopology<12, 4> {
+    let mut topology = MuxTopology::new(address(true, false, true));
+    topology.channels[0].label = Some("temperature".try_into().unwrap());
+    topology.channels[0].devices.push(0x48).unwrap();
+    topology.channels[0].devices.push(0x49).unwrap();
+    topology.channels[7].label = Some("eeprom".try_into().unwrap());
+    topology.channels[7].devices.push(0x50).unwrap();
+    topology
+}
+
+#[test]
+fn topology_round_trip() {
+    let topology = board();
+
+    let mut buf = [0; 128];
+    let bytes = topology.to_slice(&mut buf).unwrap();
+    let loaded = MuxTopology::<12, 4>::from_bytes(bytes).unwrap();
+
+    assert_eq!(loaded, topology);
+    assert_eq!(loaded.address, 0x75);
+    assert_eq!(loaded.channel_of(0x49), Some(0));
+    assert_eq!(loaded.channel_of(0x50), Some(7));
+    assert_eq!(loaded.channel_of(0x51), None);
+
+    let labels = loaded.labels();
+    assert_eq!(labels.channel_label(0), Some("temperature"));
+    assert_eq!(labels.channel_label(3), None);
+}
+
+#[test]
+fn empty_topology_encoding() {
+    let mut buf = [0; 32];
+    let bytes = MuxTopology::<4, 4>::new(0x70).to_slice(&mut buf).unwrap();
+
+    // The address, then "no label" and "no devices" for each channel.
+    let mut expected = vec![0x70];
+    expected.extend([0, 0].repeat(pca9548a::CHANNELS));
+    assert_eq!(bytes, &expected[..]);
+}
+
+#[test]
+fn topology_rejects_oversized_entries() {
+    let mut buf = [0; 128];
+    let bytes = board().to_slice(&mut buf).unwrap();
+
+    // "temperature" does not fit into a label of 8 bytes.
+    assert!(MuxTopology::<8, 4>::from_bytes(bytes).is_err());
+    // Channel 0 lists two devices.
+    assert!(MuxTopology::<12, 1>::from_bytes(bytes).is_err());
+    // Truncated input.
+    assert!(MuxTopology::<12, 4>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+}